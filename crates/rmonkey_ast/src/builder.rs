@@ -0,0 +1,457 @@
+//! Helpers for building ASTs in Rust code.
+//!
+//! ```
+//! use rmonkey_ast::builder::*;
+//!
+//! let program = program([
+//!     let_("add", func(["x", "y"], [return_(ident("x").add(ident("y")))])),
+//!     expr(ident("add").call([int(1), int(2)])),
+//! ]);
+//! assert_eq!(program.stmts.len(), 2);
+//! ```
+//!
+//! Every tree built here has the same shape the parser produces for the
+//! equivalent source, so printing it with `rmonkey_fmt::codegen` and parsing
+//! the result gives back an equal tree. To keep that promise the helpers
+//! panic on input that has no source representation, such as an identifier
+//! that is a keyword or a string containing `"`.
+
+use crate::{
+    operator::{Infix, Prefix},
+    pattern::Pattern,
+    Catch, Expr, MatchArm, Program, Stmt,
+};
+
+const KEYWORDS: [&str; 16] = [
+    "let", "fn", "true", "false", "if", "else", "return", "import", "export", "as", "throw", "try",
+    "catch", "finally", "match", "macro",
+];
+
+pub fn program(stmts: impl IntoIterator<Item = Stmt>) -> Program {
+    Program::new(stmts.into_iter().collect())
+}
+
+pub fn let_(name: &str, value: impl Into<Expr>) -> Stmt {
+    Stmt::LetStmt {
+        export: false,
+        name: bind(name),
+        ty: None,
+        value: value.into(),
+    }
+}
+
+/// `let [a, b] = value`, destructuring the value
+pub fn let_pattern(pattern: Pattern, value: impl Into<Expr>) -> Stmt {
+    Stmt::LetStmt {
+        export: false,
+        name: pattern,
+        ty: None,
+        value: value.into(),
+    }
+}
+
+pub fn export_let(name: &str, value: impl Into<Expr>) -> Stmt {
+    Stmt::LetStmt {
+        export: true,
+        name: bind(name),
+        ty: None,
+        value: value.into(),
+    }
+}
+
+/// # Panics
+/// if `path` contains `"` or `alias` is not a valid identifier
+pub fn import(path: &str, alias: &str) -> Stmt {
+    assert!(!path.contains('"'), "string contains `\"`: {path}");
+    Stmt::Import {
+        path: path.to_string(),
+        alias: ident(alias).to_string(),
+    }
+}
+
+pub fn return_(value: impl Into<Expr>) -> Stmt {
+    Stmt::ReturnStmt(value.into())
+}
+
+pub fn throw(value: impl Into<Expr>) -> Stmt {
+    Stmt::Throw(value.into())
+}
+
+pub fn expr(value: impl Into<Expr>) -> Stmt {
+    Stmt::ExprStmt(value.into())
+}
+
+/// # Panics
+/// if `name` is not a valid identifier or is a keyword
+pub fn ident(name: &str) -> Expr {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphabetic() || c == '_')
+        && !KEYWORDS.contains(&name);
+    assert!(valid, "invalid identifier: `{name}`");
+    Expr::Ident(name.to_string())
+}
+
+/// negative values are built as `-n`, the way the parser reads them.
+///
+/// # Panics
+/// if `val` is `i64::MIN`, which can't be written in source
+pub fn int(val: i64) -> Expr {
+    if val < 0 {
+        let abs = val
+            .checked_neg()
+            .unwrap_or_else(|| panic!("integer out of range: {val}"));
+        return Expr::PrefixExpr {
+            op: Prefix::Minus,
+            right: Box::new(Expr::IntLiteral(abs)),
+        };
+    }
+    Expr::IntLiteral(val)
+}
+
+pub fn boolean(val: bool) -> Expr {
+    Expr::BoolLiteral(val)
+}
+
+/// # Panics
+/// if `val` contains `"`, since string literals have no escapes
+pub fn string(val: &str) -> Expr {
+    assert!(
+        !val.contains('"'),
+        "string literal can't contain `\"`: {val}"
+    );
+    Expr::StringLiteral(val.to_string())
+}
+
+pub fn array(elements: impl IntoIterator<Item = Expr>) -> Expr {
+    Expr::Array {
+        elements: elements.into_iter().collect(),
+    }
+}
+
+pub fn hash(pairs: impl IntoIterator<Item = (Expr, Expr)>) -> Expr {
+    Expr::HashLiteral {
+        pairs: pairs.into_iter().collect(),
+    }
+}
+
+pub fn func<'a>(
+    params: impl IntoIterator<Item = &'a str>,
+    body: impl IntoIterator<Item = Stmt>,
+) -> Expr {
+    Expr::Func {
+        params: params.into_iter().map(bind).collect(),
+        param_types: Vec::new(),
+        ret: None,
+        body: Box::new(block(body)),
+    }
+}
+
+/// a function with parameters that may destructure their arguments
+pub fn func_patterns(
+    params: impl IntoIterator<Item = Pattern>,
+    body: impl IntoIterator<Item = Stmt>,
+) -> Expr {
+    Expr::Func {
+        params: params.into_iter().collect(),
+        param_types: Vec::new(),
+        ret: None,
+        body: Box::new(block(body)),
+    }
+}
+
+pub fn macro_<'a>(
+    params: impl IntoIterator<Item = &'a str>,
+    body: impl IntoIterator<Item = Stmt>,
+) -> Expr {
+    Expr::Macro {
+        params: params
+            .into_iter()
+            .map(|name| bind(name).to_string())
+            .collect(),
+        body: Box::new(block(body)),
+    }
+}
+
+pub fn if_(condition: impl Into<Expr>, consequence: impl IntoIterator<Item = Stmt>) -> Expr {
+    Expr::If {
+        condition: Box::new(condition.into()),
+        consequence: Box::new(block(consequence)),
+        alternative: None,
+    }
+}
+
+pub fn if_else(
+    condition: impl Into<Expr>,
+    consequence: impl IntoIterator<Item = Stmt>,
+    alternative: impl IntoIterator<Item = Stmt>,
+) -> Expr {
+    Expr::If {
+        condition: Box::new(condition.into()),
+        consequence: Box::new(block(consequence)),
+        alternative: Some(Box::new(block(alternative))),
+    }
+}
+
+pub fn try_catch(
+    body: impl IntoIterator<Item = Stmt>,
+    param: &str,
+    handler: impl IntoIterator<Item = Stmt>,
+) -> Expr {
+    Expr::Try {
+        body: Box::new(block(body)),
+        catch: Some(Box::new(Catch {
+            param: ident(param).to_string(),
+            body: Box::new(block(handler)),
+        })),
+        finally: None,
+    }
+}
+
+pub fn try_finally(
+    body: impl IntoIterator<Item = Stmt>,
+    finally: impl IntoIterator<Item = Stmt>,
+) -> Expr {
+    Expr::Try {
+        body: Box::new(block(body)),
+        catch: None,
+        finally: Some(Box::new(block(finally))),
+    }
+}
+
+pub fn try_catch_finally(
+    body: impl IntoIterator<Item = Stmt>,
+    param: &str,
+    handler: impl IntoIterator<Item = Stmt>,
+    finally: impl IntoIterator<Item = Stmt>,
+) -> Expr {
+    Expr::Try {
+        body: Box::new(block(body)),
+        catch: Some(Box::new(Catch {
+            param: ident(param).to_string(),
+            body: Box::new(block(handler)),
+        })),
+        finally: Some(Box::new(block(finally))),
+    }
+}
+
+pub fn match_(value: impl Into<Expr>, arms: impl IntoIterator<Item = MatchArm>) -> Expr {
+    Expr::Match {
+        value: Box::new(value.into()),
+        arms: arms.into_iter().collect(),
+    }
+}
+
+pub fn arm(pattern: Pattern, body: impl Into<Expr>) -> MatchArm {
+    MatchArm {
+        pattern,
+        guard: None,
+        body: body.into(),
+    }
+}
+
+pub fn arm_if(pattern: Pattern, guard: impl Into<Expr>, body: impl Into<Expr>) -> MatchArm {
+    MatchArm {
+        pattern,
+        guard: Some(guard.into()),
+        body: body.into(),
+    }
+}
+
+pub fn wildcard() -> Pattern {
+    Pattern::Wildcard
+}
+
+/// # Panics
+/// if `name` is not a valid identifier or is `_`, which is read as a
+/// wildcard
+pub fn bind(name: &str) -> Pattern {
+    assert!(name != "_", "`_` is a wildcard, not a binding");
+    Pattern::Ident(ident(name).to_string())
+}
+
+/// # Panics
+/// if `value` is not an integer, boolean or string literal
+pub fn literal(value: impl Into<Expr>) -> Pattern {
+    let value = value.into();
+    let valid = match &value {
+        Expr::IntLiteral(_) | Expr::BoolLiteral(_) | Expr::StringLiteral(_) => true,
+        Expr::PrefixExpr { op, right } => {
+            *op == Prefix::Minus && matches!(**right, Expr::IntLiteral(_))
+        }
+        _ => false,
+    };
+    assert!(valid, "not a literal pattern: {value}");
+    Pattern::Literal(value)
+}
+
+pub fn array_pattern(
+    elements: impl IntoIterator<Item = Pattern>,
+    rest: Option<Pattern>,
+) -> Pattern {
+    Pattern::Array {
+        elements: elements.into_iter().collect(),
+        rest: rest.map(Box::new),
+    }
+}
+
+pub fn hash_pattern(pairs: impl IntoIterator<Item = (Expr, Pattern)>) -> Pattern {
+    Pattern::Hash {
+        pairs: pairs.into_iter().collect(),
+    }
+}
+
+fn block(stmts: impl IntoIterator<Item = Stmt>) -> Stmt {
+    Stmt::BlockStmt {
+        stmts: stmts.into_iter().collect(),
+    }
+}
+
+impl From<i64> for Expr {
+    fn from(val: i64) -> Self {
+        int(val)
+    }
+}
+
+impl From<bool> for Expr {
+    fn from(val: bool) -> Self {
+        boolean(val)
+    }
+}
+
+impl From<Expr> for Stmt {
+    fn from(val: Expr) -> Self {
+        Stmt::ExprStmt(val)
+    }
+}
+
+#[allow(clippy::should_implement_trait)]
+impl Expr {
+    pub fn add(self, right: impl Into<Expr>) -> Expr {
+        self.infix(Infix::Plus, right)
+    }
+
+    pub fn sub(self, right: impl Into<Expr>) -> Expr {
+        self.infix(Infix::Minus, right)
+    }
+
+    pub fn mul(self, right: impl Into<Expr>) -> Expr {
+        self.infix(Infix::Asterisk, right)
+    }
+
+    pub fn div(self, right: impl Into<Expr>) -> Expr {
+        self.infix(Infix::Slash, right)
+    }
+
+    pub fn equals(self, right: impl Into<Expr>) -> Expr {
+        self.infix(Infix::Eq, right)
+    }
+
+    pub fn not_equals(self, right: impl Into<Expr>) -> Expr {
+        self.infix(Infix::NotEq, right)
+    }
+
+    pub fn lt(self, right: impl Into<Expr>) -> Expr {
+        self.infix(Infix::Lt, right)
+    }
+
+    pub fn gt(self, right: impl Into<Expr>) -> Expr {
+        self.infix(Infix::Gt, right)
+    }
+
+    pub fn neg(self) -> Expr {
+        Expr::PrefixExpr {
+            op: Prefix::Minus,
+            right: Box::new(self),
+        }
+    }
+
+    pub fn not(self) -> Expr {
+        Expr::PrefixExpr {
+            op: Prefix::Bang,
+            right: Box::new(self),
+        }
+    }
+
+    pub fn call(self, args: impl IntoIterator<Item = Expr>) -> Expr {
+        Expr::Call {
+            callee: Box::new(self),
+            args: args.into_iter().collect(),
+        }
+    }
+
+    /// `self?`
+    pub fn propagate(self) -> Expr {
+        Expr::Propagate {
+            value: Box::new(self),
+        }
+    }
+
+    pub fn index(self, index: impl Into<Expr>) -> Expr {
+        Expr::IndexExpr {
+            left: Box::new(self),
+            index: Box::new(index.into()),
+        }
+    }
+
+    /// `self[start:end:step]`
+    pub fn slice(self, start: Option<Expr>, end: Option<Expr>, step: Option<Expr>) -> Expr {
+        Expr::Slice {
+            left: Box::new(self),
+            start: start.map(Box::new),
+            end: end.map(Box::new),
+            step: step.map(Box::new),
+        }
+    }
+
+    fn infix(self, op: Infix, right: impl Into<Expr>) -> Expr {
+        Expr::InfixExpr {
+            left: Box::new(self),
+            right: Box::new(right.into()),
+            op,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let case = [
+            (let_("x", int(1).add(ident("y"))), "let x = (1 + y)"),
+            (expr(int(-5).mul(2)), "((-5) * 2)"),
+            (expr(ident("f").call([int(1), boolean(true)])), "f(1, true)"),
+            (expr(array([string("a")]).index(0)), r#"(["a"][0])"#),
+            (expr(ident("a").slice(Some(int(1)), None, None)), "(a[1:])"),
+            (
+                expr(ident("a").slice(None, Some(int(-1)), Some(int(2)))),
+                "(a[:(-1):2])",
+            ),
+            (expr(hash([(string("k"), int(1))])), r#"{"k": 1}"#),
+            (
+                let_("id", func(["x"], [return_(ident("x"))])),
+                "let id = fn(x){return x}",
+            ),
+            (
+                expr(if_else(ident("a").lt(1), [expr(1)], [expr(2)])),
+                "if((a < 1)){1}else{2}",
+            ),
+        ];
+        for (stmt, expected) in case.iter() {
+            assert_eq!(stmt.to_string(), *expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid identifier: `let`")]
+    fn test_keyword_ident() {
+        ident("let");
+    }
+
+    #[test]
+    #[should_panic(expected = "string literal can't contain")]
+    fn test_string_with_quote() {
+        string("a\"b");
+    }
+}
@@ -0,0 +1,322 @@
+//! Stable JSON encoding of a [`Program`].
+//!
+//! The document is versioned so that external tools can detect a schema they
+//! don't understand instead of silently misreading it:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "body": [
+//!     {
+//!       "type": "LetStatement",
+//!       "span": { "start": 0, "end": 10 },
+//!       "name": { "type": "Identifier", "value": "x" },
+//!       "value": { "type": "IntegerLiteral", "value": 1 }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Every node is an object tagged by `type`. Node names are fixed by the
+//! `#[serde(rename)]` attributes on [`Stmt`] and [`Expr`], so renaming a Rust
+//! variant doesn't change the schema. Nodes wrapping a single value
+//! (literals, identifiers, `ReturnStatement`, `ThrowStatement` and
+//! `ExpressionStatement`) store it under `value`. Operators are written as
+//! their source symbol (`"+"`, `"=="`, `"!"`, ...).
+//!
+//! Type annotations are stored under `ty` on `LetStatement`, and under
+//! `param_types` and `ret` on `FunctionLiteral`, tagged by `type` like nodes
+//! (`{ "type": "array", "element": { "type": "int" } }`). They are omitted
+//! when absent.
+//!
+//! `export let` sets `"export": true` on `LetStatement`, which is omitted
+//! when false. `import "m.monkey" as m` is an `ImportStatement` with a
+//! string `path` and an `Identifier` `alias`.
+//!
+//! `value?` is a `PropagateExpression` with the operand under `value`.
+//!
+//! `left[start:end:step]` is a `SliceExpression` with the sliced value under
+//! `left` and optional `start`, `end` and `step` expressions, each omitted
+//! when left out of the source.
+//!
+//! A `TryExpression` has a `body` block, an optional `catch` object with an
+//! `Identifier` `param` and a `body` block, and an optional `finally` block.
+//!
+//! A `MatchExpression` has a `value` and a list of `arms`, each an object
+//! with a `pattern`, an optional `guard` and a `body`. Patterns are tagged
+//! by `type` like nodes: `WildcardPattern`, `Identifier` for a name,
+//! `LiteralPattern` with the literal under `value`, `ArrayPattern` with
+//! `elements` and an optional `rest` pattern, and `HashPattern` with `pairs`
+//! of a literal key and a pattern. The `name` of a `LetStatement` and the
+//! `params` of a `FunctionLiteral` are patterns too, usually `Identifier`s.
+//!
+//! A `MacroLiteral` has `params`, a list of names as strings, and a `body`
+//! block.
+//!
+//! `span` is a byte range into the source text and is only present on
+//! top-level statements produced by the parser. It is optional on input,
+//! but must then be given on every statement or on none.
+
+use serde::{de::Error, Deserialize, Serialize};
+
+use crate::{span::Span, Program, Stmt};
+
+/// version of the JSON schema written by [`to_json`].
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Document {
+    version: u32,
+    body: Vec<Node>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Node {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span: Option<Span>,
+    #[serde(flatten)]
+    stmt: Stmt,
+}
+
+pub fn to_json(program: &Program) -> serde_json::Result<String> {
+    serde_json::to_string(&document(program))
+}
+
+pub fn to_json_pretty(program: &Program) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&document(program))
+}
+
+pub fn from_json(json: &str) -> serde_json::Result<Program> {
+    let doc: Document = serde_json::from_str(json)?;
+    if doc.version != SCHEMA_VERSION {
+        return Err(serde_json::Error::custom(format!(
+            "unsupported schema version: {}, expected {SCHEMA_VERSION}",
+            doc.version
+        )));
+    }
+
+    // spans are either given for every statement or not at all, since
+    // `Program::spans` is parallel to the statements
+    let spans: Vec<Span> = doc.body.iter().filter_map(|node| node.span).collect();
+    if !spans.is_empty() && spans.len() != doc.body.len() {
+        return Err(serde_json::Error::custom(format!(
+            "span given on {} of {} statements",
+            spans.len(),
+            doc.body.len()
+        )));
+    }
+    let stmts = doc.body.into_iter().map(|node| node.stmt).collect();
+    Ok(Program::with_spans(stmts, spans))
+}
+
+fn document(program: &Program) -> Document {
+    let body = program
+        .stmts
+        .iter()
+        .enumerate()
+        .map(|(i, stmt)| Node {
+            span: program.spans.get(i).copied(),
+            stmt: stmt.clone(),
+        })
+        .collect();
+    Document {
+        version: SCHEMA_VERSION,
+        body,
+    }
+}
+
+/// (de)serialize a single-field variant as `{ "value": ... }` so that it can
+/// live inside an internally tagged enum.
+pub(crate) mod value {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize)]
+    struct Wrap<'a, T> {
+        value: &'a T,
+    }
+
+    #[derive(Deserialize)]
+    struct Unwrap<T> {
+        value: T,
+    }
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &T, s: S) -> Result<S::Ok, S::Error> {
+        Wrap { value }.serialize(s)
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<T, D::Error> {
+        Unwrap::deserialize(d).map(|w| w.value)
+    }
+}
+
+/// (de)serialize a name as an `Identifier` node, rejecting any other node
+pub(crate) mod ident {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::Expr;
+
+    pub fn serialize<S: Serializer>(name: &str, s: S) -> Result<S::Ok, S::Error> {
+        Expr::Ident(name.to_string()).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+        match Expr::deserialize(d)? {
+            Expr::Ident(name) => Ok(name),
+            expr => Err(D::Error::custom(format!(
+                "expected an Identifier, got {expr}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{operator::Infix, pattern::Pattern, Expr};
+
+    fn sample() -> Program {
+        Program::with_spans(
+            vec![
+                Stmt::LetStmt {
+                    export: false,
+                    name: Pattern::Ident("x".to_string()),
+                    ty: None,
+                    value: Expr::InfixExpr {
+                        left: Box::new(Expr::IntLiteral(1)),
+                        right: Box::new(Expr::StringLiteral("a".to_string())),
+                        op: Infix::Plus,
+                    },
+                },
+                Stmt::ReturnStmt(Expr::Ident("x".to_string())),
+            ],
+            vec![Span::new(0, 18), Span::new(19, 28)],
+        )
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = to_json(&sample()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"body":[{"span":{"start":0,"end":18},"type":"LetStatement","name":{"type":"Identifier","value":"x"},"value":{"type":"InfixExpression","left":{"type":"IntegerLiteral","value":1},"right":{"type":"StringLiteral","value":"a"},"op":"+"}},{"span":{"start":19,"end":28},"type":"ReturnStatement","value":{"type":"Identifier","value":"x"}}]}"#
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = sample();
+        let decoded = from_json(&to_json(&program).unwrap()).unwrap();
+        assert_eq!(decoded.stmts, program.stmts);
+        assert_eq!(decoded.spans, program.spans);
+    }
+
+    #[test]
+    fn test_from_json_without_span() {
+        let json = r#"{"version":1,"body":[{"type":"ExpressionStatement","value":{"type":"BooleanLiteral","value":true}}]}"#;
+        let program = from_json(json).unwrap();
+        assert_eq!(program.stmts, vec![Stmt::ExprStmt(Expr::BoolLiteral(true))]);
+        assert!(program.spans.is_empty());
+    }
+
+    #[test]
+    fn test_from_json_partial_span() {
+        let json = r#"{"version":1,"body":[{"span":{"start":0,"end":5},"type":"ExpressionStatement","value":{"type":"BooleanLiteral","value":true}},{"type":"ExpressionStatement","value":{"type":"BooleanLiteral","value":false}}]}"#;
+        let err = from_json(json).unwrap_err();
+        assert_eq!(err.to_string(), "span given on 1 of 2 statements");
+    }
+
+    #[test]
+    fn test_match() {
+        use crate::builder::*;
+
+        let program = program([expr(match_(
+            ident("x"),
+            [
+                arm(literal(-1), 1),
+                arm_if(array_pattern([bind("h")], Some(wildcard())), ident("h"), 2),
+                arm(hash_pattern([(string("k"), literal(true))]), 3),
+            ],
+        ))]);
+        let json = to_json(&program).unwrap();
+        assert!(json.contains(r#"{"type":"ArrayPattern","elements":[{"type":"Identifier","value":"h"}],"rest":{"type":"WildcardPattern"}}"#), "{json}");
+        assert_eq!(from_json(&json).unwrap().stmts, program.stmts);
+    }
+
+    #[test]
+    fn test_macro() {
+        use crate::builder::*;
+
+        let program = program([let_("m", macro_(["a"], [expr(ident("a"))]))]);
+        let json = to_json(&program).unwrap();
+        assert!(
+            json.contains(r#"{"type":"MacroLiteral","params":["a"],"body":"#),
+            "{json}"
+        );
+        assert_eq!(from_json(&json).unwrap().stmts, program.stmts);
+    }
+
+    #[test]
+    fn test_slice() {
+        use crate::builder::*;
+
+        let program = program([expr(ident("a").slice(None, Some(int(2)), None))]);
+        let json = to_json(&program).unwrap();
+        assert!(
+            json.contains(r#"{"type":"SliceExpression","left":{"type":"Identifier","value":"a"},"end":{"type":"IntegerLiteral","value":2}}"#),
+            "{json}"
+        );
+        assert_eq!(from_json(&json).unwrap().stmts, program.stmts);
+    }
+
+    #[test]
+    fn test_import() {
+        use crate::builder::*;
+
+        let program = program([import("m.monkey", "m")]);
+        let json = to_json(&program).unwrap();
+        assert!(
+            json.contains(r#""alias":{"type":"Identifier","value":"m"}"#),
+            "{json}"
+        );
+        assert_eq!(from_json(&json).unwrap().stmts, program.stmts);
+
+        let json = r#"{"version":1,"body":[{"type":"ImportStatement","path":"m.monkey","alias":{"type":"IntegerLiteral","value":1}}]}"#;
+        let err = from_json(json).unwrap_err();
+        assert!(
+            err.to_string().contains("expected an Identifier, got 1"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_catch() {
+        use crate::builder::*;
+
+        let program = program([expr(try_catch([throw(1)], "e", [expr(ident("e"))]))]);
+        let json = to_json(&program).unwrap();
+        assert!(
+            json.contains(r#""catch":{"param":{"type":"Identifier","value":"e"},"body":"#),
+            "{json}"
+        );
+        assert_eq!(from_json(&json).unwrap().stmts, program.stmts);
+
+        let json = json.replace(
+            r#"{"type":"Identifier","value":"e"},"body""#,
+            r#"{"type":"StringLiteral","value":"e"},"body""#,
+        );
+        let err = from_json(&json).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(r#"expected an Identifier, got "e""#),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let err = from_json(r#"{"version":0,"body":[]}"#).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported schema version: 0, expected 1");
+    }
+}
@@ -1,83 +1,219 @@
 use std::fmt;
 
 use operator::{Infix, Prefix};
-use serde::Serialize;
+use pattern::Pattern;
+use serde::{Deserialize, Serialize};
+use span::{BlockSpans, Span};
+use ty::TypeExpr;
 
+pub mod builder;
+pub mod json;
+pub mod modify;
 pub mod operator;
+pub mod pattern;
 pub mod precedence;
-#[derive(Debug, Serialize)]
+pub mod span;
+pub mod ty;
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Program {
     pub stmts: Vec<Stmt>,
+    /// span of each top-level statement, parallel to `stmts`.
+    /// empty when the program was not produced from source text.
+    #[serde(default)]
+    pub spans: Vec<Span>,
+    /// every block and hash literal, in source order of its `{`.
+    /// empty when the program was not produced from source text.
+    #[serde(skip)]
+    pub blocks: Vec<BlockSpans>,
+    /// span of every `//` comment, including the `//`
+    #[serde(skip)]
+    pub comments: Vec<Span>,
+    /// span of every `Expr::Ident`, in source order
+    #[serde(skip)]
+    pub idents: Vec<Span>,
 }
 
 impl Program {
     pub fn new(stmts: Vec<Stmt>) -> Self {
-        Program { stmts }
+        Program::with_spans(stmts, Vec::new())
+    }
+
+    pub fn with_spans(stmts: Vec<Stmt>, spans: Vec<Span>) -> Self {
+        Program {
+            stmts,
+            spans,
+            blocks: Vec::new(),
+            comments: Vec::new(),
+            idents: Vec::new(),
+        }
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum Stmt {
-    LetStmt { name: Expr, value: Expr },
+    #[serde(rename = "LetStatement")]
+    LetStmt {
+        /// `export let`, only at the top level of a module
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        export: bool,
+        /// a name, or a pattern destructuring the value
+        name: Pattern,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ty: Option<TypeExpr>,
+        value: Expr,
+    },
+    #[serde(rename = "ReturnStatement", with = "json::value")]
     ReturnStmt(Expr),
+    #[serde(rename = "ExpressionStatement", with = "json::value")]
     ExprStmt(Expr),
+    #[serde(rename = "BlockStatement")]
     BlockStmt { stmts: Vec<Stmt> },
+    /// `import "path" as alias`, only at the top level
+    #[serde(rename = "ImportStatement")]
+    Import {
+        path: String,
+        #[serde(with = "json::ident")]
+        alias: String,
+    },
+    #[serde(rename = "ThrowStatement", with = "json::value")]
+    Throw(Expr),
 }
 
 impl fmt::Display for Stmt {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Stmt::LetStmt { name, value } => write!(f, "let {name} = {value}"),
+            Stmt::LetStmt {
+                export,
+                name,
+                ty,
+                value,
+            } => {
+                if *export {
+                    write!(f, "export ")?;
+                }
+                match ty {
+                    Some(ty) => write!(f, "let {name}: {ty} = {value}"),
+                    None => write!(f, "let {name} = {value}"),
+                }
+            }
             Stmt::ReturnStmt(value) => write!(f, "return {value}"),
             Stmt::ExprStmt(expr) => write!(f, "{expr}"),
             Stmt::BlockStmt { stmts } => {
                 let stmts: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
                 write!(f, "{}", stmts.join("\n"))
             }
+            Stmt::Import { path, alias } => write!(f, "import \"{path}\" as {alias}"),
+            Stmt::Throw(value) => write!(f, "throw {value}"),
         }
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum Expr {
+    #[serde(rename = "Identifier", with = "json::value")]
     Ident(String),
+    #[serde(rename = "IntegerLiteral", with = "json::value")]
     IntLiteral(i64),
+    #[serde(rename = "BooleanLiteral", with = "json::value")]
     BoolLiteral(bool),
+    #[serde(rename = "StringLiteral", with = "json::value")]
     StringLiteral(String),
+    #[serde(rename = "IfExpression")]
     If {
         condition: Box<Expr>,
         consequence: Box<Stmt>,
         alternative: Option<Box<Stmt>>,
     },
-    PrefixExpr {
-        op: Prefix,
-        right: Box<Expr>,
-    },
+    #[serde(rename = "PrefixExpression")]
+    PrefixExpr { op: Prefix, right: Box<Expr> },
+    #[serde(rename = "InfixExpression")]
     InfixExpr {
         left: Box<Expr>,
         right: Box<Expr>,
         op: Infix,
     },
+    #[serde(rename = "FunctionLiteral")]
     Func {
-        params: Vec<Expr>,
+        /// names, or patterns destructuring the arguments
+        params: Vec<Pattern>,
+        /// annotation of each parameter, parallel to `params`. empty when no
+        /// parameter is annotated.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        param_types: Vec<Option<TypeExpr>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ret: Option<TypeExpr>,
         body: Box<Stmt>,
     },
-    Call {
-        callee: Box<Expr>,
-        args: Vec<Expr>,
+    #[serde(rename = "CallExpression")]
+    Call { callee: Box<Expr>, args: Vec<Expr> },
+    #[serde(rename = "ArrayLiteral")]
+    Array { elements: Vec<Expr> },
+    #[serde(rename = "IndexExpression")]
+    IndexExpr { left: Box<Expr>, index: Box<Expr> },
+    /// `left[start:end:step]`, any of whose bounds may be left out
+    #[serde(rename = "SliceExpression")]
+    Slice {
+        left: Box<Expr>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        start: Option<Box<Expr>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        end: Option<Box<Expr>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        step: Option<Box<Expr>>,
     },
-    Array {
-        elements: Vec<Expr>,
+    #[serde(rename = "HashLiteral")]
+    HashLiteral { pairs: Vec<(Expr, Expr)> },
+    /// postfix `value?`, which returns `value` from the current function
+    /// when it is an error
+    #[serde(rename = "PropagateExpression")]
+    Propagate { value: Box<Expr> },
+    /// `try { } catch (e) { } finally { }`, with a `catch`, a `finally` or
+    /// both
+    #[serde(rename = "TryExpression")]
+    Try {
+        body: Box<Stmt>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        catch: Option<Box<Catch>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        finally: Option<Box<Stmt>>,
     },
-    IndexExpr {
-        left: Box<Expr>,
-        index: Box<Expr>,
+    /// `match (value) { pattern => body, pattern if guard => body }`
+    #[serde(rename = "MatchExpression")]
+    Match {
+        value: Box<Expr>,
+        arms: Vec<MatchArm>,
     },
-    HashLiteral {
-        pairs: Vec<(Expr, Expr)>,
+    /// `macro(a, b) { }`, which is called with the code of its arguments
+    /// and returns the code to put in place of the call. defined by a
+    /// top-level `let` and expanded before the program runs.
+    #[serde(rename = "MacroLiteral")]
+    Macro {
+        params: Vec<String>,
+        body: Box<Stmt>,
     },
 }
 
+/// an arm of a `match`, taken for the first value matching `pattern` for
+/// which `guard` is truthy
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+/// the `catch (param) { body }` of a `try`
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Catch {
+    #[serde(with = "json::ident")]
+    pub param: String,
+    pub body: Box<Stmt>,
+}
+
 impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -99,17 +235,25 @@ impl fmt::Display for Expr {
             },
             Expr::PrefixExpr { op, right } => write!(f, "({op}{right})"),
             Expr::InfixExpr { left, right, op } => write!(f, "({left} {op} {right})"),
-            Expr::Func { params, body } => {
-                if params.is_empty() {
-                    return write!(f, "fn(){{{body}}}");
-                }
-                let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
-                write!(
-                    f,
-                    "fn({}){{{}}}",
-                    params.join(", ").trim_end_matches(", "),
-                    body
-                )
+            Expr::Func {
+                params,
+                param_types,
+                ret,
+                body,
+            } => {
+                let params: Vec<String> = params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| match param_types.get(i) {
+                        Some(Some(ty)) => format!("{p}: {ty}"),
+                        _ => p.to_string(),
+                    })
+                    .collect();
+                let ret = match ret {
+                    Some(ty) => format!(" -> {ty}"),
+                    None => String::new(),
+                };
+                write!(f, "fn({}){ret}{{{body}}}", params.join(", "))
             }
             Expr::Call { callee, args } => {
                 if args.is_empty() {
@@ -123,6 +267,22 @@ impl fmt::Display for Expr {
                 write!(f, "[{}]", elems.join(", ").trim_end_matches(", "))
             }
             Expr::IndexExpr { left, index } => write!(f, "({left}[{index}])"),
+            Expr::Slice {
+                left,
+                start,
+                end,
+                step,
+            } => {
+                let bound = |bound: &Option<Box<Expr>>| match bound {
+                    Some(bound) => bound.to_string(),
+                    None => String::new(),
+                };
+                write!(f, "({left}[{}:{}", bound(start), bound(end))?;
+                if let Some(step) = step {
+                    write!(f, ":{step}")?;
+                }
+                write!(f, "])")
+            }
             Expr::HashLiteral { pairs } => {
                 let mut s: Vec<String> = Vec::new();
                 for (key, val) in pairs.iter() {
@@ -130,6 +290,33 @@ impl fmt::Display for Expr {
                 }
                 write!(f, "{{{}}}", s.join(", "))
             }
+            Expr::Propagate { value } => write!(f, "({value}?)"),
+            Expr::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                write!(f, "try{{{body}}}")?;
+                if let Some(catch) = catch {
+                    let Catch { param, body } = catch.as_ref();
+                    write!(f, "catch({param}){{{body}}}")?;
+                }
+                if let Some(finally) = finally {
+                    write!(f, "finally{{{finally}}}")?;
+                }
+                Ok(())
+            }
+            Expr::Match { value, arms } => {
+                let arms: Vec<String> = arms
+                    .iter()
+                    .map(|arm| match &arm.guard {
+                        Some(guard) => format!("{} if {guard} => {}", arm.pattern, arm.body),
+                        None => format!("{} => {}", arm.pattern, arm.body),
+                    })
+                    .collect();
+                write!(f, "match({value}){{{}}}", arms.join(", "))
+            }
+            Expr::Macro { params, body } => write!(f, "macro({}){{{body}}}", params.join(", ")),
         }
     }
 }
@@ -0,0 +1,178 @@
+//! Bottom-up rewriting of the expressions of a tree, the way quoted code is
+//! filled in and macro calls are expanded.
+//!
+//! `f` sees every expression after the expressions inside it, and may
+//! replace it. Names bound by a `let`, a parameter, a `catch` or a pattern
+//! are not expressions and are left as they are.
+
+use crate::{Expr, Stmt};
+
+pub fn modify_stmts<E>(
+    stmts: &mut [Stmt],
+    f: &mut impl FnMut(&mut Expr) -> Result<(), E>,
+) -> Result<(), E> {
+    for stmt in stmts.iter_mut() {
+        modify_stmt(stmt, f)?;
+    }
+    Ok(())
+}
+
+pub fn modify_stmt<E>(
+    stmt: &mut Stmt,
+    f: &mut impl FnMut(&mut Expr) -> Result<(), E>,
+) -> Result<(), E> {
+    match stmt {
+        Stmt::LetStmt { value, .. } => modify_expr(value, f),
+        Stmt::ReturnStmt(expr) | Stmt::ExprStmt(expr) | Stmt::Throw(expr) => modify_expr(expr, f),
+        Stmt::BlockStmt { stmts } => modify_stmts(stmts, f),
+        Stmt::Import { .. } => Ok(()),
+    }
+}
+
+pub fn modify_expr<E>(
+    expr: &mut Expr,
+    f: &mut impl FnMut(&mut Expr) -> Result<(), E>,
+) -> Result<(), E> {
+    match expr {
+        Expr::Ident(_) | Expr::IntLiteral(_) | Expr::BoolLiteral(_) | Expr::StringLiteral(_) => {}
+        Expr::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            modify_expr(condition, f)?;
+            modify_stmt(consequence, f)?;
+            if let Some(alternative) = alternative {
+                modify_stmt(alternative, f)?;
+            }
+        }
+        Expr::PrefixExpr { right, .. } | Expr::Propagate { value: right } => modify_expr(right, f)?,
+        Expr::InfixExpr { left, right, .. } | Expr::IndexExpr { left, index: right } => {
+            modify_expr(left, f)?;
+            modify_expr(right, f)?;
+        }
+        Expr::Slice {
+            left,
+            start,
+            end,
+            step,
+        } => {
+            modify_expr(left, f)?;
+            for bound in [start, end, step].into_iter().flatten() {
+                modify_expr(bound, f)?;
+            }
+        }
+        Expr::Func { body, .. } | Expr::Macro { body, .. } => modify_stmt(body, f)?,
+        Expr::Call { callee, args } => {
+            modify_expr(callee, f)?;
+            for arg in args.iter_mut() {
+                modify_expr(arg, f)?;
+            }
+        }
+        Expr::Array { elements } => {
+            for element in elements.iter_mut() {
+                modify_expr(element, f)?;
+            }
+        }
+        Expr::HashLiteral { pairs } => {
+            for (key, value) in pairs.iter_mut() {
+                modify_expr(key, f)?;
+                modify_expr(value, f)?;
+            }
+        }
+        Expr::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            modify_stmt(body, f)?;
+            if let Some(catch) = catch {
+                modify_stmt(&mut catch.body, f)?;
+            }
+            if let Some(finally) = finally {
+                modify_stmt(finally, f)?;
+            }
+        }
+        Expr::Match { value, arms } => {
+            modify_expr(value, f)?;
+            for arm in arms.iter_mut() {
+                if let Some(guard) = &mut arm.guard {
+                    modify_expr(guard, f)?;
+                }
+                modify_expr(&mut arm.body, f)?;
+            }
+        }
+    }
+    f(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+    use crate::builder::*;
+
+    #[test]
+    fn test_modify() {
+        // every `1` becomes a `2`
+        let mut turn_one_into_two = |expr: &mut Expr| -> Result<(), Infallible> {
+            if *expr == Expr::IntLiteral(1) {
+                *expr = Expr::IntLiteral(2);
+            }
+            Ok(())
+        };
+        let case = [
+            (expr(int(1)), expr(int(2))),
+            (expr(int(1).add(int(1))), expr(int(2).add(int(2)))),
+            (expr(int(1).neg().index(1)), expr(int(2).neg().index(2))),
+            (
+                expr(int(1).slice(None, Some(int(1)), Some(int(1)))),
+                expr(int(2).slice(None, Some(int(2)), Some(int(2)))),
+            ),
+            (
+                expr(if_else(int(1), [return_(int(1))], [throw(int(1))])),
+                expr(if_else(int(2), [return_(int(2))], [throw(int(2))])),
+            ),
+            (
+                let_("f", func(["x"], [expr(ident("f").call([int(1)]))])),
+                let_("f", func(["x"], [expr(ident("f").call([int(2)]))])),
+            ),
+            (
+                expr(hash([(int(1), array([int(1)]))])),
+                expr(hash([(int(2), array([int(2)]))])),
+            ),
+            (
+                expr(try_catch_finally([expr(int(1))], "e", [expr(int(1))], [])),
+                expr(try_catch_finally([expr(int(2))], "e", [expr(int(2))], [])),
+            ),
+            (
+                expr(match_(int(1), [arm_if(literal(1), int(1), int(1))])),
+                expr(match_(int(2), [arm_if(literal(1), int(2), int(2))])),
+            ),
+            (
+                expr(macro_(["a"], [expr(int(1))])),
+                expr(macro_(["a"], [expr(int(2))])),
+            ),
+        ];
+        for (mut stmt, expected) in case.into_iter() {
+            modify_stmt(&mut stmt, &mut turn_one_into_two).unwrap();
+            assert_eq!(stmt, expected);
+        }
+    }
+
+    #[test]
+    fn test_modify_error() {
+        let mut stmt = expr(ident("a").add(ident("b")));
+        let mut seen = Vec::new();
+        let result = modify_stmt(&mut stmt, &mut |expr: &mut Expr| match expr {
+            Expr::Ident(name) if name == "b" => Err(name.clone()),
+            expr => {
+                seen.push(expr.to_string());
+                Ok(())
+            }
+        });
+        assert_eq!(result, Err("b".to_string()));
+        assert_eq!(seen, ["a"]);
+    }
+}
@@ -1,10 +1,12 @@
 use std::fmt;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Prefix {
+    #[serde(rename = "-")]
     Minus,
+    #[serde(rename = "!")]
     Bang,
 }
 
@@ -17,15 +19,23 @@ impl fmt::Display for Prefix {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Infix {
+    #[serde(rename = "==")]
     Eq,
+    #[serde(rename = "!=")]
     NotEq,
+    #[serde(rename = "<")]
     Lt,
+    #[serde(rename = ">")]
     Gt,
+    #[serde(rename = "+")]
     Plus,
+    #[serde(rename = "-")]
     Minus,
+    #[serde(rename = "/")]
     Slash,
+    #[serde(rename = "*")]
     Asterisk,
 }
 
@@ -0,0 +1,188 @@
+//! Patterns, the left-hand side of a `match` arm, e.g. the
+//! `[first, ...rest]` of `match (xs) { [first, ...rest] => first }`, and
+//! what a `let` or a function parameter binds, which is most often just a
+//! name.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Expr;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Pattern {
+    /// `_`, which matches anything and binds nothing
+    #[serde(rename = "WildcardPattern")]
+    Wildcard,
+    /// a name, which matches anything and is bound to it. encoded like an
+    /// `Expr::Ident`, since that is what a `let` or a parameter usually is.
+    #[serde(rename = "Identifier", with = "crate::json::value")]
+    Ident(String),
+    /// an integer, boolean or string literal, which matches an equal value.
+    /// a negative integer is `-n`, the way the parser reads it.
+    #[serde(rename = "LiteralPattern", with = "crate::json::value")]
+    Literal(Expr),
+    /// `[a, b]`, which matches an array of exactly that length, or
+    /// `[a, b, ...rest]`, which matches an array of at least that length
+    /// and binds the remaining elements to `rest`. `rest` is a name or a
+    /// wildcard.
+    #[serde(rename = "ArrayPattern")]
+    Array {
+        elements: Vec<Pattern>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rest: Option<Box<Pattern>>,
+    },
+    /// `{"type": "x", "v": v}`, which matches a hash having each key with a
+    /// matching value. other keys are ignored. the keys are literals.
+    #[serde(rename = "HashPattern")]
+    Hash { pairs: Vec<(Expr, Pattern)> },
+}
+
+impl Pattern {
+    /// whether the pattern matches every value
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(self, Pattern::Wildcard | Pattern::Ident(_))
+    }
+
+    /// the name, if the pattern is just a name
+    pub fn as_ident(&self) -> Option<&str> {
+        match self {
+            Pattern::Ident(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// whether every value `other` matches is also matched by `self`, so
+    /// that an arm for `other` after one for `self` can never be taken
+    pub fn covers(&self, other: &Pattern) -> bool {
+        match (self, other) {
+            (pattern, _) if pattern.is_irrefutable() => true,
+            (Pattern::Literal(a), Pattern::Literal(b)) => a == b,
+            (
+                Pattern::Array { elements, rest },
+                Pattern::Array {
+                    elements: other_elements,
+                    rest: other_rest,
+                },
+            ) => {
+                let lengths = match rest {
+                    Some(_) => elements.len() <= other_elements.len(),
+                    None => other_rest.is_none() && elements.len() == other_elements.len(),
+                };
+                lengths
+                    && elements
+                        .iter()
+                        .zip(other_elements.iter())
+                        .all(|(a, b)| a.covers(b))
+            }
+            (Pattern::Hash { pairs }, Pattern::Hash { pairs: other_pairs }) => {
+                pairs.iter().all(|(key, pattern)| {
+                    other_pairs
+                        .iter()
+                        .any(|(other_key, other)| key == other_key && pattern.covers(other))
+                })
+            }
+            _ => false,
+        }
+    }
+
+    /// the names the pattern binds, in source order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_names(&mut names);
+        names
+    }
+
+    fn collect_names<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Pattern::Wildcard | Pattern::Literal(_) => {}
+            Pattern::Ident(name) => names.push(name),
+            Pattern::Array { elements, rest } => {
+                for element in elements.iter() {
+                    element.collect_names(names);
+                }
+                if let Some(rest) = rest {
+                    rest.collect_names(names);
+                }
+            }
+            Pattern::Hash { pairs } => {
+                for (_, pattern) in pairs.iter() {
+                    pattern.collect_names(names);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Ident(name) => write!(f, "{name}"),
+            Pattern::Literal(Expr::PrefixExpr { op, right }) => write!(f, "{op}{right}"),
+            Pattern::Literal(value) => write!(f, "{value}"),
+            Pattern::Array { elements, rest } => {
+                let mut elems: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                if let Some(rest) = rest {
+                    elems.push(format!("...{rest}"));
+                }
+                write!(f, "[{}]", elems.join(", "))
+            }
+            Pattern::Hash { pairs } => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, pattern)| format!("{key}: {pattern}"))
+                    .collect();
+                write!(f, "{{{}}}", pairs.join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::*;
+
+    #[test]
+    fn test_covers() {
+        let case = [
+            (wildcard(), array_pattern([literal(1)], None), true),
+            (bind("x"), literal(1), true),
+            (literal(1), literal(1), true),
+            (literal(1), literal(2), false),
+            (literal(1), bind("x"), false),
+            (
+                array_pattern([bind("a")], Some(wildcard())),
+                array_pattern([literal(1), literal(2)], None),
+                true,
+            ),
+            (
+                array_pattern([bind("a"), bind("b")], Some(wildcard())),
+                array_pattern([literal(1)], Some(wildcard())),
+                false,
+            ),
+            (
+                array_pattern([bind("a")], None),
+                array_pattern([literal(1)], Some(wildcard())),
+                false,
+            ),
+            (
+                hash_pattern([(string("t"), literal(string("x")))]),
+                hash_pattern([
+                    (string("t"), literal(string("x"))),
+                    (string("v"), bind("v")),
+                ]),
+                true,
+            ),
+            (
+                hash_pattern([(string("v"), bind("v"))]),
+                hash_pattern([]),
+                false,
+            ),
+        ];
+        for (a, b, expected) in case.iter() {
+            assert_eq!(a.covers(b), *expected, "{a} covers {b}");
+        }
+    }
+}
@@ -0,0 +1,10 @@
+pub use rmonkey_span::Span;
+
+/// spans of a `{ ... }` and of the statements directly inside it
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BlockSpans {
+    /// from `{` to `}`
+    pub span: Span,
+    /// empty for hash literals
+    pub stmts: Vec<Span>,
+}
@@ -0,0 +1,64 @@
+//! Type annotations as written in the source, e.g. the `[int]` of
+//! `fn(xs: [int]) -> int`.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TypeExpr {
+    Int,
+    Bool,
+    String,
+    Null,
+    /// opts out of type checking
+    Any,
+    /// `[int]`
+    Array {
+        element: Box<TypeExpr>,
+    },
+    /// `{string: int}`
+    Hash {
+        key: Box<TypeExpr>,
+        value: Box<TypeExpr>,
+    },
+    /// `fn(int, int) -> bool`
+    Func {
+        params: Vec<TypeExpr>,
+        ret: Box<TypeExpr>,
+    },
+}
+
+impl TypeExpr {
+    /// the type named `name`, for the names that are not built from other
+    /// types
+    pub fn from_name(name: &str) -> Option<TypeExpr> {
+        match name {
+            "int" => Some(TypeExpr::Int),
+            "bool" => Some(TypeExpr::Bool),
+            "string" => Some(TypeExpr::String),
+            "null" => Some(TypeExpr::Null),
+            "any" => Some(TypeExpr::Any),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeExpr::Int => write!(f, "int"),
+            TypeExpr::Bool => write!(f, "bool"),
+            TypeExpr::String => write!(f, "string"),
+            TypeExpr::Null => write!(f, "null"),
+            TypeExpr::Any => write!(f, "any"),
+            TypeExpr::Array { element } => write!(f, "[{element}]"),
+            TypeExpr::Hash { key, value } => write!(f, "{{{key}: {value}}}"),
+            TypeExpr::Func { params, ret } => {
+                let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+                write!(f, "fn({}) -> {ret}", params.join(", "))
+            }
+        }
+    }
+}
@@ -2,6 +2,8 @@ use std::fmt;
 
 use rmonkey_ast::operator::{Infix, Prefix};
 
+use crate::RMonkeyError;
+
 #[derive(Debug)]
 pub enum EvalErrorKind {
     TypeMismatch {
@@ -21,6 +23,117 @@ pub enum EvalErrorKind {
     UncaughtRef {
         ident: String,
     },
+    /// no module host could find the imported path
+    ModuleNotFound {
+        path: String,
+    },
+    /// modules importing each other, from the first module of the cycle
+    /// back to it
+    ImportCycle {
+        cycle: Vec<String>,
+    },
+    /// an error while loading, parsing or evaluating an imported module
+    Module {
+        path: String,
+        error: Box<RMonkeyError>,
+    },
+    NoExport {
+        module: String,
+        name: String,
+    },
+    NotCallable {
+        callee: String,
+    },
+    IndexNotSupported {
+        left: String,
+        index: String,
+    },
+    /// an index past either end of an array or a string, when the
+    /// evaluator is set to fail on it
+    IndexOutOfBounds {
+        index: i64,
+        len: usize,
+    },
+    /// a slice with a step of 0
+    ZeroSliceStep,
+    /// a `throw` that no `catch` handled
+    Thrown {
+        value: String,
+    },
+    /// a `match` with no arm for the value
+    NoMatch {
+        value: String,
+    },
+    /// a `let` or a parameter whose pattern the value doesn't match
+    Destructure {
+        pattern: String,
+        reason: String,
+    },
+    /// a hash key that is not an integer, a boolean, a string, a function
+    /// or an array or hash of those
+    Unhashable {
+        ty: String,
+    },
+    /// a value `json_stringify` has no JSON for, like a function
+    Unencodable {
+        value: String,
+    },
+    /// text `json_parse` could not read, with the 1-based position where
+    /// it went wrong
+    JsonParse {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    /// integer arithmetic whose result doesn't fit in 64 bits, like
+    /// `-9223372036854775808` negated
+    Overflow {
+        expr: String,
+    },
+    /// an integer `/` by 0
+    DivisionByZero,
+    /// a string repeated into more bytes than a string may hold
+    StringTooLong {
+        len: usize,
+        count: i64,
+    },
+    /// `unwrap` of an error value
+    Unwrap {
+        error: String,
+    },
+    /// a `?` returning an error value from the current function. the
+    /// evaluator turns it into a return at the end of the statement, so it
+    /// never reaches the host.
+    Propagated,
+}
+
+impl EvalErrorKind {
+    /// the kind of the error object a `catch` binds for this error
+    pub fn name(&self) -> &'static str {
+        match self {
+            EvalErrorKind::TypeMismatch { .. }
+            | EvalErrorKind::UnknownInfixOperator { .. }
+            | EvalErrorKind::UnknownPrefixOperator { .. }
+            | EvalErrorKind::NotCallable { .. }
+            | EvalErrorKind::IndexNotSupported { .. }
+            | EvalErrorKind::Destructure { .. }
+            | EvalErrorKind::Unhashable { .. }
+            | EvalErrorKind::Unencodable { .. } => "TypeError",
+            EvalErrorKind::UncaughtRef { .. } => "ReferenceError",
+            EvalErrorKind::ModuleNotFound { .. }
+            | EvalErrorKind::ImportCycle { .. }
+            | EvalErrorKind::Module { .. }
+            | EvalErrorKind::NoExport { .. } => "ImportError",
+            EvalErrorKind::IndexOutOfBounds { .. } | EvalErrorKind::ZeroSliceStep => "IndexError",
+            EvalErrorKind::Thrown { .. } => "Exception",
+            EvalErrorKind::NoMatch { .. } => "MatchError",
+            EvalErrorKind::JsonParse { .. } => "JsonError",
+            EvalErrorKind::Overflow { .. }
+            | EvalErrorKind::DivisionByZero
+            | EvalErrorKind::StringTooLong { .. } => "ArithmeticError",
+            EvalErrorKind::Unwrap { .. } | EvalErrorKind::Propagated => "Error",
+        }
+    }
 }
 
 impl fmt::Display for EvalErrorKind {
@@ -36,6 +149,44 @@ impl fmt::Display for EvalErrorKind {
                 write!(f, "unknown prefix operator; {op}{right}")
             }
             EvalErrorKind::UncaughtRef { ident } => write!(f, "identifier not found: {ident}"),
+            EvalErrorKind::ModuleNotFound { path } => write!(f, "module not found: {path}"),
+            EvalErrorKind::ImportCycle { cycle } => {
+                write!(f, "import cycle: {}", cycle.join(" -> "))
+            }
+            EvalErrorKind::Module { path, error } => write!(f, "in module {path}: {error}"),
+            EvalErrorKind::NoExport { module, name } => {
+                write!(f, "module {module} has no export {name}")
+            }
+            EvalErrorKind::NotCallable { callee } => write!(f, "not a function: {callee}"),
+            EvalErrorKind::IndexNotSupported { left, index } => {
+                write!(f, "index operator not supported: {left}[{index}]")
+            }
+            EvalErrorKind::IndexOutOfBounds { index, len } => {
+                write!(
+                    f,
+                    "index out of bounds: the length is {len} but the index is {index}"
+                )
+            }
+            EvalErrorKind::ZeroSliceStep => write!(f, "slice step cannot be zero"),
+            EvalErrorKind::Thrown { value } => write!(f, "uncaught exception: {value}"),
+            EvalErrorKind::NoMatch { value } => write!(f, "no match arm for value: {value}"),
+            EvalErrorKind::Destructure { pattern, reason } => {
+                write!(f, "cannot destructure with `{pattern}`: {reason}")
+            }
+            EvalErrorKind::Unhashable { ty } => write!(f, "unusable as hash key: {ty}"),
+            EvalErrorKind::Unencodable { value } => write!(f, "unencodable as JSON: {value}"),
+            EvalErrorKind::JsonParse {
+                message,
+                line,
+                column,
+            } => write!(f, "invalid JSON at line {line} column {column}: {message}"),
+            EvalErrorKind::Overflow { expr } => write!(f, "integer overflow: {expr}"),
+            EvalErrorKind::DivisionByZero => write!(f, "division by zero"),
+            EvalErrorKind::StringTooLong { len, count } => {
+                write!(f, "string too long: {len} bytes repeated {count} times")
+            }
+            EvalErrorKind::Unwrap { error } => write!(f, "called `unwrap` on {error}"),
+            EvalErrorKind::Propagated => write!(f, "`?` outside of a statement"),
         }
     }
 }
@@ -7,12 +7,42 @@ pub mod eval_error;
 
 #[derive(Debug)]
 pub enum RMonkeyError {
-    UnexpectedToken { expected: Token, got: Token },
-    InvalidPrefix { got: Token },
+    UnexpectedToken {
+        expected: Token,
+        got: Token,
+    },
+    InvalidPrefix {
+        got: Token,
+    },
     Custom(String),
+    /// a Rust value that has no object, or an object that has no value of
+    /// the Rust type asked for
+    Convert(String),
     EvalError(EvalErrorKind),
 }
 
+impl RMonkeyError {
+    /// the kind of the error object a `catch` binds for this error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RMonkeyError::UnexpectedToken { .. } | RMonkeyError::InvalidPrefix { .. } => {
+                "SyntaxError"
+            }
+            RMonkeyError::Custom(_) => "Error",
+            RMonkeyError::Convert(_) => "TypeError",
+            RMonkeyError::EvalError(err) => err.name(),
+        }
+    }
+
+    /// the message of the error object a `catch` binds for this error
+    pub fn message(&self) -> String {
+        match self {
+            RMonkeyError::Custom(msg) | RMonkeyError::Convert(msg) => msg.clone(),
+            err => err.to_string(),
+        }
+    }
+}
+
 impl fmt::Display for RMonkeyError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -24,6 +54,7 @@ impl fmt::Display for RMonkeyError {
                 write!(f, "invalid prefix: {got} is invalid for prefix")
             }
             RMonkeyError::Custom(msg) => write!(f, "custom error: {msg}"),
+            RMonkeyError::Convert(msg) => write!(f, "conversion error: {msg}"),
             RMonkeyError::EvalError(err) => write!(f, "{err}"),
         }
     }
@@ -0,0 +1,97 @@
+//! Bookkeeping for `throw` and `try`.
+//!
+//! Exceptions unwind as `Err`s like every other runtime error, so that an
+//! uncaught one reaches the host as an `RMonkeyError`. What a `catch` needs
+//! beyond the error, the thrown value and the call stack at the point the
+//! error was raised, is kept here while the error unwinds.
+
+use rmonkey_error::{eval_error::EvalErrorKind, RMonkeyError};
+use rmonkey_object::Object;
+
+/// shared by every evaluator of a program run
+#[derive(Debug, Default)]
+pub(crate) struct CallStack {
+    /// names of the functions being called, outermost first
+    frames: Vec<String>,
+    /// the error being unwound, if any
+    pending: Pending,
+    /// the error value a `?` is returning
+    propagated: Option<Object>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Pending {
+    /// the value of the `throw` being unwound
+    thrown: Option<Object>,
+    /// the frames when the error being unwound was raised
+    trace: Option<Vec<String>>,
+}
+
+impl CallStack {
+    pub(crate) fn push(&mut self, name: String) {
+        self.frames.push(name);
+    }
+
+    /// leave the innermost call. `failed` is whether it ends with an error,
+    /// which was then raised in this call unless a deeper one saw it first.
+    pub(crate) fn pop(&mut self, failed: bool) {
+        if failed && self.pending.trace.is_none() {
+            self.pending.trace = Some(self.frames.clone());
+        }
+        self.frames.pop();
+    }
+
+    /// start unwinding `value`
+    pub(crate) fn throw(&mut self, value: Object) -> RMonkeyError {
+        let error = EvalErrorKind::Thrown {
+            value: value.to_string(),
+        };
+        self.pending = Pending {
+            thrown: Some(value),
+            trace: Some(self.frames.clone()),
+        };
+        RMonkeyError::EvalError(error)
+    }
+
+    /// stop unwinding `err` and return the value a `catch` binds for it:
+    /// the thrown value, or an error object for a runtime error
+    pub(crate) fn catch(&mut self, err: RMonkeyError) -> Object {
+        let pending = self.take();
+        match (err, pending.thrown) {
+            (RMonkeyError::EvalError(EvalErrorKind::Thrown { .. }), Some(value)) => value,
+            (err, _) => {
+                let mut stack = pending.trace.unwrap_or_else(|| self.frames.clone());
+                stack.reverse();
+                Object::Error {
+                    kind: err.kind().to_string(),
+                    message: err.message(),
+                    stack,
+                    payload: None,
+                }
+            }
+        }
+    }
+
+    /// start returning the error value `value` from the current function
+    pub(crate) fn propagate(&mut self, value: Object) -> RMonkeyError {
+        self.propagated = Some(value);
+        RMonkeyError::EvalError(EvalErrorKind::Propagated)
+    }
+
+    /// the error value of the `?` that raised `err`, if it is one
+    pub(crate) fn propagated(&mut self, err: &RMonkeyError) -> Option<Object> {
+        match err {
+            RMonkeyError::EvalError(EvalErrorKind::Propagated) => self.propagated.take(),
+            _ => None,
+        }
+    }
+
+    /// set the error being unwound aside, e.g. while a `finally` runs
+    pub(crate) fn take(&mut self) -> Pending {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub(crate) fn restore(&mut self, pending: Pending) {
+        self.pending = pending;
+    }
+}
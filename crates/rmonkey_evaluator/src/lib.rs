@@ -3,16 +3,51 @@ use std::{cell::RefCell, rc::Rc};
 
 use rmonkey_ast::{
     operator::{Infix, Prefix},
-    Expr, Program, Stmt,
+    pattern::Pattern,
+    Catch, Expr, MatchArm, Program, Stmt,
 };
 use rmonkey_error::{eval_error::EvalErrorKind, RMonkeyError, Result};
-use rmonkey_object::builtin::builtins;
-use rmonkey_object::{scope::Scope, Object};
+use rmonkey_lexer::Lexer;
+use rmonkey_object::builtin::{builtins, Caller, Rng};
+use rmonkey_object::native::{IntoNative, Namespace};
+use rmonkey_object::{scope::Scope, IndexMap, Object};
+use rmonkey_parser::Parser;
+
+use exception::CallStack;
+use module::Modules;
+pub use module::{FsHost, MemoryHost, ModuleHost};
+
+mod exception;
+mod macros;
+mod module;
+mod pattern;
+
+/// the most bytes a string made by `*` may hold
+const MAX_STRING_LEN: usize = 1 << 30;
 
 #[derive(Debug, Default)]
 pub struct Evaluator {
     env: Rc<RefCell<Scope>>,
     builtin: Rc<RefCell<HashMap<&'static str, Object>>>,
+    modules: Rc<RefCell<Modules>>,
+    /// id of the module being evaluated, `None` for the main program
+    module: Option<String>,
+    stack: Rc<RefCell<CallStack>>,
+    /// the macros defined so far, see [`Evaluator::expand_macros`]
+    macros: Rc<RefCell<Scope>>,
+    out_of_bounds: OutOfBounds,
+    /// where the `random` builtins get their numbers
+    rng: Rc<RefCell<Rng>>,
+}
+
+/// what an index past either end of an array or a string gives
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfBounds {
+    /// `null`, like a key missing from a hash
+    #[default]
+    Null,
+    /// an `IndexOutOfBounds` error
+    Error,
 }
 
 impl Evaluator {
@@ -20,9 +55,53 @@ impl Evaluator {
         Evaluator {
             env: Rc::new(RefCell::new(Scope::new())),
             builtin: Rc::new(RefCell::new(builtins())),
+            modules: Rc::new(RefCell::new(Modules::default())),
+            module: None,
+            stack: Rc::new(RefCell::new(CallStack::default())),
+            macros: Rc::new(RefCell::new(Scope::new())),
+            out_of_bounds: OutOfBounds::default(),
+            rng: Rc::new(RefCell::new(Rng::default())),
+        }
+    }
+
+    /// an evaluator that loads `import`ed modules through `host`. one made
+    /// by [`Evaluator::new`] can't import anything.
+    pub fn with_host(host: impl ModuleHost + 'static) -> Self {
+        Evaluator {
+            modules: Rc::new(RefCell::new(Modules::new(host))),
+            ..Evaluator::new()
         }
     }
 
+    /// what indexing past either end of an array or a string gives from
+    /// now on
+    pub fn set_out_of_bounds(&mut self, out_of_bounds: OutOfBounds) {
+        self.out_of_bounds = out_of_bounds;
+    }
+
+    /// restart the generator of the `random` builtins from `seed`, so that
+    /// they give the same numbers on every run
+    pub fn seed_random(&mut self, seed: u64) {
+        *self.rng.borrow_mut() = Rng::new(seed);
+    }
+
+    /// make a closure of the host a builtin called `name`. its arguments are
+    /// converted from objects and checked like those of other builtins, see
+    /// [`rmonkey_object::native`].
+    pub fn register_fn<Args>(&mut self, name: &'static str, func: impl IntoNative<Args>) {
+        let func = func.into_native(name);
+        self.builtin
+            .borrow_mut()
+            .insert(name, Object::Native { func });
+    }
+
+    /// make the functions of `namespace` builtins under its name
+    pub fn register_namespace(&mut self, namespace: Namespace) {
+        self.builtin
+            .borrow_mut()
+            .insert(namespace.name(), namespace.into_object());
+    }
+
     pub fn set_val_to_env(&mut self, key: String, val: Object) {
         self.env.borrow_mut().set(key, val);
     }
@@ -32,6 +111,9 @@ impl Evaluator {
     }
 
     pub fn eval(&mut self, node: Program) -> Result<Object> {
+        // left over from an error that ended the last run
+        self.stack.borrow_mut().take();
+        let node = self.expand_macros(node)?;
         let mut result = Object::Null;
         for p in node.stmts.iter() {
             result = self.eval_stmt(p)?;
@@ -42,11 +124,23 @@ impl Evaluator {
         Ok(result)
     }
 
+    /// the value of a statement. a `?` in it that met an error value
+    /// returns that value from the function, like a `return` would.
     fn eval_stmt(&mut self, node: &Stmt) -> Result<Object> {
+        match self.eval_stmt_inner(node) {
+            Err(err) => match self.stack.borrow_mut().propagated(&err) {
+                Some(value) => Ok(Object::ReturnValue(Box::new(value))),
+                None => Err(err),
+            },
+            result => result,
+        }
+    }
+
+    fn eval_stmt_inner(&mut self, node: &Stmt) -> Result<Object> {
         match node {
-            Stmt::LetStmt { name, value } => {
+            Stmt::LetStmt { name, value, .. } => {
                 let value = self.eval_expr(value)?;
-                self.set_val_to_env(name.to_string(), value);
+                self.destructure(name, &value)?;
                 Ok(Object::Null)
             }
             Stmt::ReturnStmt(expr) => {
@@ -55,7 +149,98 @@ impl Evaluator {
             }
             Stmt::ExprStmt(expr) => Ok(self.eval_expr(expr)?),
             Stmt::BlockStmt { stmts } => Ok(self.eval_block_stmt(stmts)?),
+            Stmt::Import { path, alias } => {
+                let module = self.import(path)?;
+                self.set_val_to_env(alias.clone(), module);
+                Ok(Object::Null)
+            }
+            Stmt::Throw(expr) => {
+                let value = self.eval_expr(expr)?;
+                Err(self.stack.borrow_mut().throw(value))
+            }
+        }
+    }
+
+    /// the module `path` refers to, evaluated on its first import
+    fn import(&mut self, path: &str) -> Result<Object> {
+        let id = self
+            .modules
+            .borrow()
+            .host
+            .resolve(path, self.module.as_deref())
+            .ok_or_else(|| {
+                RMonkeyError::EvalError(EvalErrorKind::ModuleNotFound {
+                    path: path.to_string(),
+                })
+            })?;
+        if let Some(module) = self.modules.borrow().cache.get(&id) {
+            return Ok(module.clone());
+        }
+        let loading = self.modules.borrow().loading.clone();
+        if let Some(start) = loading.iter().position(|loading| *loading == id) {
+            let mut cycle = loading[start..].to_vec();
+            cycle.push(id);
+            return Err(RMonkeyError::EvalError(EvalErrorKind::ImportCycle {
+                cycle,
+            }));
+        }
+
+        self.modules.borrow_mut().loading.push(id.clone());
+        let exports = self.eval_module(&id);
+        self.modules.borrow_mut().loading.pop();
+        let module = Object::Module {
+            path: id.clone(),
+            exports: exports.map_err(|error| {
+                RMonkeyError::EvalError(EvalErrorKind::Module {
+                    path: id.clone(),
+                    error: Box::new(error),
+                })
+            })?,
+        };
+        self.modules.borrow_mut().cache.insert(id, module.clone());
+        Ok(module)
+    }
+
+    /// evaluate the module `id` in a scope of its own and return its exports
+    fn eval_module(&self, id: &str) -> Result<HashMap<String, Object>> {
+        let source = self
+            .modules
+            .borrow()
+            .host
+            .load(id)
+            .map_err(RMonkeyError::Custom)?;
+        let program = Parser::new(Lexer::new(&source)).parse_program()?;
+        let mut evaluator = Evaluator {
+            env: Rc::new(RefCell::new(Scope::new())),
+            builtin: Rc::clone(&self.builtin),
+            modules: Rc::clone(&self.modules),
+            module: Some(id.to_string()),
+            stack: Rc::clone(&self.stack),
+            out_of_bounds: self.out_of_bounds,
+            rng: Rc::clone(&self.rng),
+            // a module's macros are its own, like its other bindings
+            macros: Rc::new(RefCell::new(Scope::new())),
+        };
+        let names: Vec<String> = program
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::LetStmt {
+                    export: true, name, ..
+                } => Some(name.names()),
+                _ => None,
+            })
+            .flatten()
+            .map(|name| name.to_string())
+            .collect();
+        evaluator.eval(program)?;
+        let mut exports = HashMap::new();
+        for name in names {
+            if let Some(value) = evaluator.get(&name) {
+                exports.insert(name, value);
+            }
         }
+        Ok(exports)
     }
 
     fn eval_block_stmt(&mut self, stmts: &[Stmt]) -> Result<Object> {
@@ -82,8 +267,12 @@ impl Evaluator {
                 alternative,
             } => self.eval_if_expr(condition, consequence, alternative),
             Expr::PrefixExpr { op, right } => Ok(self.eval_prefix_expr(op, right)?),
+            Expr::Propagate { value } => match self.eval_expr(value)? {
+                error @ Object::Error { .. } => Err(self.stack.borrow_mut().propagate(error)),
+                value => Ok(value),
+            },
             Expr::InfixExpr { left, right, op } => Ok(self.eval_infix_expr(op, left, right)?),
-            Expr::Func { params, body } => Ok(self.eval_func_literal(body, params)?),
+            Expr::Func { params, body, .. } => Ok(self.eval_func_literal(body, params)?),
             Expr::Call { callee, args } => Ok(self.eval_call_expr(callee, args)?),
             Expr::Array { elements } => {
                 let mut elems: Vec<Object> = Vec::new();
@@ -97,7 +286,22 @@ impl Evaluator {
                 let index = self.eval_expr(index)?;
                 self.eval_index_expr(left, index)
             }
+            Expr::Slice {
+                left,
+                start,
+                end,
+                step,
+            } => self.eval_slice_expr(left, start.as_deref(), end.as_deref(), step.as_deref()),
             Expr::HashLiteral { pairs } => self.eval_hash_literal(pairs.to_vec()),
+            Expr::Try {
+                body,
+                catch,
+                finally,
+            } => self.eval_try_expr(body, catch.as_deref(), finally.as_deref()),
+            Expr::Match { value, arms } => self.eval_match_expr(value, arms),
+            Expr::Macro { .. } => Err(RMonkeyError::Custom(
+                "a macro can only be defined by a top-level `let`".to_string(),
+            )),
         }
     }
 
@@ -133,7 +337,13 @@ impl Evaluator {
 
     fn eval_minus_operator_expr(&self, right: Object) -> Result<Object> {
         if let Object::Int(val) = right {
-            Ok(Object::Int(-val))
+            val.checked_neg().map(Object::Int).ok_or_else(|| {
+                RMonkeyError::EvalError(EvalErrorKind::Overflow {
+                    expr: format!("-({val})"),
+                })
+            })
+        } else if let Object::Float(val) = right {
+            Ok(Object::Float(-val))
         } else {
             Err(RMonkeyError::EvalError(
                 rmonkey_error::eval_error::EvalErrorKind::UnknownPrefixOperator {
@@ -149,7 +359,16 @@ impl Evaluator {
         let right = self.eval_expr(right)?;
         match (&left, &right) {
             (Object::Int(left_val), Object::Int(right_val)) => {
-                Ok(self.eval_int_infix_expr(op, left_val, right_val))
+                self.eval_int_infix_expr(op, left_val, right_val)
+            }
+            (Object::Float(left_val), Object::Float(right_val)) => {
+                Ok(self.eval_float_infix_expr(op, *left_val, *right_val))
+            }
+            (Object::Int(left_val), Object::Float(right_val)) => {
+                Ok(self.eval_float_infix_expr(op, *left_val as f64, *right_val))
+            }
+            (Object::Float(left_val), Object::Int(right_val)) => {
+                Ok(self.eval_float_infix_expr(op, *left_val, *right_val as f64))
             }
             (Object::Bool(left_val), Object::Bool(right_val)) => match op {
                 Infix::Eq => Ok(self.native_bool_to_bool_object(left_val == right_val)),
@@ -164,6 +383,10 @@ impl Evaluator {
             },
             (Object::String(left_val), Object::String(right_val)) => match op {
                 Infix::Plus => Ok(Object::String(format!("{left_val}{right_val}"))),
+                Infix::Eq => Ok(self.native_bool_to_bool_object(left_val == right_val)),
+                Infix::NotEq => Ok(self.native_bool_to_bool_object(left_val != right_val)),
+                Infix::Lt => Ok(self.native_bool_to_bool_object(left_val < right_val)),
+                Infix::Gt => Ok(self.native_bool_to_bool_object(left_val > right_val)),
                 _ => Err(RMonkeyError::EvalError(
                     EvalErrorKind::UnknownInfixOperator {
                         op: op.clone(),
@@ -172,6 +395,23 @@ impl Evaluator {
                     },
                 )),
             },
+            // repetition. a count below 1 gives the empty string.
+            (Object::String(val), Object::Int(count)) if *op == Infix::Asterisk => {
+                let times = usize::try_from((*count).max(0)).unwrap_or(usize::MAX);
+                match val.len().checked_mul(times) {
+                    Some(len) if len <= MAX_STRING_LEN => Ok(Object::String(val.repeat(times))),
+                    _ => Err(RMonkeyError::EvalError(EvalErrorKind::StringTooLong {
+                        len: val.len(),
+                        count: *count,
+                    })),
+                }
+            }
+            // arrays and hashes are equal when their elements are
+            (Object::Array { .. }, Object::Array { .. }) | (Object::Hash(_), Object::Hash(_))
+                if matches!(op, Infix::Eq | Infix::NotEq) =>
+            {
+                Ok(self.native_bool_to_bool_object((left == right) == (*op == Infix::Eq)))
+            }
             _ => {
                 if left.obj_type() != right.obj_type() {
                     Err(RMonkeyError::EvalError(EvalErrorKind::TypeMismatch {
@@ -192,12 +432,33 @@ impl Evaluator {
         }
     }
 
-    fn eval_int_infix_expr(&self, op: &Infix, left: &i64, right: &i64) -> Object {
+    fn eval_int_infix_expr(&self, op: &Infix, left: &i64, right: &i64) -> Result<Object> {
+        let result = match op {
+            Infix::Plus => left.checked_add(*right),
+            Infix::Minus => left.checked_sub(*right),
+            Infix::Asterisk => left.checked_mul(*right),
+            Infix::Slash if *right == 0 => {
+                return Err(RMonkeyError::EvalError(EvalErrorKind::DivisionByZero))
+            }
+            Infix::Slash => left.checked_div(*right),
+            Infix::Lt => return Ok(self.native_bool_to_bool_object(left < right)),
+            Infix::Gt => return Ok(self.native_bool_to_bool_object(left > right)),
+            Infix::Eq => return Ok(self.native_bool_to_bool_object(left == right)),
+            Infix::NotEq => return Ok(self.native_bool_to_bool_object(left != right)),
+        };
+        result.map(Object::Int).ok_or_else(|| {
+            RMonkeyError::EvalError(EvalErrorKind::Overflow {
+                expr: format!("{left} {op} {right}"),
+            })
+        })
+    }
+
+    fn eval_float_infix_expr(&self, op: &Infix, left: f64, right: f64) -> Object {
         match op {
-            Infix::Plus => Object::Int(left + right),
-            Infix::Minus => Object::Int(left - right),
-            Infix::Asterisk => Object::Int(left * right),
-            Infix::Slash => Object::Int(left / right),
+            Infix::Plus => Object::Float(left + right),
+            Infix::Minus => Object::Float(left - right),
+            Infix::Asterisk => Object::Float(left * right),
+            Infix::Slash => Object::Float(left / right),
             Infix::Lt => self.native_bool_to_bool_object(left < right),
             Infix::Gt => self.native_bool_to_bool_object(left > right),
             Infix::Eq => self.native_bool_to_bool_object(left == right),
@@ -219,7 +480,7 @@ impl Evaluator {
         alt: &Option<Box<Stmt>>,
     ) -> Result<Object> {
         let cond = self.eval_expr(condition)?;
-        if self.is_truthy(cond) {
+        if cond.is_truthy() {
             return self.eval_stmt(consequence);
         }
         match alt {
@@ -228,26 +489,97 @@ impl Evaluator {
         }
     }
 
-    fn is_truthy(&self, obj: Object) -> bool {
-        match obj {
-            Object::Bool(val) => val,
-            Object::Null => false,
-            _ => true,
+    /// run `f` in a scope enclosed by the current one, so that the names it
+    /// sets are dropped afterwards
+    fn in_child_scope<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let child = Scope::new_enclosed_environment(Rc::clone(&self.env));
+        let outer = std::mem::replace(&mut self.env, Rc::new(RefCell::new(child)));
+        let result = f(self);
+        self.env = outer;
+        result
+    }
+
+    /// the value of the body, or of the `catch` if the body failed. the
+    /// `catch` runs in a scope of its own holding its parameter. the
+    /// `finally` runs last, and only changes the outcome if it fails or
+    /// returns.
+    fn eval_try_expr(
+        &mut self,
+        body: &Stmt,
+        catch: Option<&Catch>,
+        finally: Option<&Stmt>,
+    ) -> Result<Object> {
+        let mut result = self.eval_stmt(body);
+        if let Some(catch) = catch {
+            if let Err(err) = result {
+                let exception = self.stack.borrow_mut().catch(err);
+                result = self.in_child_scope(|evaluator| {
+                    evaluator.set_val_to_env(catch.param.clone(), exception);
+                    evaluator.eval_stmt(&catch.body)
+                });
+            }
+        }
+        if let Some(finally) = finally {
+            // errors raised and caught in the `finally` must not replace
+            // the one still unwinding
+            let pending = self.stack.borrow_mut().take();
+            let value = self.eval_stmt(finally)?;
+            self.stack.borrow_mut().restore(pending);
+            if let Object::ReturnValue(_) = value {
+                return Ok(value);
+            }
+        }
+        result
+    }
+
+    /// the value of the body of the first arm whose pattern matches and
+    /// whose guard, if any, is truthy. each arm runs in a scope of its own
+    /// holding the names bound by its pattern.
+    fn eval_match_expr(&mut self, value: &Expr, arms: &[MatchArm]) -> Result<Object> {
+        let value = self.eval_expr(value)?;
+        for arm in arms.iter() {
+            let mut bindings = Vec::new();
+            if pattern::bind(&arm.pattern, &value, &mut bindings).is_err() {
+                continue;
+            }
+            let result = self.in_child_scope(|evaluator| {
+                for (name, value) in bindings {
+                    evaluator.set_val_to_env(name, value);
+                }
+                if let Some(guard) = &arm.guard {
+                    if !evaluator.eval_expr(guard)?.is_truthy() {
+                        return Ok(None);
+                    }
+                }
+                evaluator.eval_expr(&arm.body).map(Some)
+            });
+            if let Some(result) = result.transpose() {
+                return result;
+            }
         }
+        Err(RMonkeyError::EvalError(EvalErrorKind::NoMatch {
+            value: value.to_string(),
+        }))
     }
 
-    fn eval_func_literal(&mut self, body: &Stmt, params: &[Expr]) -> Result<Object> {
+    fn eval_func_literal(&mut self, body: &Stmt, params: &[Pattern]) -> Result<Object> {
         Ok(Object::Func {
             params: params.to_vec(),
-            body: body.clone(),
+            body: Rc::new(body.clone()),
             scope: Scope::new_enclosed_environment(Rc::clone(&self.env)),
         })
     }
 
     fn eval_call_expr(&mut self, callee: &Expr, args: &[Expr]) -> Result<Object> {
+        // the name of the call in stack traces
+        let name = match callee {
+            Expr::Ident(name) if name == "quote" => return self.eval_quote(args),
+            Expr::Ident(name) => name.to_string(),
+            _ => "<anonymous>".to_string(),
+        };
         let callee = self.eval_expr(callee)?;
         let args = self.eval_exprs(args)?;
-        self.apply_func(callee, args)
+        self.apply_func(name, callee, args)
     }
 
     fn eval_exprs(&mut self, exprs: &[Expr]) -> Result<Vec<Object>> {
@@ -258,78 +590,173 @@ impl Evaluator {
         Ok(result)
     }
 
-    fn apply_func(&self, callee: Object, args: Vec<Object>) -> Result<Object> {
-        if let Object::Func {
-            params,
-            ref body,
-            scope,
-        } = callee
-        {
-            let mut function_env = self.extend_func_env(params, args, scope);
-            let result = function_env.eval_stmt(body).unwrap();
-            if let Object::ReturnValue(value) = result {
-                return Ok(*value);
+    fn apply_func(&mut self, name: String, callee: Object, args: Vec<Object>) -> Result<Object> {
+        let result = match callee {
+            Object::Func {
+                params,
+                body,
+                scope,
+            } => {
+                // a failed destructuring of the arguments has no frame to pop
+                let mut function_env = self.extend_func_env(params, args, scope)?;
+                self.stack.borrow_mut().push(name);
+                match function_env.eval_stmt(&body) {
+                    Ok(Object::ReturnValue(value)) => Ok(*value),
+                    result => result,
+                }
             }
-            return Ok(result);
-        }
-
-        if let Object::BuiltIn { func } = callee {
-            return func(args);
-        }
-
-        Err(RMonkeyError::Custom("".to_string()))
+            Object::BuiltIn { func } => {
+                self.stack.borrow_mut().push(name);
+                func(self, args)
+            }
+            Object::Native { func } => {
+                self.stack.borrow_mut().push(name);
+                func.call(self, args)
+            }
+            callee => {
+                return Err(RMonkeyError::EvalError(EvalErrorKind::NotCallable {
+                    callee: callee.obj_type().to_string(),
+                }))
+            }
+        };
+        self.stack.borrow_mut().pop(result.is_err());
+        result
     }
 
     /// create child scope and bind params name and actual given arg value.
     fn extend_func_env(
         &self,
-        params: Vec<Expr>,
+        params: Vec<Pattern>,
         args: Vec<Object>,
         parent_scope: Scope,
-    ) -> Evaluator {
-        let function_env = Evaluator {
+    ) -> Result<Evaluator> {
+        let mut function_env = Evaluator {
             env: Rc::new(RefCell::new(parent_scope)),
             builtin: Rc::clone(&self.builtin),
+            modules: Rc::clone(&self.modules),
+            module: self.module.clone(),
+            stack: Rc::clone(&self.stack),
+            out_of_bounds: self.out_of_bounds,
+            rng: Rc::clone(&self.rng),
+            macros: Rc::clone(&self.macros),
         };
         // bind param and actual given arg.
-        for (ident, arg) in params.iter().zip(args.iter()) {
-            if let Expr::Ident(ident) = ident {
-                function_env
-                    .env
-                    .borrow_mut()
-                    .set(ident.to_string(), arg.clone());
-            }
+        for (param, arg) in params.iter().zip(args.iter()) {
+            function_env.destructure(param, arg)?;
+        }
+        Ok(function_env)
+    }
+
+    /// bind the names of `pattern` to the parts of `value` they match
+    fn destructure(&mut self, pattern: &Pattern, value: &Object) -> Result<()> {
+        let mut bindings = Vec::new();
+        if let Err(reason) = pattern::bind(pattern, value, &mut bindings) {
+            return Err(RMonkeyError::EvalError(EvalErrorKind::Destructure {
+                pattern: pattern.to_string(),
+                reason,
+            }));
+        }
+        for (name, value) in bindings {
+            self.set_val_to_env(name, value);
         }
-        function_env
+        Ok(())
     }
 
     fn eval_index_expr(&mut self, left: Object, index: Object) -> Result<Object> {
         match (left, index) {
-            (Object::Array { elements }, Object::Int(val)) => {
-                self.eval_array_index_expr(elements, val)
+            (left @ (Object::Array { .. } | Object::String(_)), Object::Int(index)) => {
+                self.eval_sequence_index_expr(left, index)
             }
             (Object::Hash(pairs), ref index_obj) => self.eval_hash_index_expr(pairs, index_obj),
-            _ => todo!(),
+            (
+                Object::Error {
+                    kind,
+                    message,
+                    stack,
+                    payload,
+                },
+                Object::String(field),
+            ) => Ok(match field.as_str() {
+                "kind" => Object::String(kind),
+                "message" => Object::String(message),
+                "stack" => Object::Array {
+                    elements: stack.into_iter().map(Object::String).collect(),
+                },
+                "payload" => payload.map_or(Object::Null, |payload| *payload),
+                _ => Object::Null,
+            }),
+            (Object::Module { path, mut exports }, Object::String(name)) => {
+                match exports.remove(&name) {
+                    Some(value) => Ok(value),
+                    None => Err(RMonkeyError::EvalError(EvalErrorKind::NoExport {
+                        module: path,
+                        name,
+                    })),
+                }
+            }
+            (left, index) => Err(RMonkeyError::EvalError(EvalErrorKind::IndexNotSupported {
+                left: left.obj_type().to_string(),
+                index: index.obj_type().to_string(),
+            })),
         }
     }
 
-    fn eval_array_index_expr(&mut self, arr: Vec<Object>, index: i64) -> Result<Object> {
-        let max = arr.len() as i64;
+    /// the element of an array, or the character of a string, at `index`,
+    /// counting from the end when it is negative
+    fn eval_sequence_index_expr(&self, seq: Object, index: i64) -> Result<Object> {
+        if let Some(value) = seq.get_index(index) {
+            return Ok(value);
+        }
+        match self.out_of_bounds {
+            OutOfBounds::Null => Ok(Object::Null),
+            OutOfBounds::Error => Err(RMonkeyError::EvalError(EvalErrorKind::IndexOutOfBounds {
+                index,
+                len: seq.length().unwrap_or_default(),
+            })),
+        }
+    }
 
-        if index < 0 || index > max {
-            return Ok(Object::Null);
+    /// `left[start:end:step]` of an array or a string
+    fn eval_slice_expr(
+        &mut self,
+        left: &Expr,
+        start: Option<&Expr>,
+        end: Option<&Expr>,
+        step: Option<&Expr>,
+    ) -> Result<Object> {
+        let left = self.eval_expr(left)?;
+        let start = self.eval_slice_bound(&left, start)?;
+        let end = self.eval_slice_bound(&left, end)?;
+        let step = self.eval_slice_bound(&left, step)?.unwrap_or(1);
+        if step == 0 {
+            return Err(RMonkeyError::EvalError(EvalErrorKind::ZeroSliceStep));
         }
+        left.slice(start, end, step).ok_or_else(|| {
+            RMonkeyError::EvalError(EvalErrorKind::IndexNotSupported {
+                left: left.obj_type().to_string(),
+                index: "SLICE".to_string(),
+            })
+        })
+    }
 
-        if let Some(array_val) = arr.get(index as usize) {
-            return Ok(array_val.clone());
+    fn eval_slice_bound(&mut self, left: &Object, bound: Option<&Expr>) -> Result<Option<i64>> {
+        let Some(bound) = bound else {
+            return Ok(None);
+        };
+        match self.eval_expr(bound)? {
+            Object::Int(val) => Ok(Some(val)),
+            bound => Err(RMonkeyError::EvalError(EvalErrorKind::IndexNotSupported {
+                left: left.obj_type().to_string(),
+                index: bound.obj_type().to_string(),
+            })),
         }
-        Ok(Object::Null)
     }
 
     fn eval_hash_literal(&mut self, pairs: Vec<(Expr, Expr)>) -> Result<Object> {
-        let mut hash: HashMap<Object, Object> = HashMap::new();
+        let mut hash = IndexMap::new();
         for (key, val) in pairs.iter() {
             let key = self.eval_expr(key)?;
+            key.check_hashable()?;
             let value = self.eval_expr(val)?;
 
             hash.insert(key, value);
@@ -339,9 +766,10 @@ impl Evaluator {
 
     fn eval_hash_index_expr(
         &mut self,
-        pairs: HashMap<Object, Object>,
+        pairs: IndexMap<Object, Object>,
         index: &Object,
     ) -> Result<Object> {
+        index.check_hashable()?;
         match pairs.get(index) {
             Some(val) => Ok(val.clone()),
             None => Ok(Object::Null),
@@ -349,12 +777,29 @@ impl Evaluator {
     }
 }
 
+/// how builtins like `map` call the function they are given
+impl Caller for Evaluator {
+    fn call(&mut self, func: Object, args: Vec<Object>) -> Result<Object> {
+        self.apply_func("<anonymous>".to_string(), func, args)
+    }
+
+    fn random(&mut self) -> u64 {
+        self.rng.borrow_mut().next_u64()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use rmonkey_lexer::Lexer;
+    use rmonkey_object::{native::Namespace, Object};
     use rmonkey_parser::Parser;
 
-    use crate::Evaluator;
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    use crate::{Evaluator, MemoryHost, ModuleHost, OutOfBounds};
 
     #[test]
     fn test_prefix_expr() {
@@ -505,6 +950,10 @@ mod tests {
                 "let newAdder = fn(x) { fn(y) { x + y } };let addTwo = newAdder(2);addTwo(3);",
                 "5",
             ),
+            (
+                "let a = 1; let f = fn() { fn() { fn() { a } } }; f()()();",
+                "1",
+            ),
         ];
         for (input, expected) in case.iter() {
             let mut e = Evaluator::new();
@@ -522,6 +971,8 @@ mod tests {
             (r#"len("")"#, "0"),
             (r#"len("four")"#, "4"),
             (r#"len("hello world")"#, "11"),
+            // characters, not bytes
+            (r#"len("héllo")"#, "5"),
             (
                 r#"len(1)"#,
                 "custom error: arg to `len` not supported, got INTEGER",
@@ -566,6 +1017,10 @@ mod tests {
             (r#"[1, 2, 3][2]"#, "3"),
             ("let myArray = [1, 2, 3]; myArray[2];", "3"),
             ("[1, 2, 3][3]", "null"),
+            ("[1, 2, 3][-1]", "3"),
+            ("[1, 2, 3][-3]", "1"),
+            ("[1, 2, 3][-4]", "null"),
+            ("[][0]", "null"),
         ];
         for (input, expected) in case.iter() {
             let mut e = Evaluator::new();
@@ -610,7 +1065,14 @@ mod tests {
 
     #[test]
     fn test_hash_string() {
-        let case = [(r#"{"one": 10 - 9, "two": 5}"#, r#"{"one": 1, "two": 5}"#)];
+        let case = [
+            (r#"{"one": 10 - 9, "two": 5}"#, r#"{"one": 1, "two": 5}"#),
+            // pairs keep the order they were written in
+            (
+                r#"{"c": 1, "a": 2, "b": 3, 1: 4}"#,
+                r#"{"c": 1, "a": 2, "b": 3, 1: 4}"#,
+            ),
+        ];
         for (input, expected) in case.iter() {
             let mut e = Evaluator::new();
             let l = Lexer::new(input);
@@ -642,4 +1104,1127 @@ mod tests {
             }
         }
     }
+
+    fn eval_with_modules(modules: &[(&str, &str)], input: &str) -> String {
+        let mut host = MemoryHost::default();
+        for (path, source) in modules.iter() {
+            host.insert(*path, *source);
+        }
+        let mut e = Evaluator::with_host(host);
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        match e.eval(program) {
+            Ok(r) => r.to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_import() {
+        let modules = [
+            (
+                "lib/math.monkey",
+                r#"let twice = fn(x) { x * 2 }; export let quad = fn(x) { twice(twice(x)) }; export let name = "math";"#,
+            ),
+            (
+                "lib/util.monkey",
+                r#"import "./math.monkey" as mathlib; export let octo = fn(x) { mathlib["quad"](x) * 2 };"#,
+            ),
+            ("a.monkey", r#"import "b.monkey" as b; export let a = 1;"#),
+            ("b.monkey", r#"import "a.monkey" as a; export let b = 1;"#),
+            ("self.monkey", r#"import "self.monkey" as me;"#),
+            ("bad.monkey", "let a = 1 + true;"),
+        ];
+        let case = [
+            (r#"import "lib/math.monkey" as m; m["quad"](3)"#, "12"),
+            (r#"import "lib/math.monkey" as m; m["name"]"#, r#""math""#),
+            (r#"import "lib/math.monkey" as m; m"#, "<module lib/math.monkey>"),
+            (r#"import "lib/util.monkey" as u; u["octo"](1)"#, "8"),
+            // only exports are visible
+            (
+                r#"import "lib/math.monkey" as m; m["twice"]"#,
+                "module lib/math.monkey has no export twice",
+            ),
+            (r#"import "lib/math.monkey" as m; twice"#, "identifier not found: twice"),
+            (
+                r#"import "lib/util.monkey" as u; mathlib"#,
+                "identifier not found: mathlib",
+            ),
+            (
+                r#"import "nope.monkey" as m;"#,
+                "module not found: nope.monkey",
+            ),
+            (
+                r#"import "a.monkey" as a;"#,
+                "in module a.monkey: in module b.monkey: import cycle: a.monkey -> b.monkey -> a.monkey",
+            ),
+            (
+                r#"import "self.monkey" as me;"#,
+                "in module self.monkey: import cycle: self.monkey -> self.monkey",
+            ),
+            (
+                r#"import "bad.monkey" as m;"#,
+                "in module bad.monkey: type mismatch: INTEGER + BOOLEAN",
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_with_modules(&modules, input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_import_cache() {
+        struct Counting {
+            loads: Rc<Cell<usize>>,
+        }
+        impl ModuleHost for Counting {
+            fn resolve(&self, path: &str, _from: Option<&str>) -> Option<String> {
+                Some(path.to_string())
+            }
+            fn load(&self, id: &str) -> std::result::Result<String, String> {
+                self.loads.set(self.loads.get() + 1);
+                Ok(match id {
+                    "a" => r#"export let n = 1;"#,
+                    _ => r#"import "a" as a; export let n = a["n"] + 1;"#,
+                }
+                .to_string())
+            }
+        }
+
+        let loads = Rc::new(Cell::new(0));
+        let mut e = Evaluator::with_host(Counting {
+            loads: Rc::clone(&loads),
+        });
+        let input =
+            r#"import "a" as a; import "b" as b; import "a" as c; a["n"] + b["n"] + c["n"]"#;
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        assert_eq!(e.eval(program).unwrap().to_string(), "4");
+        assert_eq!(loads.get(), 2);
+    }
+
+    #[test]
+    fn test_import_without_host() {
+        // the file system is only reachable through a host the embedder
+        // chose
+        let dir = env!("CARGO_MANIFEST_DIR");
+        for path in [
+            "/etc/hostname",
+            &format!("{dir}/Cargo.toml"),
+            "./Cargo.toml",
+        ] {
+            assert_eq!(
+                eval_input(&format!(r#"import "{path}" as m;"#)),
+                format!("module not found: {path}")
+            );
+        }
+    }
+
+    fn eval_input(input: &str) -> String {
+        let mut e = Evaluator::new();
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        match e.eval(program) {
+            Ok(r) => r.to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let case = [
+            (r#"try { throw "boom"; } catch (e) { e }"#, r#""boom""#),
+            ("try { 1 } catch (e) { 2 }", "1"),
+            ("let x = try { throw 1; 2 } catch (e) { e + 10 }; x", "11"),
+            // the parameter doesn't outlive the `catch`
+            ("let x = 1; try { throw 2 } catch (x) { x }; x", "1"),
+            ("try { throw 2 } catch (e) { e }; try { e } catch (err) { err[\"kind\"] }", "\"ReferenceError\""),
+            // runtime errors are caught as error objects
+            (
+                "try { 1 + true } catch (e) { e }",
+                "TypeError: type mismatch: INTEGER + BOOLEAN",
+            ),
+            (r#"try { x } catch (e) { e["kind"] }"#, r#""ReferenceError""#),
+            (
+                r#"try { len(1) } catch (e) { e["message"] }"#,
+                r#""arg to `len` not supported, got INTEGER""#,
+            ),
+            (r#"try { 1(2) } catch (e) { e["message"] }"#, r#""not a function: INTEGER""#),
+            (
+                r#"try { 1[0] } catch (e) { e["kind"] }"#,
+                r#""TypeError""#,
+            ),
+            ("try { 1 / 0 } catch (e) { e }", "ArithmeticError: division by zero"),
+            (
+                "try { 9223372036854775807 + 1 } catch (e) { e }",
+                "ArithmeticError: integer overflow: 9223372036854775807 + 1",
+            ),
+            (
+                r#"try { -9223372036854775807 - 2 } catch (e) { e["message"] }"#,
+                r#""integer overflow: -9223372036854775807 - 2""#,
+            ),
+            (
+                "let min = -9223372036854775807 - 1; try { -min } catch (e) { e }",
+                "ArithmeticError: integer overflow: -(-9223372036854775808)",
+            ),
+            (
+                "let min = -9223372036854775807 - 1; try { min / -1 } catch (e) { e }",
+                "ArithmeticError: integer overflow: -9223372036854775808 / -1",
+            ),
+            ("try { 4294967296 * 4294967296 } catch (e) { e[\"kind\"] }", r#""ArithmeticError""#),
+            // a thrown value is bound as it is, with no stack
+            (r#"try { throw "boom"; } catch (e) { e["stack"] }"#, "index operator not supported: STRING[STRING]"),
+            (r#"try { throw error("boom"); } catch (e) { e["message"] }"#, r#""boom""#),
+            // the stack trace is innermost first
+            (
+                r#"let inner = fn() { {}["a"] + 1 }; let outer = fn() { inner() }; try { outer() } catch (e) { e["stack"] }"#,
+                r#"["inner", "outer"]"#,
+            ),
+            (
+                r#"let f = fn(x) { len(x) }; try { f(1) } catch (e) { e["stack"] }"#,
+                r#"["len", "f"]"#,
+            ),
+            (
+                r#"let f = fn() { try { 1 + true } catch (e) { e["stack"] } }; f()"#,
+                r#"["f"]"#,
+            ),
+            // arguments that can't be destructured leave no frame behind
+            (
+                r#"let f = fn([a]) { a }; let g = fn() { f(1) }; let s = fn() { try { g() } catch (e) { e["stack"] } }; [s(), s()]"#,
+                r#"[["g", "s"], ["g", "s"]]"#,
+            ),
+            // errors in function bodies unwind through the call
+            (
+                "let f = fn(n) { if (n == 0) { throw \"done\"; } f(n - 1) }; try { f(3) } catch (e) { e }",
+                r#""done""#,
+            ),
+            // rethrown
+            (
+                r#"try { try { throw 1; } catch (e) { throw e + 1; } } catch (e) { e }"#,
+                "2",
+            ),
+            // uncaught exceptions reach the host
+            (r#"throw "boom";"#, r#"uncaught exception: "boom""#),
+            ("try { throw 1; } catch (e) { 1 + true }", "type mismatch: INTEGER + BOOLEAN"),
+            ("let f = fn() { 1 + true }; f(); 2", "type mismatch: INTEGER + BOOLEAN"),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_try_finally() {
+        let case = [
+            (
+                "let log = []; let r = try { 1 } finally { let log = push(log, 2); }; [r, log]",
+                "[1, [2]]",
+            ),
+            (
+                "let log = []; let r = try { throw 1; } catch (e) { push(log, e) } finally { let log = push(log, 2); }; [r, log]",
+                "[[1], [2]]",
+            ),
+            // the error keeps unwinding after the `finally`
+            (
+                "let log = []; try { try { throw 1; } finally { let log = push(log, 2); } } catch (e) { push(log, e) }",
+                "[2, 1]",
+            ),
+            (
+                "try { try { throw 1; } finally { try { throw 2; } catch (e) { e } } } catch (e) { e }",
+                "1",
+            ),
+            // a `return` in the `finally` wins
+            (
+                "let f = fn() { try { return 1; } finally { return 2; } }; f()",
+                "2",
+            ),
+            (
+                "let f = fn() { try { throw 1; } finally { return 2; } }; f()",
+                "2",
+            ),
+            (
+                "let f = fn() { try { return 1; } finally { 2 } }; f()",
+                "1",
+            ),
+            ("try { 1 } finally { throw 2; }", "uncaught exception: 2"),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_error_values() {
+        let case = [
+            (r#"error("boom")"#, "Error: boom"),
+            (
+                r#"let e = error("boom", [1, 2]); [e["message"], e["payload"]]"#,
+                r#"["boom", [1, 2]]"#,
+            ),
+            (r#"error("boom")["payload"]"#, "null"),
+            (r#"[is_error(error("boom")), is_error(1)]"#, "[true, false]"),
+            ("unwrap(1)", "1"),
+            (r#"unwrap(error("boom"))"#, "called `unwrap` on Error: boom"),
+            (
+                r#"try { unwrap(error("boom")) } catch (e) { e["message"] }"#,
+                r#""called `unwrap` on Error: boom""#,
+            ),
+            (
+                "error(1)",
+                "custom error: argument to `error` must be STRING, got INTEGER",
+            ),
+            // caught runtime errors are error values too
+            ("is_error(try { 1 + true } catch (e) { e })", "true"),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_propagate() {
+        let case = [
+            ("let f = fn(x) { x? + 1 }; f(1)", "2"),
+            (
+                r#"let f = fn(x) { x? + 1 }; f(error("boom"))"#,
+                "Error: boom",
+            ),
+            // returns from the function, not just the expression
+            (
+                r#"let log = []; let f = fn(x) { let y = x?; let log = push(log, y); y }; [f(error("boom")), log]"#,
+                r#"[Error: boom, []]"#,
+            ),
+            (
+                r#"let parse = fn(s) { if (len(s) == 0) { return error("empty"); } len(s) }; let twice = fn(s) { parse(s)? * 2 }; [twice("ab"), twice("")]"#,
+                "[4, Error: empty]",
+            ),
+            (
+                r#"let f = fn(x) { if (true) { [1, x?] } }; f(error("boom"))"#,
+                "Error: boom",
+            ),
+            // from the top level, it ends the program
+            (r#"error("boom")?; 1"#, "Error: boom"),
+            // it is not an exception
+            (
+                r#"let f = fn() { try { error("boom")?; 1 } catch (e) { 2 } }; f()"#,
+                "Error: boom",
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_match() {
+        let describe = r#"let describe = fn(x) {
+            match (x) {
+                0 => "zero",
+                -1 => "minus one",
+                true => "true",
+                "s" => "string",
+                [] => "empty",
+                [first, ...rest] => [first, rest],
+                {"type": "point", "x": x} => x,
+                n if n > 10 => "big",
+                _ => "other",
+            }
+        };"#;
+        let case = [
+            ("describe(0)", r#""zero""#),
+            ("describe(-1)", r#""minus one""#),
+            ("describe(true)", r#""true""#),
+            (r#"describe("s")"#, r#""string""#),
+            ("describe([])", r#""empty""#),
+            ("describe([1, 2, 3])", "[1, [2, 3]]"),
+            ("describe([1])", "[1, []]"),
+            (r#"describe({"type": "point", "x": 4, "y": 5})"#, "4"),
+            ("describe(11)", r#""big""#),
+            ("describe(5)", r#""other""#),
+        ];
+        for (input, expected) in case.iter() {
+            let input = format!("{describe} {input}");
+            assert_eq!(eval_input(&input), *expected, "{input}");
+        }
+
+        let case = [
+            ("match ([1, [2, 3]]) { [a, [b, c]] => a + b + c }", "6"),
+            ("match ([1, 2]) { [a] => a, [a, b] => b }", "2"),
+            (
+                r#"match ({"type": "line"}) { {"type": "point"} => 1, {"x": x} => x, _ => 2 }"#,
+                "2",
+            ),
+            // the guard sees the bindings of its pattern
+            ("match (5) { n if n > 10 => 1, n => n * 2 }", "10"),
+            // bindings only live in their arm, even when the guard fails
+            (
+                "let n = 5; let r = match (20) { n if n > 100 => 1, _ => n }; [r, n]",
+                "[5, 5]",
+            ),
+            ("match (3) { n => n }; n", "identifier not found: n"),
+            ("match (1) { 2 => 2 }", "no match arm for value: 1"),
+            (
+                r#"try { match ("a") {} } catch (e) { e["kind"] }"#,
+                r#""MatchError""#,
+            ),
+            (
+                "let f = fn(x) { match (x) { 0 => 1, _ => 2 } }; f(0) + f(1)",
+                "3",
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_destructuring() {
+        let case = [
+            ("let [a, b] = [1, 2]; a + b", "3"),
+            ("let [a, ...rest] = [1, 2, 3]; rest", "[2, 3]"),
+            ("let [_, [b, c]] = [1, [2, 3]]; b * c", "6"),
+            (r#"let {"x": x, "y": y} = {"x": 1, "y": 2, "z": 3}; x + y"#, "3"),
+            (r#"let {"p": [a, _]} = {"p": [4, 5]}; a"#, "4"),
+            ("let _ = 1; 2", "2"),
+            ("let f = fn([a, b], c) { a + b + c }; f([1, 2], 3)", "6"),
+            (r#"let norm = fn({"x": x, "y": y}) { x * x + y * y }; norm({"x": 1, "y": 2})"#, "5"),
+            ("let [a, 0] = [1, 0]; a", "1"),
+            (
+                "let [a, b] = [1, 2, 3];",
+                "cannot destructure with `[a, b]`: expected an array of 2 elements, got 3",
+            ),
+            (
+                "let [a, b, ...c] = [1];",
+                "cannot destructure with `[a, b, ...c]`: expected an array of at least 2 elements, got 1",
+            ),
+            (
+                "let [a] = 1;",
+                "cannot destructure with `[a]`: expected an array, got INTEGER",
+            ),
+            (
+                r#"let {"x": x} = {"y": 1};"#,
+                r#"cannot destructure with `{"x": x}`: missing key "x""#,
+            ),
+            (
+                "let [a, 0] = [1, 2];",
+                "cannot destructure with `[a, 0]`: expected 0, got 2",
+            ),
+            (
+                "let f = fn([a]) { a }; f(true)",
+                "cannot destructure with `[a]`: expected an array, got BOOLEAN",
+            ),
+            (
+                r#"try { let [a] = []; } catch (e) { e["kind"] }"#,
+                r#""TypeError""#,
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_quote() {
+        let case = [
+            ("quote(5)", "QUOTE(5)"),
+            ("quote(5 + 8)", "QUOTE((5 + 8))"),
+            ("quote(foobar + barfoo)", "QUOTE((foobar + barfoo))"),
+            ("quote(unquote(4 + 4))", "QUOTE(8)"),
+            ("quote(8 + unquote(4 + 4))", "QUOTE((8 + 8))"),
+            ("quote(unquote(4 - 8) * 2)", "QUOTE(((-4) * 2))"),
+            ("quote(unquote(true == false))", "QUOTE(false)"),
+            (r#"quote(len(unquote("a")))"#, r#"QUOTE(len("a"))"#),
+            ("quote(unquote([1, 2]))", "QUOTE([1, 2])"),
+            ("quote(unquote(quote(4 + 4)))", "QUOTE((4 + 4))"),
+            (
+                "let quoted = quote(4 + 4); quote(unquote(4 + 4) + unquote(quoted))",
+                "QUOTE((8 + (4 + 4)))",
+            ),
+            (
+                "quote(1, 2)",
+                "custom error: wrong number of args. got=2, want=1",
+            ),
+            (
+                "quote(unquote(fn(x) { x }))",
+                "custom error: cannot unquote a value of type FUNCTION",
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_macros() {
+        let unless = r#"
+        let unless = macro(condition, consequence, alternative) {
+            quote(if (!(unquote(condition))) {
+                unquote(consequence);
+            } else {
+                unquote(alternative);
+            });
+        };"#;
+        let case = [
+            (
+                r#"unless(10 > 5, "not greater", "greater")"#,
+                r#""greater""#,
+            ),
+            (
+                r#"unless(1 > 5, "not greater", "greater")"#,
+                r#""not greater""#,
+            ),
+            // the arguments are not evaluated, only the branch taken is
+            ("unless(true, missing, 1)", "1"),
+            ("unless", "identifier not found: unless"),
+            (
+                "unless(true, 1)",
+                "custom error: macro unless takes 3 arguments, got 2",
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            let input = format!("{unless} {input}");
+            assert_eq!(eval_input(&input), *expected, "{input}");
+        }
+
+        let case = [
+            (
+                "let infix = macro() { quote(1 + 2) }; let f = fn() { infix() * 3 }; f()",
+                "9",
+            ),
+            // a macro can compute the code it returns
+            (
+                "let twice = macro(x) { let code = quote(unquote(x) + unquote(x)); code }; twice(5)",
+                "10",
+            ),
+            ("let m = macro() { 1 }; m()", "custom error: macro m must return quoted code, got INTEGER"),
+            (
+                "let f = fn() { macro() { quote(1) } }; f()",
+                "custom error: a macro can only be defined by a top-level `let`",
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+
+        // macros stay defined for later programs, like other bindings
+        let mut e = Evaluator::new();
+        for (input, expected) in [
+            ("let one = macro() { quote(1) }; 0", "0"),
+            ("one() + 1", "2"),
+        ] {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            assert_eq!(e.eval(program).unwrap().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_expand_macros() {
+        let input = "let m = macro(a) { quote(unquote(a) * 2) }; let x = m(1 + 1); x";
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        let expanded = Evaluator::new().expand_macros(program).unwrap();
+        let stmts: Vec<String> = expanded.stmts.iter().map(|s| s.to_string()).collect();
+        assert_eq!(stmts, ["let x = ((1 + 1) * 2)", "x"]);
+    }
+
+    #[test]
+    fn test_array_builtins() {
+        let case = [
+            ("map([1, 2, 3], fn(x) { x * 2 })", "[2, 4, 6]"),
+            ("map([], fn(x) { x })", "[]"),
+            ("map([[1], [1, 2]], len)", "[1, 2]"),
+            ("filter([1, 2, 3, 4], fn(x) { x > 2 })", "[3, 4]"),
+            ("reduce([1, 2, 3], fn(acc, x) { acc + x })", "6"),
+            (
+                "reduce([1, 2, 3], fn(acc, x) { push(acc, x) }, [])",
+                "[1, 2, 3]",
+            ),
+            ("reduce([], fn(acc, x) { acc + x }, 0)", "0"),
+            (
+                "reduce([], fn(acc, x) { acc + x })",
+                "custom error: `reduce` of an empty array needs an initial value",
+            ),
+            ("find([1, 2, 3], fn(x) { x > 1 })", "2"),
+            ("find([1, 2, 3], fn(x) { x > 3 })", "null"),
+            (
+                "[any([1, 2], fn(x) { x > 1 }), any([], fn(x) { true })]",
+                "[true, false]",
+            ),
+            (
+                "[all([1, 2], fn(x) { x > 1 }), all([], fn(x) { false })]",
+                "[false, true]",
+            ),
+            ("sort([3, 1, 2])", "[1, 2, 3]"),
+            (r#"sort(["b", "c", "a"])"#, r#"["a", "b", "c"]"#),
+            ("sort([3, 1, 2], fn(a, b) { b - a })", "[3, 2, 1]"),
+            // stable: equal elements keep their order
+            (
+                "sort([[2, 1], [1, 2], [2, 3], [1, 4]], fn(a, b) { a[0] - b[0] })",
+                "[[1, 2], [1, 4], [2, 1], [2, 3]]",
+            ),
+            (
+                r#"sort([1, "a"])"#,
+                "custom error: `sort` cannot compare INTEGER with STRING",
+            ),
+            (
+                "sort([1, 2], fn(a, b) { true })",
+                "custom error: the comparator of `sort` must return INTEGER, got BOOLEAN",
+            ),
+            ("reverse([1, 2, 3])", "[3, 2, 1]"),
+            ("zip([1, 2, 3], [4, 5])", "[[1, 4], [2, 5]]"),
+            (r#"enumerate(["a", "b"])"#, r#"[[0, "a"], [1, "b"]]"#),
+            ("flatten([1, [2, [3]], []])", "[1, 2, [3]]"),
+            ("range(3)", "[0, 1, 2]"),
+            ("range(1, 3)", "[1, 2]"),
+            ("range(5, 0, -2)", "[5, 3, 1]"),
+            ("range(3, 1)", "[]"),
+            (
+                "range(0, 3, 0)",
+                "custom error: the step of `range` must not be 0",
+            ),
+            ("concat([1], [], [2, 3])", "[1, 2, 3]"),
+            ("concat()", "[]"),
+            ("slice([1, 2, 3, 4], 1)", "[2, 3, 4]"),
+            ("slice([1, 2, 3, 4], 1, 3)", "[2, 3]"),
+            ("slice([1, 2, 3, 4], -2)", "[3, 4]"),
+            ("slice([1, 2, 3, 4], 3, 1)", "[]"),
+            ("slice([1, 2, 3, 4], -10, 10)", "[1, 2, 3, 4]"),
+            (
+                "map(1, fn(x) { x })",
+                "custom error: argument to `map` must be ARRAY, got INTEGER",
+            ),
+            (
+                "map([1], 1)",
+                "custom error: argument to `map` must be FUNCTION, got INTEGER",
+            ),
+            (
+                "map([1])",
+                "custom error: wrong number of args. got=1, want=2",
+            ),
+            (
+                "range()",
+                "custom error: wrong number of args. got=0, want=1 to 3",
+            ),
+            // errors in the function stop the builtin and can be caught
+            (
+                "map([1, true], fn(x) { x + 1 })",
+                "type mismatch: BOOLEAN + INTEGER",
+            ),
+            (
+                r#"try { map([1], fn(x) { throw "boom" }) } catch (e) { e }"#,
+                r#""boom""#,
+            ),
+            // closures see the scope they were defined in
+            ("let n = 10; map([1, 2], fn(x) { x + n })", "[11, 12]"),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_hash_builtins() {
+        let case = [
+            (r#"keys({"b": 1, "a": 2})"#, r#"["b", "a"]"#),
+            (r#"values({"b": 1, "a": 2})"#, "[1, 2]"),
+            (r#"entries({"b": 1, "a": 2})"#, r#"[["b", 1], ["a", 2]]"#),
+            (
+                r#"[has({"a": puts()}, "a"), has({"a": 1}, "b")]"#,
+                "[true, false]",
+            ),
+            (
+                r#"delete({"a": 1, "b": 2, "c": 3}, "a")"#,
+                r#"{"b": 2, "c": 3}"#,
+            ),
+            (r#"delete({"a": 1}, "b")"#, r#"{"a": 1}"#),
+            // the hash given is left as it was
+            (r#"let h = {"a": 1}; delete(h, "a"); h"#, r#"{"a": 1}"#),
+            (
+                r#"merge({"a": 1, "b": 2}, {"b": 3, "c": 4})"#,
+                r#"{"a": 1, "b": 3, "c": 4}"#,
+            ),
+            ("merge()", "{}"),
+            (
+                r#"from_entries([["a", 1], [2, true]])"#,
+                r#"{"a": 1, 2: true}"#,
+            ),
+            (
+                r#"let h = {"x": 1, "y": 2}; from_entries(entries(h))"#,
+                r#"{"x": 1, "y": 2}"#,
+            ),
+            (
+                "from_entries([[1]])",
+                "custom error: an entry of `from_entries` must be a [key, value] pair, got [1]",
+            ),
+            (
+                "keys([1])",
+                "custom error: argument to `keys` must be HASH, got ARRAY",
+            ),
+            (
+                r#"has({"a": 1})"#,
+                "custom error: wrong number of args. got=1, want=2",
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_hash_keys() {
+        let case = [
+            (r#"{[1, 2]: "a"}[[1, 2]]"#, r#""a""#),
+            (r#"{[1, 2]: "a"}[[2, 1]]"#, "null"),
+            // hashes are the same key whatever the order of their pairs
+            (r#"{{"a": 1, "b": 2}: 3}[{"b": 2, "a": 1}]"#, "3"),
+            (r#"{{"a": 1}: 3}[{"a": 2}]"#, "null"),
+            // a function is only the same key as itself
+            (r#"let f = fn(x) { x }; {f: "f"}[f]"#, r#""f""#),
+            (
+                r#"let make = fn() { fn(x) { x } }; {make(): "f"}[make()]"#,
+                "null",
+            ),
+            (r#"{len: 1}[len]"#, "1"),
+            (r#"{1: "int", "1": "string", true: "bool"}[1]"#, r#""int""#),
+            ("{puts(): 1}", "unusable as hash key: NULL"),
+            ("{[1, [puts()]]: 1}", "unusable as hash key: NULL"),
+            (r#"{"a": 1}[error("e")]"#, "unusable as hash key: ERROR"),
+            (r#"has({}, [puts()])"#, "unusable as hash key: NULL"),
+            (
+                r#"try { {puts(): 1} } catch (e) { e["kind"] }"#,
+                r#""TypeError""#,
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_structural_eq() {
+        let case = [
+            ("[1, [2, 3]] == [1, [2, 3]]", "true"),
+            ("[1, 2] == [2, 1]", "false"),
+            ("[1, 2] != [1, 2, 3]", "true"),
+            (r#"{"a": 1, "b": [2]} == {"b": [2], "a": 1}"#, "true"),
+            (r#"{"a": 1} != {"a": 2}"#, "true"),
+            ("let f = fn() { 1 }; [f] == [f]", "true"),
+            ("[fn() { 1 }] == [fn() { 1 }]", "false"),
+            ("[1] < [2]", "unknown operator: ARRAY < ARRAY"),
+            ("[1] == {}", "type mismatch: ARRAY == HASH"),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_string_ops() {
+        let case = [
+            (r#""héllo"[1]"#, r#""é""#),
+            (r#""abc"[3]"#, "null"),
+            (r#""abc"[-1]"#, r#""c""#),
+            (r#""abc"[-4]"#, "null"),
+            (r#""ab" * 3"#, r#""ababab""#),
+            (r#""ab" * 0"#, r#""""#),
+            (r#""ab" * -1"#, r#""""#),
+            (r#""" * 9223372036854775807"#, r#""""#),
+            (
+                r#""ab" * 9223372036854775807"#,
+                "string too long: 2 bytes repeated 9223372036854775807 times",
+            ),
+            (
+                r#"try { "ab" * 1073741824 } catch (e) { e["kind"] }"#,
+                r#""ArithmeticError""#,
+            ),
+            (
+                r#"["a" < "b", "b" > "a", "a" < "a"]"#,
+                "[true, true, false]",
+            ),
+            (r#"["a" == "a", "a" != "a"]"#, "[true, false]"),
+            (r#""a" - "b""#, "unknown operator: STRING - STRING"),
+            (r#"3 * "ab""#, "type mismatch: INTEGER * STRING"),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_string_builtins() {
+        let case = [
+            (r#"split("a,b,,c", ",")"#, r#"["a", "b", "", "c"]"#),
+            (r#"split("hé", "")"#, r#"["h", "é"]"#),
+            (r#"join(["a", 1, true], "-")"#, r#""a-1-true""#),
+            (r#"join([], ", ")"#, r#""""#),
+            (r#"trim("  a b  ")"#, r#""a b""#),
+            (
+                r#"[upper("héllo"), lower("HÉLLO")]"#,
+                r#"["HÉLLO", "héllo"]"#,
+            ),
+            (r#"replace("a-b-c", "-", "+")"#, r#""a+b+c""#),
+            (
+                r#"[contains("hello", "ell"), contains("hello", "x")]"#,
+                "[true, false]",
+            ),
+            (
+                r#"[starts_with("hello", "he"), ends_with("hello", "he")]"#,
+                "[true, false]",
+            ),
+            (
+                r#"[index_of("héllo", "l"), index_of("hello", "x")]"#,
+                "[2, -1]",
+            ),
+            (r#"chars("hé")"#, r#"["h", "é"]"#),
+            (r#"slice("héllo", 1, 3)"#, r#""él""#),
+            (r#"slice("héllo", -2)"#, r#""lo""#),
+            (r#"format("{} is {}", "x", [1])"#, r#""x is [1]""#),
+            (r#"format("{{}} {}", 1)"#, r#""{} 1""#),
+            (
+                r#"format("{} {}", 1)"#,
+                "custom error: `format` has more `{}` than arguments",
+            ),
+            (
+                r#"format("{}", 1, 2)"#,
+                "custom error: `format` has more arguments than `{}`",
+            ),
+            (
+                r#"upper(1)"#,
+                "custom error: argument to `upper` must be STRING, got INTEGER",
+            ),
+            (
+                r#"slice(1, 0)"#,
+                "custom error: argument to `slice` must be ARRAY or STRING, got INTEGER",
+            ),
+            // parsing a log line
+            (
+                r#"let [level, msg] = map(split("WARN: disk full", ":"), trim); format("[{}] {}", lower(level), msg)"#,
+                r#""[warn] disk full""#,
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_slice_expr() {
+        let case = [
+            ("[1, 2, 3, 4][1:3]", "[2, 3]"),
+            ("[1, 2, 3, 4][:2]", "[1, 2]"),
+            ("[1, 2, 3, 4][2:]", "[3, 4]"),
+            ("[1, 2, 3, 4][:]", "[1, 2, 3, 4]"),
+            ("[1, 2, 3, 4][::2]", "[1, 3]"),
+            ("[1, 2, 3, 4][::-1]", "[4, 3, 2, 1]"),
+            ("[1, 2, 3, 4][-3:-1]", "[2, 3]"),
+            ("[1, 2, 3, 4][3:1]", "[]"),
+            ("[1, 2, 3, 4][-10:10]", "[1, 2, 3, 4]"),
+            ("let n = 2; [1, 2, 3, 4][n - 1:n + 1]", "[2, 3]"),
+            (r#""héllo"[1:3]"#, r#""él""#),
+            (r#""héllo"[::-1]"#, r#""olléh""#),
+            ("[1, 2][::0]", "slice step cannot be zero"),
+            (
+                r#"[1, 2]["a":]"#,
+                "index operator not supported: ARRAY[STRING]",
+            ),
+            ("{}[1:]", "index operator not supported: HASH[SLICE]"),
+            (
+                "try { [1][::0] } catch (e) { e[\"kind\"] }",
+                r#""IndexError""#,
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let case = [
+            ("[1, 2, 3][1]", "2"),
+            ("[1, 2, 3][-1]", "3"),
+            (
+                "[1, 2, 3][3]",
+                "index out of bounds: the length is 3 but the index is 3",
+            ),
+            (
+                "[1, 2, 3][-4]",
+                "index out of bounds: the length is 3 but the index is -4",
+            ),
+            (
+                r#""ab"[2]"#,
+                "index out of bounds: the length is 2 but the index is 2",
+            ),
+            // slices are clamped instead
+            ("[1, 2, 3][1:10]", "[2, 3]"),
+            // and so is everything inside functions
+            (
+                "let f = fn(a) { a[5] }; f([1])",
+                "index out of bounds: the length is 1 but the index is 5",
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            let mut e = Evaluator::new();
+            e.set_out_of_bounds(OutOfBounds::Error);
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            let result = match e.eval(program) {
+                Ok(r) => r.to_string(),
+                Err(e) => e.to_string(),
+            };
+            assert_eq!(result, *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_math() {
+        let case = [
+            (r#"math["abs"](-3)"#, "3"),
+            (r#"math["abs"](0 - math["pi"])"#, "3.141592653589793"),
+            (r#"[math["min"](3, 1, 2), math["max"](3, 1, 2)]"#, "[1, 3]"),
+            (r#"math["max"](1, math["e"])"#, "2.718281828459045"),
+            (r#"math["pow"](2, 10)"#, "1024"),
+            (r#"math["pow"](2, -1)"#, "0.5"),
+            (r#"math["sqrt"](16)"#, "4.0"),
+            (
+                r#"[math["floor"](math["pi"]), math["ceil"](math["pi"]), math["round"](math["e"])]"#,
+                "[3, 4, 3]",
+            ),
+            (r#"math["floor"](-7)"#, "-7"),
+            (r#"math["sin"](0)"#, "0.0"),
+            (r#"math["cos"](0)"#, "1.0"),
+            (r#"math["atan2"](1, 1) * 4 == math["pi"]"#, "true"),
+            (r#"math["log"](8, 2)"#, "3.0"),
+            // floats mix with integers in arithmetic
+            (r#"math["sqrt"](4) + 1"#, "3.0"),
+            (r#"-math["sqrt"](4) < 1"#, "true"),
+            (r#"math["sqrt"](4) == 2"#, "true"),
+            (r#"math["pow"](2, 64)"#, "integer overflow: pow(2, 64)"),
+            (
+                r#"math["abs"](-9223372036854775807 - 1)"#,
+                "integer overflow: abs(-9223372036854775808)",
+            ),
+            (
+                r#"try { math["pow"](10, 30) } catch (e) { e["kind"] }"#,
+                r#""ArithmeticError""#,
+            ),
+            (
+                r#"math["floor"](math["sqrt"](-1))"#,
+                "custom error: `floor` of NaN is not an integer",
+            ),
+            (
+                r#"math["sqrt"]("4")"#,
+                "custom error: argument to `sqrt` must be INTEGER or FLOAT, got STRING",
+            ),
+            (
+                r#"math["min"]()"#,
+                "custom error: wrong number of args. got=0, want=at least 1",
+            ),
+            (r#"{math["sqrt"](2): 1}"#, "unusable as hash key: FLOAT"),
+            (r#"math["nope"]"#, "module math has no export nope"),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_random() {
+        let input = r#"
+            let f = fn() { random["float"]() };
+            [f(), random["int"](1, 7), random["choice"]([1, 2, 3]), random["shuffle"](range(5))]
+        "#;
+        let run = |seed| {
+            let mut e = Evaluator::new();
+            e.seed_random(seed);
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            e.eval(program).unwrap()
+        };
+        // the same seed gives the same numbers, however they are reached
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+
+        let case = [
+            (
+                r#"let x = random["float"](); [x > -1, x < 1]"#,
+                "[true, true]",
+            ),
+            (
+                r#"all(map(range(50), fn(_) { random["int"](-2, 2) }), fn(x) { if (x > -3) { x < 2 } else { false } })"#,
+                "true",
+            ),
+            (r#"sort(random["shuffle"]([3, 1, 2]))"#, "[1, 2, 3]"),
+            (r#"random["int"](2, 3)"#, "2"),
+            (
+                r#"random["int"](3, 3)"#,
+                "custom error: `int` needs a start below the end, got 3 and 3",
+            ),
+            (
+                r#"random["choice"]([])"#,
+                "custom error: `choice` of an empty array",
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        let case = [
+            (
+                r#"json_parse("[1, 2.5, true, null]")"#,
+                "[1, 2.5, true, null]",
+            ),
+            (
+                r#"json_stringify({"a": [1, true], "b": "x"})"#,
+                r#""{"a":[1,true],"b":"x"}""#,
+            ),
+            (
+                r#"json_parse(json_stringify({"a": [1, {}]}, 2)) == {"a": [1, {}]}"#,
+                "true",
+            ),
+            (
+                r#"json_stringify([1, fn(x) { x }])"#,
+                "unencodable as JSON: FUNCTION",
+            ),
+            (
+                r#"json_stringify({1: 2})"#,
+                "unencodable as JSON: INTEGER hash key",
+            ),
+            (
+                r#"json_parse("[1, }")"#,
+                "invalid JSON at line 1 column 5: expected value",
+            ),
+            (
+                r#"try { json_parse("") } catch (e) { e["kind"] }"#,
+                r#""JsonError""#,
+            ),
+            (
+                r#"json_stringify(1, -1)"#,
+                "custom error: the indent of `json_stringify` must be from 0 to 16, got -1",
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            assert_eq!(eval_input(input), *expected, "{input}");
+        }
+
+        // string literals can't hold a `"`, so JSON objects come from the
+        // host, either as text or already parsed
+        let mut e = Evaluator::new();
+        let text = Object::String(r#"{"b": [1, 2.5], "a": null}"#.to_string());
+        e.set_val_to_env("text".to_string(), text);
+        let payload = rmonkey_object::json::from_json(r#"{"n": 2}"#).unwrap();
+        e.set_val_to_env("payload".to_string(), payload);
+        let input = r#"let o = json_parse(text); [o["b"][1], keys(o), payload["n"] * 3]"#;
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        assert_eq!(
+            e.eval(program).unwrap().to_string(),
+            r#"[2.5, ["b", "a"], 6]"#
+        );
+    }
+
+    #[test]
+    fn test_register_fn() {
+        let mut e = Evaluator::new();
+        // closures can capture the host's state
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&log);
+        e.register_fn("log", move |line: String| sink.borrow_mut().push(line));
+        e.register_fn("sum", |xs: Vec<i64>| xs.iter().sum::<i64>());
+        e.register_fn(
+            "pick",
+            |flag: bool, a: Object, b: Object| {
+                if flag {
+                    a
+                } else {
+                    b
+                }
+            },
+        );
+        e.register_fn("checked_div", |a: i64, b: i64| {
+            a.checked_div(b)
+                .ok_or_else(|| "division by zero".to_string())
+        });
+        let mut geo = Namespace::new("geo");
+        geo.register_fn("dist", |x: f64, y: f64| (x * x + y * y).sqrt())
+            .register_fn("origin", || vec![0, 0]);
+        e.register_namespace(geo);
+
+        let case = [
+            (r#"log("a"); log("b")"#, "null"),
+            ("sum([1, 2, 3])", "6"),
+            ("map([[1], [2, 3]], sum)", "[1, 5]"),
+            (r#"pick(false, 1, "x")"#, r#""x""#),
+            (r#"[geo["dist"](3, 4), geo["origin"]()]"#, "[5.0, [0, 0]]"),
+            (
+                "sum([1, true])",
+                "custom error: argument to `sum` must be ARRAY of INTEGER, got ARRAY containing BOOLEAN",
+            ),
+            (
+                "sum(1)",
+                "custom error: argument to `sum` must be ARRAY of INTEGER, got INTEGER",
+            ),
+            (
+                "pick(true)",
+                "custom error: wrong number of args. got=1, want=3",
+            ),
+            (
+                r#"geo["dist"]("3", 4)"#,
+                "custom error: argument to `dist` must be INTEGER or FLOAT, got STRING",
+            ),
+            (
+                r#"try { checked_div(1, 0) } catch (e) { e["message"] }"#,
+                r#""division by zero""#,
+            ),
+        ];
+        for (input, expected) in case.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            let result = match e.eval(program) {
+                Ok(r) => r.to_string(),
+                Err(e) => e.to_string(),
+            };
+            assert_eq!(result, *expected, "{input}");
+        }
+        assert_eq!(*log.borrow(), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_serde_bridge() {
+        #[derive(serde::Serialize)]
+        struct Config {
+            base: i64,
+            names: Vec<String>,
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Report {
+            total: i64,
+            greeting: Option<String>,
+            lengths: Vec<i64>,
+        }
+
+        let config = Config {
+            base: 10,
+            names: vec!["ab".to_string(), "cde".to_string()],
+        };
+        let mut e = Evaluator::new();
+        e.set_val_to_env(
+            "config".to_string(),
+            rmonkey_object::to_object(&config).unwrap(),
+        );
+        let input = r#"
+            let lengths = map(config["names"], len);
+            {"total": reduce(lengths, fn(a, b) { a + b }, config["base"]), "greeting": puts(), "lengths": lengths}
+        "#;
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        let report: Report = rmonkey_object::from_object(e.eval(program).unwrap()).unwrap();
+        assert_eq!(
+            report,
+            Report {
+                total: 15,
+                greeting: None,
+                lengths: vec![2, 3],
+            }
+        );
+    }
 }
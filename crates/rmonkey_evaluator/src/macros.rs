@@ -0,0 +1,150 @@
+//! Macros, which run before the program does.
+//!
+//! `quote(x)` evaluates to the code `x` instead of its value, with each
+//! `unquote(y)` inside it replaced by the code of `y`'s value. A macro is a
+//! `macro(a, b) { }` bound by a top-level `let`: its parameters are bound to
+//! the quoted arguments of each call, and the quoted code it returns takes
+//! the place of the call.
+
+use std::{cell::RefCell, rc::Rc};
+
+use rmonkey_ast::{
+    modify::{modify_expr, modify_stmts},
+    operator::Prefix,
+    pattern::Pattern,
+    Expr, Program, Stmt,
+};
+use rmonkey_error::{RMonkeyError, Result};
+use rmonkey_object::{scope::Scope, Object};
+
+use crate::Evaluator;
+
+impl Evaluator {
+    /// `program` with its macro definitions taken out and every call of a
+    /// macro replaced by the code it returns. the macros stay defined for
+    /// the programs evaluated after it. the expanded program has no spans,
+    /// since its code is not the source's.
+    pub fn expand_macros(&mut self, program: Program) -> Result<Program> {
+        let mut stmts = Vec::new();
+        for stmt in program.stmts {
+            match stmt {
+                Stmt::LetStmt {
+                    name: Pattern::Ident(name),
+                    value: Expr::Macro { params, body },
+                    ..
+                } => {
+                    let scope = Scope::new_enclosed_environment(Rc::clone(&self.macros));
+                    let r#macro = Object::Macro {
+                        params,
+                        body,
+                        scope,
+                    };
+                    self.macros.borrow_mut().set(name, r#macro);
+                }
+                stmt => stmts.push(stmt),
+            }
+        }
+        modify_stmts(&mut stmts, &mut |expr| self.expand_macro_call(expr))?;
+        Ok(Program::new(stmts))
+    }
+
+    /// replace `expr` by the code the macro returns, if it calls one
+    fn expand_macro_call(&mut self, expr: &mut Expr) -> Result<()> {
+        let Expr::Call { callee, args } = expr else {
+            return Ok(());
+        };
+        let Expr::Ident(name) = callee.as_ref() else {
+            return Ok(());
+        };
+        let Some(Object::Macro {
+            params,
+            body,
+            scope,
+        }) = self.macros.borrow().get(name.to_string())
+        else {
+            return Ok(());
+        };
+        if params.len() != args.len() {
+            return Err(RMonkeyError::Custom(format!(
+                "macro {name} takes {} arguments, got {}",
+                params.len(),
+                args.len()
+            )));
+        }
+
+        let mut macro_env = Evaluator {
+            env: Rc::new(RefCell::new(scope)),
+            builtin: Rc::clone(&self.builtin),
+            modules: Rc::clone(&self.modules),
+            module: self.module.clone(),
+            stack: Rc::clone(&self.stack),
+            out_of_bounds: self.out_of_bounds,
+            rng: Rc::clone(&self.rng),
+            macros: Rc::clone(&self.macros),
+        };
+        for (param, arg) in params.into_iter().zip(args.iter()) {
+            macro_env.set_val_to_env(param, Object::Quote(Box::new(arg.clone())));
+        }
+        let code = match macro_env.eval_stmt(&body)? {
+            Object::ReturnValue(value) => *value,
+            value => value,
+        };
+        match code {
+            Object::Quote(code) => {
+                *expr = *code;
+                Ok(())
+            }
+            value => Err(RMonkeyError::Custom(format!(
+                "macro {name} must return quoted code, got {}",
+                value.obj_type()
+            ))),
+        }
+    }
+
+    /// the code of the argument of `quote`, with its `unquote`s evaluated
+    pub(crate) fn eval_quote(&mut self, args: &[Expr]) -> Result<Object> {
+        let [code] = args else {
+            return Err(RMonkeyError::Custom(format!(
+                "wrong number of args. got={}, want=1",
+                args.len()
+            )));
+        };
+        let mut code = code.clone();
+        modify_expr(&mut code, &mut |expr| {
+            if let Expr::Call { callee, args } = expr {
+                if let (Expr::Ident(name), [value]) = (callee.as_ref(), args.as_slice()) {
+                    if name == "unquote" {
+                        let value = self.eval_expr(value)?;
+                        *expr = code_of(value)?;
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        Ok(Object::Quote(Box::new(code)))
+    }
+}
+
+/// the code that evaluates to `value`
+fn code_of(value: Object) -> Result<Expr> {
+    match value {
+        Object::Int(val) => match val.checked_neg() {
+            // negative values are `-n`, the way the parser reads them
+            Some(abs) if val < 0 => Ok(Expr::PrefixExpr {
+                op: Prefix::Minus,
+                right: Box::new(Expr::IntLiteral(abs)),
+            }),
+            _ => Ok(Expr::IntLiteral(val)),
+        },
+        Object::Bool(val) => Ok(Expr::BoolLiteral(val)),
+        Object::String(val) => Ok(Expr::StringLiteral(val)),
+        Object::Array { elements } => Ok(Expr::Array {
+            elements: elements.into_iter().map(code_of).collect::<Result<_>>()?,
+        }),
+        Object::Quote(code) => Ok(*code),
+        value => Err(RMonkeyError::Custom(format!(
+            "cannot unquote a value of type {}",
+            value.obj_type()
+        ))),
+    }
+}
@@ -0,0 +1,228 @@
+//! Loading of `import`ed modules.
+//!
+//! The evaluator asks a [`ModuleHost`] to turn an import path into a module
+//! id and to read the module's source, so that the same language runs
+//! against the file system ([`FsHost`]) or against sources held in memory
+//! ([`MemoryHost`], e.g. in the wasm playground). Each module is evaluated
+//! once, in a scope of its own, and its `export let`s are cached by id.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rmonkey_object::Object;
+
+pub trait ModuleHost {
+    /// the id of the module that `path` refers to when imported from the
+    /// module `from`, or from the main program when `from` is `None`.
+    /// `None` if there is no such module. every module must have a single
+    /// id, since ids are what cycles and the cache are checked against.
+    fn resolve(&self, path: &str, from: Option<&str>) -> Option<String>;
+
+    /// the source of the module `id`
+    fn load(&self, id: &str) -> Result<String, String>;
+}
+
+/// whether `path` is relative to the importing module rather than to the
+/// search path
+fn is_relative(path: &str) -> bool {
+    path.starts_with("./") || path.starts_with("../")
+}
+
+/// loads modules from files. a `./` or `../` path is relative to the
+/// directory of the importing module, or to the current directory for the
+/// main program. any other path is looked up in each directory of the
+/// search path in turn. ids are canonical file paths, and only files inside
+/// a directory of the search path can be imported.
+#[derive(Debug, Clone)]
+pub struct FsHost {
+    search_path: Vec<PathBuf>,
+}
+
+impl FsHost {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        FsHost { search_path }
+    }
+
+    /// the current directory, followed by the directories of the
+    /// `RMONKEY_PATH` environment variable
+    pub fn from_env() -> Self {
+        let mut search_path = vec![PathBuf::from(".")];
+        if let Some(paths) = std::env::var_os("RMONKEY_PATH") {
+            search_path.extend(std::env::split_paths(&paths));
+        }
+        FsHost::new(search_path)
+    }
+}
+
+impl Default for FsHost {
+    fn default() -> Self {
+        FsHost::new(vec![PathBuf::from(".")])
+    }
+}
+
+impl ModuleHost for FsHost {
+    fn resolve(&self, path: &str, from: Option<&str>) -> Option<String> {
+        let candidates: Vec<PathBuf> = if is_relative(path) {
+            let dir = from
+                .and_then(|from| Path::new(from).parent())
+                .unwrap_or(Path::new("."));
+            vec![dir.join(path)]
+        } else {
+            self.search_path.iter().map(|dir| dir.join(path)).collect()
+        };
+        let roots: Vec<PathBuf> = self
+            .search_path
+            .iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .collect();
+        candidates
+            .into_iter()
+            .filter(|candidate| candidate.is_file())
+            .filter_map(|file| file.canonicalize().ok())
+            .find(|file| roots.iter().any(|root| file.starts_with(root)))
+            .map(|file| file.display().to_string())
+    }
+
+    fn load(&self, id: &str) -> Result<String, String> {
+        fs::read_to_string(id).map_err(|err| err.to_string())
+    }
+}
+
+/// modules given as sources keyed by `/`-separated paths, like
+/// `lib/math.monkey`. a `./` or `../` path is relative to the directory of
+/// the importing module, any other path is a key as is.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryHost {
+    modules: HashMap<String, String>,
+}
+
+impl MemoryHost {
+    pub fn new(modules: HashMap<String, String>) -> Self {
+        MemoryHost { modules }
+    }
+
+    pub fn insert(&mut self, path: impl Into<String>, source: impl Into<String>) {
+        self.modules.insert(path.into(), source.into());
+    }
+}
+
+impl ModuleHost for MemoryHost {
+    fn resolve(&self, path: &str, from: Option<&str>) -> Option<String> {
+        let id = if is_relative(path) {
+            let mut parts: Vec<&str> = from.map_or(Vec::new(), |from| from.split('/').collect());
+            // the importing module's file name
+            parts.pop();
+            for part in path.split('/') {
+                match part {
+                    "." => {}
+                    ".." => {
+                        parts.pop()?;
+                    }
+                    part => parts.push(part),
+                }
+            }
+            parts.join("/")
+        } else {
+            path.to_string()
+        };
+        self.modules.contains_key(&id).then_some(id)
+    }
+
+    fn load(&self, id: &str) -> Result<String, String> {
+        self.modules
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("no module {id}"))
+    }
+}
+
+/// the modules of one program run, shared by every evaluator of the run
+pub(crate) struct Modules {
+    pub(crate) host: Box<dyn ModuleHost>,
+    /// evaluated modules by id
+    pub(crate) cache: HashMap<String, Object>,
+    /// ids of the modules being evaluated, outermost first
+    pub(crate) loading: Vec<String>,
+}
+
+impl Modules {
+    pub(crate) fn new(host: impl ModuleHost + 'static) -> Self {
+        Modules {
+            host: Box::new(host),
+            cache: HashMap::new(),
+            loading: Vec::new(),
+        }
+    }
+}
+
+/// no module can be imported, unless the embedder passes a host
+impl Default for Modules {
+    fn default() -> Self {
+        Modules::new(MemoryHost::default())
+    }
+}
+
+impl std::fmt::Debug for Modules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Modules")
+            .field("cache", &self.cache.keys())
+            .field("loading", &self.loading)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_resolve() {
+        let mut host = MemoryHost::default();
+        host.insert("main.monkey", "");
+        host.insert("lib/a.monkey", "");
+        host.insert("lib/b.monkey", "");
+        assert_eq!(
+            host.resolve("lib/a.monkey", None),
+            Some("lib/a.monkey".to_string())
+        );
+        assert_eq!(
+            host.resolve("./b.monkey", Some("lib/a.monkey")),
+            Some("lib/b.monkey".to_string())
+        );
+        assert_eq!(
+            host.resolve("../main.monkey", Some("lib/a.monkey")),
+            Some("main.monkey".to_string())
+        );
+        assert_eq!(
+            host.resolve("../../main.monkey", Some("lib/a.monkey")),
+            None
+        );
+        assert_eq!(host.resolve("c.monkey", None), None);
+    }
+
+    #[test]
+    fn test_fs_resolve() {
+        let dir = std::env::temp_dir().join(format!("rmonkey_modules_{}", std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(dir.join("lib/a.monkey"), "").unwrap();
+        fs::write(dir.join("b.monkey"), "").unwrap();
+        let host = FsHost::new(vec![dir.join("lib"), dir.clone()]);
+
+        let a = host.resolve("a.monkey", None).unwrap();
+        assert!(a.ends_with("a.monkey"), "{a}");
+        let b = host.resolve("../b.monkey", Some(&a)).unwrap();
+        assert_eq!(host.resolve("b.monkey", None), Some(b));
+        assert_eq!(host.resolve("./b.monkey", Some(&a)), None);
+
+        // files outside the search path can't be imported
+        let host = FsHost::new(vec![dir.join("lib")]);
+        assert_eq!(host.resolve("../b.monkey", Some(&a)), None);
+        let absolute = dir.join("b.monkey").display().to_string();
+        assert_eq!(host.resolve(&absolute, None), None);
+        assert_eq!(host.resolve(&absolute, Some(&a)), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+//! Matching of values against patterns, for `match` arms and for `let`s
+//! and parameters that destructure their value.
+
+use rmonkey_ast::{operator::Prefix, pattern::Pattern, Expr};
+use rmonkey_object::Object;
+
+/// match `value` against `pattern` and push the names the pattern binds to
+/// `bindings`, which is left partly filled when it doesn't match. the error
+/// tells why it doesn't.
+pub(crate) fn bind(
+    pattern: &Pattern,
+    value: &Object,
+    bindings: &mut Vec<(String, Object)>,
+) -> Result<(), String> {
+    match pattern {
+        Pattern::Wildcard => Ok(()),
+        Pattern::Ident(name) => {
+            bindings.push((name.clone(), value.clone()));
+            Ok(())
+        }
+        Pattern::Literal(literal) => match literal_value(literal) {
+            Some(expected) if expected == *value => Ok(()),
+            _ => Err(format!("expected {pattern}, got {value}")),
+        },
+        Pattern::Array { elements, rest } => {
+            let Object::Array { elements: values } = value else {
+                return Err(format!("expected an array, got {}", value.obj_type()));
+            };
+            match rest {
+                Some(_) if values.len() < elements.len() => {
+                    return Err(format!(
+                        "expected an array of at least {} elements, got {}",
+                        elements.len(),
+                        values.len()
+                    ))
+                }
+                None if values.len() != elements.len() => {
+                    return Err(format!(
+                        "expected an array of {} elements, got {}",
+                        elements.len(),
+                        values.len()
+                    ))
+                }
+                _ => {}
+            }
+            for (pattern, value) in elements.iter().zip(values.iter()) {
+                bind(pattern, value, bindings)?;
+            }
+            match rest {
+                Some(rest) => {
+                    let rest_values = Object::Array {
+                        elements: values[elements.len()..].to_vec(),
+                    };
+                    bind(rest, &rest_values, bindings)
+                }
+                None => Ok(()),
+            }
+        }
+        Pattern::Hash { pairs } => {
+            let Object::Hash(hash) = value else {
+                return Err(format!("expected a hash, got {}", value.obj_type()));
+            };
+            for (key, pattern) in pairs.iter() {
+                match literal_value(key).and_then(|key| hash.get(&key)) {
+                    Some(value) => bind(pattern, value, bindings)?,
+                    None => return Err(format!("missing key {key}")),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// the value of a literal of a pattern
+fn literal_value(literal: &Expr) -> Option<Object> {
+    match literal {
+        Expr::IntLiteral(val) => Some(Object::Int(*val)),
+        Expr::BoolLiteral(val) => Some(Object::Bool(*val)),
+        Expr::StringLiteral(val) => Some(Object::String(val.clone())),
+        Expr::PrefixExpr {
+            op: Prefix::Minus,
+            right,
+        } => match literal_value(right)? {
+            Object::Int(val) => Some(Object::Int(-val)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,367 @@
+//! Compact printer for generated code.
+//!
+//! Unlike [`Formatter`](crate::Formatter), which lays code out for people,
+//! this printer only promises that its output parses back to an equal AST.
+//! Parentheses are inserted exactly where precedence or associativity would
+//! otherwise change the tree.
+//!
+//! Trees built with `rmonkey_ast::builder` and trees produced by the parser
+//! always round-trip. A `Stmt::BlockStmt` is only printable as the body of
+//! an `if`, `fn`, `macro` or `try`, because that is the only place the
+//! parser produces one.
+
+use rmonkey_ast::{operator::Infix, precedence::Precedence, Catch, Expr, Program, Stmt};
+
+pub fn print(program: &Program) -> String {
+    let stmts: Vec<String> = program.stmts.iter().map(print_stmt).collect();
+    stmts.join("\n")
+}
+
+pub fn print_stmt(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::LetStmt {
+            export,
+            name,
+            ty,
+            value,
+        } => {
+            let export = if *export { "export " } else { "" };
+            match ty {
+                Some(ty) => format!("{export}let {name}: {ty} = {};", print_expr(value)),
+                None => format!("{export}let {name} = {};", print_expr(value)),
+            }
+        }
+        Stmt::ReturnStmt(value) => format!("return {};", print_expr(value)),
+        Stmt::ExprStmt(expr) => format!("{};", print_expr(expr)),
+        Stmt::BlockStmt { stmts } => print_block(stmts),
+        Stmt::Import { path, alias } => format!(r#"import "{path}" as {alias};"#),
+        Stmt::Throw(value) => format!("throw {};", print_expr(value)),
+    }
+}
+
+pub fn print_expr(expr: &Expr) -> String {
+    match expr {
+        Expr::Ident(val) => val.to_string(),
+        Expr::IntLiteral(val) => val.to_string(),
+        Expr::BoolLiteral(val) => val.to_string(),
+        Expr::StringLiteral(val) => format!(r#""{val}""#),
+        Expr::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            let cond = print_expr(condition);
+            let cons = print_body(consequence);
+            match alternative {
+                Some(alt) => format!("if ({cond}) {cons} else {}", print_body(alt)),
+                None => format!("if ({cond}) {cons}"),
+            }
+        }
+        Expr::PrefixExpr { op, right } => {
+            format!("{op}{}", print_operand(right, Precedence::Prefix, false))
+        }
+        Expr::InfixExpr { left, right, op } => {
+            let prec = infix_precedence(op);
+            let l = print_operand(left, prec.clone(), false);
+            let r = print_operand(right, prec, true);
+            format!("{l} {op} {r}")
+        }
+        Expr::Func {
+            params,
+            param_types,
+            ret,
+            body,
+        } => {
+            let params: Vec<String> = params
+                .iter()
+                .enumerate()
+                .map(|(i, param)| match param_types.get(i) {
+                    Some(Some(ty)) => format!("{param}: {ty}"),
+                    _ => param.to_string(),
+                })
+                .collect();
+            let ret = match ret {
+                Some(ty) => format!(" -> {ty}"),
+                None => String::new(),
+            };
+            format!("fn({}){ret} {}", params.join(", "), print_body(body))
+        }
+        Expr::Call { callee, args } => {
+            let callee = print_operand(callee, Precedence::Call, false);
+            format!("{callee}({})", print_list(args))
+        }
+        Expr::Array { elements } => format!("[{}]", print_list(elements)),
+        Expr::IndexExpr { left, index } => {
+            let left = print_operand(left, Precedence::Call, false);
+            format!("{left}[{}]", print_expr(index))
+        }
+        Expr::Slice {
+            left,
+            start,
+            end,
+            step,
+        } => {
+            let left = print_operand(left, Precedence::Call, false);
+            let bound =
+                |bound: &Option<Box<Expr>>| bound.as_deref().map(print_expr).unwrap_or_default();
+            match step {
+                Some(step) => format!(
+                    "{left}[{}:{}:{}]",
+                    bound(start),
+                    bound(end),
+                    print_expr(step)
+                ),
+                None => format!("{left}[{}:{}]", bound(start), bound(end)),
+            }
+        }
+        Expr::Propagate { value } => {
+            format!("{}?", print_operand(value, Precedence::Call, false))
+        }
+        Expr::HashLiteral { pairs } => {
+            let pairs: Vec<String> = pairs
+                .iter()
+                .map(|(key, val)| format!("{}: {}", print_expr(key), print_expr(val)))
+                .collect();
+            format!("{{{}}}", pairs.join(", "))
+        }
+        Expr::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            let mut code = format!("try {}", print_body(body));
+            if let Some(catch) = catch {
+                let Catch { param, body } = catch.as_ref();
+                code.push_str(&format!(" catch ({param}) {}", print_body(body)));
+            }
+            if let Some(finally) = finally {
+                code.push_str(&format!(" finally {}", print_body(finally)));
+            }
+            code
+        }
+        Expr::Match { value, arms } => {
+            let arms: Vec<String> = arms
+                .iter()
+                .map(|arm| match &arm.guard {
+                    Some(guard) => format!(
+                        "{} if {} => {}",
+                        arm.pattern,
+                        print_expr(guard),
+                        print_expr(&arm.body)
+                    ),
+                    None => format!("{} => {}", arm.pattern, print_expr(&arm.body)),
+                })
+                .collect();
+            if arms.is_empty() {
+                return format!("match ({}) {{}}", print_expr(value));
+            }
+            format!("match ({}) {{ {} }}", print_expr(value), arms.join(", "))
+        }
+        Expr::Macro { params, body } => {
+            format!("macro({}) {}", params.join(", "), print_body(body))
+        }
+    }
+}
+
+/// print `expr` as an operand that binds at least as tightly as `prec`.
+/// the right operand of a left-associative operator must bind tighter.
+fn print_operand(expr: &Expr, prec: Precedence, strict: bool) -> String {
+    let expr_prec = expr_precedence(expr);
+    let needs_paren = if strict {
+        expr_prec <= prec
+    } else {
+        expr_prec < prec
+    };
+    if needs_paren {
+        format!("({})", print_expr(expr))
+    } else {
+        print_expr(expr)
+    }
+}
+
+fn expr_precedence(expr: &Expr) -> Precedence {
+    match expr {
+        Expr::PrefixExpr { .. } => Precedence::Prefix,
+        Expr::InfixExpr { op, .. } => infix_precedence(op),
+        Expr::Call { .. } => Precedence::Call,
+        _ => Precedence::Index,
+    }
+}
+
+fn infix_precedence(op: &Infix) -> Precedence {
+    match op {
+        Infix::Eq | Infix::NotEq => Precedence::Equals,
+        Infix::Lt | Infix::Gt => Precedence::LessGreater,
+        Infix::Plus | Infix::Minus => Precedence::Sum,
+        Infix::Asterisk | Infix::Slash => Precedence::Product,
+    }
+}
+
+fn print_list(exprs: &[Expr]) -> String {
+    let exprs: Vec<String> = exprs.iter().map(print_expr).collect();
+    exprs.join(", ")
+}
+
+fn print_body(body: &Stmt) -> String {
+    match body {
+        Stmt::BlockStmt { stmts } => print_block(stmts),
+        stmt => print_block(std::slice::from_ref(stmt)),
+    }
+}
+
+fn print_block(stmts: &[Stmt]) -> String {
+    if stmts.is_empty() {
+        return "{}".to_string();
+    }
+    let stmts: Vec<String> = stmts.iter().map(print_stmt).collect();
+    format!("{{ {} }}", stmts.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use rmonkey_ast::builder::*;
+    use rmonkey_lexer::Lexer;
+    use rmonkey_parser::Parser;
+
+    use super::*;
+
+    fn parse(input: &str) -> Program {
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        p.parse_program().unwrap()
+    }
+
+    #[test]
+    fn test_print_annotations() {
+        let code = "let f: fn(int) -> [int] = fn(a: int, b) -> [int] { [a]; };";
+        let program = parse(code);
+        assert_eq!(print(&program), code);
+    }
+
+    #[test]
+    fn test_print() {
+        let case = [
+            (expr(int(1).add(int(2)).mul(3)), "(1 + 2) * 3;"),
+            (
+                expr(ident("a").sub(ident("b").sub(ident("c")))),
+                "a - (b - c);",
+            ),
+            (
+                expr(ident("a").sub(ident("b")).sub(ident("c"))),
+                "a - b - c;",
+            ),
+            (expr(int(1).add(2).neg()), "-(1 + 2);"),
+            (expr(ident("a").neg().index(0)), "(-a)[0];"),
+            (expr(ident("a").index(0).neg()), "-a[0];"),
+            (
+                expr(ident("a").neg().slice(Some(int(1)), None, Some(int(2)))),
+                "(-a)[1::2];",
+            ),
+            (expr(ident("a").slice(None, None, None)), "a[:];"),
+            (expr(ident("f").add(ident("g")).call([])), "(f + g)();"),
+            (
+                expr(func(["x"], [expr(ident("x"))]).call([int(-1)])),
+                "fn(x) { x; }(-1);",
+            ),
+            (
+                let_(
+                    "max",
+                    func(
+                        ["a", "b"],
+                        [expr(if_else(
+                            ident("a").gt(ident("b")),
+                            [return_(ident("a"))],
+                            [return_(ident("b"))],
+                        ))],
+                    ),
+                ),
+                "let max = fn(a, b) { if (a > b) { return a; } else { return b; }; };",
+            ),
+            (expr(hash([(string("a"), array([]))])), r#"{"a": []};"#),
+            (
+                import("lib/m.monkey", "m"),
+                r#"import "lib/m.monkey" as m;"#,
+            ),
+            (export_let("a", int(1)), "export let a = 1;"),
+            (throw(string("a")), r#"throw "a";"#),
+            (
+                expr(try_catch([throw(int(1))], "e", [expr(ident("e"))])),
+                "try { throw 1; } catch (e) { e; };",
+            ),
+            (
+                expr(try_finally([expr(int(1))], [])),
+                "try { 1; } finally {};",
+            ),
+            (
+                expr(try_catch_finally([], "e", [], [expr(int(2))])),
+                "try {} catch (e) {} finally { 2; };",
+            ),
+            (expr(ident("f").call([]).propagate()), "f()?;"),
+            (
+                expr(match_(
+                    ident("x"),
+                    [
+                        arm(literal(-1), 1),
+                        arm_if(
+                            array_pattern([bind("h")], Some(bind("t"))),
+                            ident("h").gt(0),
+                            ident("t"),
+                        ),
+                        arm(hash_pattern([(string("k"), literal(true))]), hash([])),
+                        arm(wildcard(), match_(ident("y"), [])),
+                    ],
+                )),
+                r#"match (x) { -1 => 1, [h, ...t] if h > 0 => t, {"k": true} => {}, _ => match (y) {} };"#,
+            ),
+            (expr(ident("a").neg().propagate().add(int(1))), "(-a)? + 1;"),
+            (
+                let_pattern(
+                    array_pattern([bind("a"), wildcard()], Some(bind("r"))),
+                    ident("xs"),
+                ),
+                "let [a, _, ...r] = xs;",
+            ),
+            (
+                let_(
+                    "f",
+                    func_patterns(
+                        [hash_pattern([(string("k"), bind("v"))]), bind("b")],
+                        [expr(ident("v"))],
+                    ),
+                ),
+                r#"let f = fn({"k": v}, b) { v; };"#,
+            ),
+            (
+                let_(
+                    "m",
+                    macro_(
+                        ["a", "b"],
+                        [expr(
+                            ident("quote").call([ident("unquote").call([ident("a")]).add(1)]),
+                        )],
+                    ),
+                ),
+                "let m = macro(a, b) { quote(unquote(a) + 1); };",
+            ),
+            (expr(macro_([], [])), "macro() {};"),
+        ];
+        for (stmt, expected) in case.into_iter() {
+            let printed = print_stmt(&stmt);
+            assert_eq!(printed, expected);
+            assert_eq!(parse(&printed).stmts, vec![stmt]);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_fixtures() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/fixtures");
+        for entry in fs::read_dir(dir).unwrap() {
+            let code = fs::read_to_string(entry.unwrap().path()).unwrap();
+            let program = parse(&code);
+            let printed = print(&program);
+            assert_eq!(parse(&printed).stmts, program.stmts, "{printed}");
+        }
+    }
+}
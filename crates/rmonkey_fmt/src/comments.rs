@@ -0,0 +1,72 @@
+//! Comments of the source being formatted.
+//!
+//! The AST has no place for comments, so they are put back by position: a
+//! comment before a statement stays before it, a comment after a statement on
+//! the same line stays after it, and a comment inside a statement but outside
+//! of any block is moved in front of the statement.
+
+use rmonkey_ast::{
+    span::{BlockSpans, Span},
+    Program,
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct Comments {
+    code: String,
+    spans: Vec<Span>,
+    taken: Vec<bool>,
+    blocks: Vec<BlockSpans>,
+    next_block: usize,
+}
+
+impl Comments {
+    pub(crate) fn new(code: &str, program: &Program) -> Self {
+        Comments {
+            code: code.to_string(),
+            spans: program.comments.clone(),
+            taken: vec![false; program.comments.len()],
+            blocks: program.blocks.clone(),
+            next_block: 0,
+        }
+    }
+
+    /// the length of the source, or 0 if there is none
+    pub(crate) fn end(&self) -> usize {
+        self.code.len()
+    }
+
+    /// spans of the next block or hash literal. must be called once per `{`
+    /// in source order to stay in step with the parser.
+    pub(crate) fn next_block(&mut self) -> Option<BlockSpans> {
+        let block = self.blocks.get(self.next_block).cloned();
+        self.next_block += 1;
+        block
+    }
+
+    /// the comments starting between `start` and `end` that were not taken yet
+    pub(crate) fn take(&mut self, start: usize, end: usize) -> Vec<String> {
+        let mut taken = Vec::new();
+        for (i, span) in self.spans.iter().enumerate() {
+            if !self.taken[i] && start <= span.start && span.start < end {
+                self.taken[i] = true;
+                taken.push(self.code[span.start..span.end].trim_end().to_string());
+            }
+        }
+        taken
+    }
+
+    /// the comment following `end` on the same line, if it starts before
+    /// `limit`
+    pub(crate) fn take_trailing(&mut self, end: usize, limit: usize) -> Option<String> {
+        let i = self
+            .spans
+            .iter()
+            .position(|span| span.start >= end && span.start < limit)?;
+        let span = self.spans[i];
+        if self.taken[i] || self.code[end..span.start].contains('\n') {
+            return None;
+        }
+        self.taken[i] = true;
+        Some(self.code[span.start..span.end].trim_end().to_string())
+    }
+}
@@ -0,0 +1,267 @@
+//! Document algebra for width-aware layout, after Wadler's
+//! "A prettier printer".
+//!
+//! A [`Doc`] describes every layout a piece of code may take. A [`Doc::Group`]
+//! is printed on one line when it fits in the remaining width, otherwise its
+//! line breaks are taken. Groups nested inside get the same choice again.
+
+use crate::options::FormatOptions;
+
+#[derive(Debug, Clone)]
+pub enum Doc {
+    Nil,
+    Text(String),
+    /// a space when flat, a newline when broken
+    Line,
+    /// nothing when flat, a newline when broken
+    SoftLine,
+    /// always a newline
+    HardLine,
+    Concat(Vec<Doc>),
+    /// indent the lines inside by one level
+    Nest(Box<Doc>),
+    /// indent the lines inside by one level when the enclosing group is
+    /// broken, so that a block inside a flat group keeps its indentation
+    NestIfBreak(Box<Doc>),
+    Group(Box<Doc>),
+    /// choose a layout depending on whether the enclosing group is broken
+    IfBreak {
+        broken: Box<Doc>,
+        flat: Box<Doc>,
+    },
+    /// text deferred to the end of the line, such as a trailing comment. it
+    /// doesn't count towards the width of the line.
+    LineSuffix(String),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Doc {
+        Doc::Concat(docs.into_iter().collect())
+    }
+
+    pub fn nest(doc: Doc) -> Doc {
+        Doc::Nest(Box::new(doc))
+    }
+
+    pub fn nest_if_break(doc: Doc) -> Doc {
+        Doc::NestIfBreak(Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub fn if_break(broken: Doc, flat: Doc) -> Doc {
+        Doc::IfBreak {
+            broken: Box::new(broken),
+            flat: Box::new(flat),
+        }
+    }
+
+    pub fn join(docs: impl IntoIterator<Item = Doc>, sep: Doc) -> Doc {
+        let mut joined = Vec::new();
+        for (i, doc) in docs.into_iter().enumerate() {
+            if i > 0 {
+                joined.push(sep.clone());
+            }
+            joined.push(doc);
+        }
+        Doc::Concat(joined)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+type Cmd<'a> = (usize, Mode, &'a Doc);
+
+pub fn render(doc: &Doc, options: &FormatOptions) -> String {
+    let mut out = String::new();
+    let mut col = 0;
+    let mut suffix = String::new();
+    let mut stack: Vec<Cmd> = vec![(0, Mode::Break, doc)];
+
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Nil => {}
+            Doc::Text(text) => {
+                out.push_str(text);
+                col += text.chars().count();
+            }
+            Doc::Line if mode == Mode::Flat => {
+                out.push(' ');
+                col += 1;
+            }
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                out.push_str(&std::mem::take(&mut suffix));
+                let trimmed = out.trim_end_matches([' ', '\t']).len();
+                out.truncate(trimmed);
+                out.push('\n');
+                out.push_str(&options.indent_str(indent));
+                col = indent * options.indent_size;
+            }
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((indent, mode, doc));
+                }
+            }
+            Doc::Nest(doc) => stack.push((indent + 1, mode, doc)),
+            Doc::NestIfBreak(doc) => match mode {
+                Mode::Flat => stack.push((indent, mode, doc)),
+                Mode::Break => stack.push((indent + 1, mode, doc)),
+            },
+            Doc::Group(doc) => {
+                let mode = if mode == Mode::Flat
+                    || fits(
+                        options.max_width as isize - col as isize,
+                        doc,
+                        indent,
+                        &stack,
+                    ) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                stack.push((indent, mode, doc));
+            }
+            Doc::IfBreak { broken, flat } => match mode {
+                Mode::Flat => stack.push((indent, mode, flat)),
+                Mode::Break => stack.push((indent, mode, broken)),
+            },
+            Doc::LineSuffix(text) => suffix.push_str(text),
+        }
+    }
+    out.push_str(&suffix);
+    out
+}
+
+/// check whether `doc` printed flat, followed by the rest of the document up
+/// to its next line break, fits in `width` columns.
+fn fits(mut width: isize, doc: &Doc, indent: usize, rest: &[Cmd]) -> bool {
+    let mut stack: Vec<Cmd> = vec![(indent, Mode::Flat, doc)];
+    let mut rest = rest.iter().rev();
+
+    while width >= 0 {
+        let (indent, mode, doc) = match stack.pop() {
+            Some(cmd) => cmd,
+            None => match rest.next() {
+                Some(cmd) => *cmd,
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Nil | Doc::LineSuffix(_) => {}
+            Doc::Text(text) => width -= text.chars().count() as isize,
+            Doc::Line if mode == Mode::Flat => width -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((indent, mode, doc));
+                }
+            }
+            Doc::Nest(doc) | Doc::NestIfBreak(doc) => stack.push((indent + 1, mode, doc)),
+            Doc::Group(doc) => stack.push((indent, mode, doc)),
+            Doc::IfBreak { broken, flat } => match mode {
+                Mode::Flat => stack.push((indent, mode, flat)),
+                Mode::Break => stack.push((indent, mode, broken)),
+            },
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&str]) -> Doc {
+        let items = items.iter().map(|i| Doc::text(*i));
+        Doc::group(Doc::concat([
+            Doc::text("["),
+            Doc::nest(Doc::concat([
+                Doc::SoftLine,
+                Doc::join(items, Doc::concat([Doc::text(","), Doc::Line])),
+            ])),
+            Doc::SoftLine,
+            Doc::text("]"),
+        ]))
+    }
+
+    fn render_width(doc: &Doc, max_width: usize) -> String {
+        let options = FormatOptions {
+            max_width,
+            ..Default::default()
+        };
+        render(doc, &options)
+    }
+
+    #[test]
+    fn test_group_fits() {
+        let doc = list(&["a", "b", "c"]);
+        assert_eq!(render_width(&doc, 9), "[a, b, c]");
+    }
+
+    #[test]
+    fn test_group_breaks() {
+        let doc = list(&["a", "b", "c"]);
+        assert_eq!(render_width(&doc, 8), "[\n  a,\n  b,\n  c\n]");
+    }
+
+    #[test]
+    fn test_trailing_text_counts() {
+        let doc = Doc::concat([list(&["a", "b"]), Doc::text(";")]);
+        assert_eq!(render_width(&doc, 7), "[a, b];");
+        assert_eq!(render_width(&doc, 6), "[\n  a,\n  b\n];");
+    }
+
+    #[test]
+    fn test_inner_group_stays_flat() {
+        let inner = list(&["x", "y"]);
+        let doc = Doc::group(Doc::concat([
+            Doc::text("["),
+            Doc::nest(Doc::concat([
+                Doc::SoftLine,
+                Doc::join(
+                    [inner, Doc::text("long_name")],
+                    Doc::concat([Doc::text(","), Doc::Line]),
+                ),
+            ])),
+            Doc::SoftLine,
+            Doc::text("]"),
+        ]));
+        assert_eq!(render_width(&doc, 12), "[\n  [x, y],\n  long_name\n]");
+    }
+
+    #[test]
+    fn test_line_suffix() {
+        let doc = Doc::concat([
+            list(&["a", "b"]),
+            Doc::LineSuffix(" // c".to_string()),
+            Doc::text(";"),
+            Doc::HardLine,
+            Doc::text("d"),
+        ]);
+        assert_eq!(render_width(&doc, 7), "[a, b]; // c\nd");
+    }
+
+    #[test]
+    fn test_if_break() {
+        let doc = Doc::group(Doc::concat([
+            Doc::text("a"),
+            Doc::Line,
+            Doc::text("b"),
+            Doc::if_break(Doc::text(","), Doc::Nil),
+        ]));
+        assert_eq!(render_width(&doc, 3), "a b");
+        assert_eq!(render_width(&doc, 2), "a\nb,");
+    }
+}
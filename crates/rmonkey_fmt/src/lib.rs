@@ -1,55 +1,194 @@
+pub mod codegen;
+mod comments;
+pub mod doc;
+pub mod options;
+
+use comments::Comments;
+use doc::Doc;
+use options::{BraceStyle, FormatOptions, TrailingComma};
 use rmonkey_ast::{
     operator::{Infix, Prefix},
+    pattern::Pattern,
     precedence::Precedence,
+    span::Span,
+    ty::TypeExpr,
     *,
 };
+use rmonkey_error::Result;
+use rmonkey_lexer::Lexer;
+use rmonkey_parser::Parser;
 
 #[derive(Default)]
 pub struct Formatter {
-    indent: usize,
+    options: FormatOptions,
+    /// comments of the source passed to `fmt_source`
+    comments: Comments,
 }
 
 impl Formatter {
+    pub fn new(options: FormatOptions) -> Self {
+        Formatter {
+            options,
+            comments: Comments::default(),
+        }
+    }
+
     pub fn fmt(&mut self, ast: Program) -> String {
-        self.fmt_block_stmt(ast.stmts)
+        let end = self.comments.end();
+        let stmts = self.fmt_stmts(ast.stmts, &ast.spans, 0, end);
+        self.comments = Comments::default();
+        doc::render(&Doc::join(stmts, Doc::HardLine), &self.options)
+    }
+
+    /// parse and format source code, keeping its comments
+    pub fn fmt_source(&mut self, code: &str) -> Result<String> {
+        let l = Lexer::new(code);
+        let mut p = Parser::new(l);
+        let program = p.parse_program()?;
+        self.comments = Comments::new(code, &program);
+        Ok(self.fmt(program))
+    }
+
+    /// format a statement list one statement per line, putting back the
+    /// comments between `start` and `end` around the statements
+    fn fmt_stmts(
+        &mut self,
+        stmts: Vec<Stmt>,
+        spans: &[Span],
+        start: usize,
+        end: usize,
+    ) -> Vec<Doc> {
+        if spans.len() != stmts.len() {
+            return stmts.into_iter().map(|s| self.fmt_stmt(s)).collect();
+        }
+
+        let mut lines = Vec::new();
+        let mut pos = start;
+        for (i, stmt) in stmts.into_iter().enumerate() {
+            let span = spans[i];
+            let leading = self.comments.take(pos, span.start);
+            lines.extend(leading.into_iter().map(Doc::text));
+            let mut doc = self.fmt_stmt(stmt);
+            // taken after formatting, so that blocks inside keep theirs
+            let inner = self.comments.take(span.start, span.end);
+            lines.extend(inner.into_iter().map(Doc::text));
+            let limit = spans.get(i + 1).map_or(end, |next| next.start);
+            if let Some(comment) = self.comments.take_trailing(span.end, limit) {
+                doc = Doc::concat([doc, Doc::LineSuffix(format!(" {comment}"))]);
+            }
+            lines.push(doc);
+            pos = span.end;
+        }
+        let rest = self.comments.take(pos, end);
+        lines.extend(rest.into_iter().map(Doc::text));
+        lines
     }
 
-    fn fmt_stmt(&mut self, stmt: Stmt) -> String {
+    fn fmt_stmt(&mut self, stmt: Stmt) -> Doc {
         match stmt {
-            Stmt::LetStmt { name, value } => self.fmt_let_stmt(name, value),
-            Stmt::ReturnStmt(_) => todo!(),
-            Stmt::ExprStmt(expr) => format!("{};", self.fmt_expr(expr, Precedence::Lowest)),
+            Stmt::LetStmt {
+                export,
+                name,
+                ty,
+                value,
+            } => self.fmt_let_stmt(export, name, ty, value),
+            Stmt::ReturnStmt(value) => Doc::concat([
+                Doc::text("return "),
+                self.fmt_expr(value, Precedence::Lowest),
+                Doc::text(";"),
+            ]),
+            Stmt::ExprStmt(expr) => {
+                Doc::concat([self.fmt_expr(expr, Precedence::Lowest), Doc::text(";")])
+            }
             Stmt::BlockStmt { stmts } => self.fmt_block_stmt(stmts),
+            Stmt::Import { path, alias } => Doc::text(format!(r#"import "{path}" as {alias};"#)),
+            Stmt::Throw(value) => Doc::concat([
+                Doc::text("throw "),
+                self.fmt_expr(value, Precedence::Lowest),
+                Doc::text(";"),
+            ]),
+        }
+    }
+
+    /// format `{ ... }` with the statements one level deeper than the braces.
+    fn fmt_block_stmt(&mut self, stmts: Vec<Stmt>) -> Doc {
+        let stmts = match self.comments.next_block() {
+            Some(block) => self.fmt_stmts(stmts, &block.stmts, block.span.start, block.span.end),
+            None => stmts.into_iter().map(|s| self.fmt_stmt(s)).collect(),
+        };
+        if stmts.is_empty() {
+            return Doc::text("{}");
         }
+
+        Doc::concat([
+            Doc::text("{"),
+            Doc::nest(Doc::concat([
+                Doc::HardLine,
+                Doc::join(stmts, Doc::HardLine),
+            ])),
+            Doc::HardLine,
+            Doc::text("}"),
+        ])
+    }
+
+    /// a block following other code on the same line, e.g. a function body
+    fn fmt_body(&mut self, body: Stmt) -> Doc {
+        let sep = match self.options.brace_style {
+            BraceStyle::SameLine => Doc::text(" "),
+            BraceStyle::NextLine => Doc::HardLine,
+        };
+        Doc::concat([sep, self.fmt_stmt(body)])
     }
-    fn fmt_block_stmt(&mut self, stmts: Vec<Stmt>) -> String {
-        let mut formatted_code = String::new();
 
-        for (_i, stmt) in stmts.into_iter().enumerate() {
-            let indent = self.indent * 2;
-            formatted_code.push_str(&format!(
-                "{}{}",
-                " ".repeat(indent),
-                &self.fmt_stmt(stmt).to_string()
-            ));
+    /// a comma separated list that is split one item per line when it
+    /// doesn't fit. the items are only indented when the list is split, so
+    /// that a function passed as the last argument keeps its body in place.
+    fn fmt_list(&mut self, open: &str, items: Vec<Doc>, close: &str) -> Doc {
+        if items.is_empty() {
+            return Doc::text(format!("{open}{close}"));
         }
-        formatted_code
+
+        let (flat_comma, broken_comma) = match self.options.trailing_comma {
+            TrailingComma::Never => ("", ""),
+            TrailingComma::Multiline => ("", ","),
+            TrailingComma::Always => (",", ","),
+        };
+        Doc::group(Doc::concat([
+            Doc::text(open),
+            Doc::nest_if_break(Doc::concat([
+                Doc::SoftLine,
+                Doc::join(items, Doc::concat([Doc::text(","), Doc::Line])),
+                Doc::if_break(Doc::text(broken_comma), Doc::text(flat_comma)),
+            ])),
+            Doc::SoftLine,
+            Doc::text(close),
+        ]))
     }
-    fn fmt_let_stmt(&mut self, name: Expr, value: Expr) -> String {
-        let name = self.fmt_expr(name, Precedence::Lowest);
-        let result = format!("let {name} = ");
 
-        let expr = self.fmt_expr(value, Precedence::Lowest);
-        format!("{result}{expr};\n")
+    fn fmt_let_stmt(
+        &mut self,
+        export: bool,
+        name: Pattern,
+        ty: Option<TypeExpr>,
+        value: Expr,
+    ) -> Doc {
+        Doc::concat([
+            Doc::text(if export { "export let " } else { "let " }),
+            Self::fmt_pattern(name),
+            Self::fmt_annotation(ty),
+            Doc::text(" = "),
+            self.fmt_expr(value, Precedence::Lowest),
+            Doc::text(";"),
+        ])
     }
 
-    fn fmt_expr(&mut self, expr: Expr, precedence: Precedence) -> String {
+    fn fmt_expr(&mut self, expr: Expr, precedence: Precedence) -> Doc {
         match expr {
             Expr::Ident(val) => self.fmt_ident_expr(val),
             Expr::IntLiteral(val) => self.fmt_int_literal(val),
             Expr::BoolLiteral(val) => self.fmt_bool_literal(val),
             Expr::StringLiteral(val) => self.fmt_string_literal(val),
-            Expr::PrefixExpr { op, right } => self.fmt_prefix_expr(op, *right),
+            Expr::PrefixExpr { op, right } => self.fmt_prefix_expr(op, *right, precedence),
             Expr::InfixExpr { left, right, op } => {
                 self.fmt_infix_expr(*left, *right, op, precedence)
             }
@@ -58,21 +197,45 @@ impl Formatter {
                 consequence,
                 alternative,
             } => self.fmt_if_expr(*condition, *consequence, alternative),
-            Expr::Func { params, body } => self.fmt_func_literal(params, *body),
+            Expr::Func {
+                params,
+                param_types,
+                ret,
+                body,
+            } => self.fmt_func_literal(params, param_types, ret, *body),
             Expr::Call { callee, args } => self.fmt_call_expr(*callee, args),
             Expr::Array { elements } => self.fmt_array_literal(elements),
             Expr::IndexExpr { left, index } => self.fmt_index_expr_literal(*left, *index),
-            Expr::HashLiteral { pairs: _ } => todo!(),
+            Expr::Slice {
+                left,
+                start,
+                end,
+                step,
+            } => self.fmt_slice_expr(*left, start, end, step),
+            Expr::Propagate { value } => {
+                Doc::concat([self.fmt_expr(*value, Precedence::Call), Doc::text("?")])
+            }
+            Expr::HashLiteral { pairs } => self.fmt_hash_literal(pairs),
+            Expr::Try {
+                body,
+                catch,
+                finally,
+            } => self.fmt_try_expr(*body, catch, finally),
+            Expr::Match { value, arms } => self.fmt_match_expr(*value, arms),
+            Expr::Macro { params, body } => self.fmt_macro_literal(params, *body),
         }
     }
 
-    fn fmt_ident_expr(&mut self, name: String) -> String {
-        name
+    fn fmt_ident_expr(&mut self, name: String) -> Doc {
+        Doc::text(name)
     }
 
-    fn fmt_prefix_expr(&mut self, op: Prefix, right: Expr) -> String {
+    fn fmt_prefix_expr(&mut self, op: Prefix, right: Expr, precedence: Precedence) -> Doc {
         let right = self.fmt_expr(right, Precedence::Prefix);
-        format!("{op}{right}")
+        Self::paren_if(
+            precedence > Precedence::Prefix,
+            Doc::concat([Doc::text(op.to_string()), right]),
+        )
     }
 
     fn fmt_infix_expr(
@@ -81,16 +244,18 @@ impl Formatter {
         right: Expr,
         op: Infix,
         precedence: Precedence,
-    ) -> String {
+    ) -> Doc {
         let cur_precedence = Self::infix_to_precedence(&op);
         let l = self.fmt_expr(left, cur_precedence.clone());
-        let r = self.fmt_expr(right, cur_precedence.clone());
-
-        if precedence > cur_precedence {
-            format!("({l} {op} {r})")
-        } else {
-            format!("{l} {op} {r}")
-        }
+        // operators are left-associative, so an operand of the same precedence
+        // on the right must keep its parentheses
+        let r = self.fmt_expr(right, Self::tighter(&cur_precedence));
+        let expr = Doc::group(Doc::concat([
+            l,
+            Doc::text(format!(" {op}")),
+            Doc::nest_if_break(Doc::concat([Doc::Line, r])),
+        ]));
+        Self::paren_if(precedence > cur_precedence, expr)
     }
 
     fn infix_to_precedence(op: &Infix) -> Precedence {
@@ -103,98 +268,268 @@ impl Formatter {
         }
     }
 
-    fn fmt_int_literal(&mut self, val: i64) -> String {
-        val.to_string()
+    /// the precedence one step above `precedence`
+    fn tighter(precedence: &Precedence) -> Precedence {
+        match precedence {
+            Precedence::Lowest => Precedence::Equals,
+            Precedence::Equals => Precedence::LessGreater,
+            Precedence::LessGreater => Precedence::Sum,
+            Precedence::Sum => Precedence::Product,
+            Precedence::Product => Precedence::Prefix,
+            Precedence::Prefix => Precedence::Call,
+            Precedence::Call | Precedence::Index => Precedence::Index,
+        }
+    }
+
+    fn paren_if(cond: bool, doc: Doc) -> Doc {
+        if cond {
+            Doc::concat([Doc::text("("), doc, Doc::text(")")])
+        } else {
+            doc
+        }
+    }
+
+    fn fmt_int_literal(&mut self, val: i64) -> Doc {
+        Doc::text(val.to_string())
     }
 
-    fn fmt_bool_literal(&mut self, val: bool) -> String {
-        val.to_string()
+    fn fmt_bool_literal(&mut self, val: bool) -> Doc {
+        Doc::text(val.to_string())
     }
 
-    fn fmt_string_literal(&mut self, val: String) -> String {
-        let res = val;
-        format!(r#""{res}""#)
+    fn fmt_string_literal(&mut self, val: String) -> Doc {
+        Doc::text(format!(r#""{val}""#))
     }
 
-    fn fmt_func_literal(&mut self, params: Vec<Expr>, body: Stmt) -> String {
+    fn fmt_func_literal(
+        &mut self,
+        params: Vec<Pattern>,
+        param_types: Vec<Option<TypeExpr>>,
+        ret: Option<TypeExpr>,
+        body: Stmt,
+    ) -> Doc {
+        let mut types = param_types.into_iter();
         let params = params
-            .iter()
-            .map(|p| self.fmt_expr(p.clone(), Precedence::Lowest))
-            .collect::<Vec<String>>()
-            .join(", ");
-        self.indent += 1;
-        let body = self.fmt_stmt(body);
-        self.indent -= 1;
-        format!(
-            "fn({}) {{\n{body}\n{}}}",
-            params.trim_end_matches(", "),
-            " ".repeat(self.indent)
-        )
+            .into_iter()
+            .map(|p| {
+                let ty = types.next().flatten();
+                Doc::concat([Self::fmt_pattern(p), Self::fmt_annotation(ty)])
+            })
+            .collect();
+        let ret = match ret {
+            Some(ty) => Doc::text(format!(" -> {ty}")),
+            None => Doc::Nil,
+        };
+        Doc::concat([
+            Doc::text("fn"),
+            self.fmt_list("(", params, ")"),
+            ret,
+            self.fmt_body(body),
+        ])
+    }
+
+    fn fmt_macro_literal(&mut self, params: Vec<String>, body: Stmt) -> Doc {
+        let params = params.into_iter().map(Doc::text).collect();
+        Doc::concat([
+            Doc::text("macro"),
+            self.fmt_list("(", params, ")"),
+            self.fmt_body(body),
+        ])
+    }
+
+    /// a pattern, which is always kept on one line
+    fn fmt_pattern(pattern: Pattern) -> Doc {
+        Doc::text(pattern.to_string())
     }
 
-    fn fmt_array_literal(&mut self, elements: Vec<Expr>) -> String {
-        let elems: Vec<String> = elements
-            .iter()
-            .map(|e| self.fmt_expr(e.clone(), Precedence::Lowest))
+    /// `: <type>`, or nothing without an annotation
+    fn fmt_annotation(ty: Option<TypeExpr>) -> Doc {
+        match ty {
+            Some(ty) => Doc::text(format!(": {ty}")),
+            None => Doc::Nil,
+        }
+    }
+
+    fn fmt_array_literal(&mut self, elements: Vec<Expr>) -> Doc {
+        let elems = elements
+            .into_iter()
+            .map(|e| self.fmt_expr(e, Precedence::Lowest))
             .collect();
-        format!("[{}]", elems.join(", ").trim_end_matches(", "))
+        self.fmt_list("[", elems, "]")
+    }
+
+    fn fmt_hash_literal(&mut self, pairs: Vec<(Expr, Expr)>) -> Doc {
+        // comments inside a hash are moved in front of its statement
+        self.comments.next_block();
+        let pairs = pairs
+            .into_iter()
+            .map(|(key, val)| {
+                Doc::concat([
+                    self.fmt_expr(key, Precedence::Lowest),
+                    Doc::text(": "),
+                    self.fmt_expr(val, Precedence::Lowest),
+                ])
+            })
+            .collect();
+        self.fmt_list("{", pairs, "}")
+    }
+
+    fn fmt_index_expr_literal(&mut self, left: Expr, index: Expr) -> Doc {
+        Doc::concat([
+            self.fmt_expr(left, Precedence::Call),
+            Doc::text("["),
+            self.fmt_expr(index, Precedence::Lowest),
+            Doc::text("]"),
+        ])
     }
 
-    fn fmt_index_expr_literal(&mut self, left: Expr, index: Expr) -> String {
-        let left = self.fmt_expr(left, Precedence::Lowest);
-        let index = self.fmt_expr(index, Precedence::Lowest);
-        format!("{left}[{index}]")
+    fn fmt_slice_expr(
+        &mut self,
+        left: Expr,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+        step: Option<Box<Expr>>,
+    ) -> Doc {
+        let mut doc = vec![self.fmt_expr(left, Precedence::Call), Doc::text("[")];
+        if let Some(start) = start {
+            doc.push(self.fmt_expr(*start, Precedence::Lowest));
+        }
+        doc.push(Doc::text(":"));
+        if let Some(end) = end {
+            doc.push(self.fmt_expr(*end, Precedence::Lowest));
+        }
+        if let Some(step) = step {
+            doc.push(Doc::text(":"));
+            doc.push(self.fmt_expr(*step, Precedence::Lowest));
+        }
+        doc.push(Doc::text("]"));
+        Doc::concat(doc)
     }
 
-    fn fmt_call_expr(&mut self, callee: Expr, args: Vec<Expr>) -> String {
-        let callee = self.fmt_expr(callee, Precedence::Lowest);
-        let args: Vec<String> = args
-            .iter()
-            .map(|a| self.fmt_expr(a.clone(), Precedence::Lowest))
+    fn fmt_call_expr(&mut self, callee: Expr, args: Vec<Expr>) -> Doc {
+        let callee = self.fmt_expr(callee, Precedence::Call);
+        let args = args
+            .into_iter()
+            .map(|a| self.fmt_expr(a, Precedence::Lowest))
             .collect();
-        format!("{callee}({})", args.join(", ").trim_end_matches(", "))
-    }
-
-    fn fmt_if_expr(&mut self, cond: Expr, cons: Stmt, alt: Option<Box<Stmt>>) -> String {
-        self.indent += 1;
-        let cond = self.fmt_expr(cond, Precedence::Lowest);
-        let cons = self.fmt_stmt(cons);
-        let res = if let Some(alt) = alt {
-            let alt = self.fmt_stmt(*alt);
-            let indent = if self.indent % 2 == 0 {
-                self.indent
-            } else if self.indent == 1 {
-                0
-            } else {
-                self.indent + 1
-            };
-            format!(
-                "if({cond}) {{\n{cons}\n{}}} else {{\n{alt}\n{}}}",
-                " ".repeat(indent),
-                " ".repeat(indent)
-            )
-        } else {
-            let indent = if self.indent == 1 { 0 } else { self.indent * 2 };
-            format!("if({cond}) {{\n{cons}\n{}}}", " ".repeat(indent))
+        Doc::concat([callee, self.fmt_list("(", args, ")")])
+    }
+
+    fn fmt_if_expr(&mut self, cond: Expr, cons: Stmt, alt: Option<Box<Stmt>>) -> Doc {
+        let mut doc = vec![
+            Doc::text("if("),
+            self.fmt_expr(cond, Precedence::Lowest),
+            Doc::text(")"),
+            self.fmt_body(cons),
+        ];
+        if let Some(alt) = alt {
+            doc.push(match self.options.brace_style {
+                BraceStyle::SameLine => Doc::text(" else"),
+                BraceStyle::NextLine => Doc::concat([Doc::HardLine, Doc::text("else")]),
+            });
+            doc.push(self.fmt_body(*alt));
+        }
+        Doc::concat(doc)
+    }
+
+    fn fmt_try_expr(
+        &mut self,
+        body: Stmt,
+        catch: Option<Box<Catch>>,
+        finally: Option<Box<Stmt>>,
+    ) -> Doc {
+        let mut doc = vec![Doc::text("try"), self.fmt_body(body)];
+        if let Some(catch) = catch {
+            let Catch { param, body } = *catch;
+            doc.push(self.fmt_clause("catch"));
+            doc.push(Doc::text(format!(" ({param})")));
+            doc.push(self.fmt_body(*body));
+        }
+        if let Some(finally) = finally {
+            doc.push(self.fmt_clause("finally"));
+            doc.push(self.fmt_body(*finally));
+        }
+        Doc::concat(doc)
+    }
+
+    /// a `match` with one arm per line
+    fn fmt_match_expr(&mut self, value: Expr, arms: Vec<MatchArm>) -> Doc {
+        let mut doc = vec![
+            Doc::text("match ("),
+            self.fmt_expr(value, Precedence::Lowest),
+            Doc::text(")"),
+            match self.options.brace_style {
+                BraceStyle::SameLine => Doc::text(" "),
+                BraceStyle::NextLine => Doc::HardLine,
+            },
+        ];
+        if arms.is_empty() {
+            doc.push(Doc::text("{}"));
+            return Doc::concat(doc);
+        }
+
+        let arms: Vec<Doc> = arms
+            .into_iter()
+            .map(|arm| {
+                let mut doc = vec![Self::fmt_pattern(arm.pattern)];
+                if let Some(guard) = arm.guard {
+                    doc.push(Doc::text(" if "));
+                    doc.push(self.fmt_expr(guard, Precedence::Lowest));
+                }
+                doc.push(Doc::text(" => "));
+                doc.push(self.fmt_expr(arm.body, Precedence::Lowest));
+                Doc::concat(doc)
+            })
+            .collect();
+        let trailing_comma = match self.options.trailing_comma {
+            TrailingComma::Never => "",
+            TrailingComma::Multiline | TrailingComma::Always => ",",
         };
-        self.indent -= 1;
-        res
+        doc.extend([
+            Doc::text("{"),
+            Doc::nest(Doc::concat([
+                Doc::HardLine,
+                Doc::join(arms, Doc::concat([Doc::text(","), Doc::HardLine])),
+                Doc::text(trailing_comma),
+            ])),
+            Doc::HardLine,
+            Doc::text("}"),
+        ]);
+        Doc::concat(doc)
+    }
+
+    /// `catch` or `finally`, which follow the closing brace like `else`
+    fn fmt_clause(&self, keyword: &str) -> Doc {
+        match self.options.brace_style {
+            BraceStyle::SameLine => Doc::text(format!(" {keyword}")),
+            BraceStyle::NextLine => Doc::concat([Doc::HardLine, Doc::text(keyword)]),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{fs, path::Path};
+
     use rmonkey_lexer::Lexer;
     use rmonkey_parser::Parser;
 
     use super::*;
 
-    fn formatter(input: &str) -> String {
-        let mut formatter = Formatter::default();
+    fn parse(input: &str) -> Program {
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
-        let program = p.parse_program().unwrap();
-        formatter.fmt(program)
+        p.parse_program().unwrap()
+    }
+
+    fn formatter(input: &str) -> String {
+        let mut formatter = Formatter::default();
+        formatter.fmt(parse(input))
+    }
+
+    fn formatter_with(input: &str, options: FormatOptions) -> String {
+        let mut formatter = Formatter::new(options);
+        formatter.fmt(parse(input))
     }
 
     #[test]
@@ -209,6 +544,8 @@ mod tests {
             (r#"[1,2,3,4]"#, r#"[1, 2, 3, 4];"#),
             (r#"[1,2   ,3,    4]"#, r#"[1, 2, 3, 4];"#),
             (r#"arr[0 ]"#, r#"arr[0];"#),
+            (r#"arr[ 1 : n-1 ]"#, r#"arr[1:n - 1];"#),
+            (r#"arr[: : -1]"#, r#"arr[::-1];"#),
             (r#"some_func(left,right)"#, r#"some_func(left, right);"#),
             (
                 r#"some_func( 0, 1,    3,      5, 4  )"#,
@@ -259,31 +596,27 @@ mod tests {
                 "let identity=fn(x){x;}",
                 "let identity = fn(x) {
   x;
-};
-",
+};",
             ),
             (
                 "let double = fn(x){x*2;};",
                 "let double = fn(x) {
   x * 2;
-};
-",
+};",
             ),
             (
                 "let add = fn(   x,     y){x+y;};",
                 "let add = fn(x, y) {
   x + y;
-};
-",
+};",
             ),
             (
                 "let newAdder = fn(x){fn(y){x + y}};",
                 "let newAdder = fn(x) {
   fn(y) {
     x + y;
- };
-};
-",
+  };
+};",
             ),
             ("let fibonacci = fn(x) {if (x == 0) {0;} else {if (x == 1) {1;}else {fibonacci(x - 1) + fibonacci(x - 2);}}};",
 "let fibonacci = fn(x) {
@@ -296,8 +629,7 @@ mod tests {
       fibonacci(x - 1) + fibonacci(x - 2);
     };
   };
-};
-")
+};")
         ];
         for (input, expected) in input.into_iter() {
             let input = formatter(input);
@@ -353,4 +685,470 @@ mod tests {
             assert_eq!(input, expected);
         }
     }
+
+    #[test]
+    fn test_return_stmt() {
+        let input = [
+            ("return   5", "return 5;"),
+            ("return a+b;", "return a + b;"),
+            (
+                "fn(x){return x;}",
+                "fn(x) {
+  return x;
+};",
+            ),
+        ];
+        for (input, expected) in input.into_iter() {
+            let input = formatter(input);
+            assert_eq!(input, expected);
+        }
+    }
+
+    #[test]
+    fn test_hash_literal() {
+        let input = [
+            ("{}", "{};"),
+            (r#"{"one":1,"two":2}"#, r#"{"one": 1, "two": 2};"#),
+            (
+                r#"let h={1:fn(x){x}, true:[1,2]}"#,
+                r#"let h = {1: fn(x) {
+  x;
+}, true: [1, 2]};"#,
+            ),
+        ];
+        for (input, expected) in input.into_iter() {
+            let input = formatter(input);
+            assert_eq!(input, expected);
+        }
+    }
+
+    #[test]
+    fn test_nested_blocks() {
+        let input = [
+            (
+                "let a=1;let b=fn(){let c=2;if(a){if(c){return a;}else{fn(){}}}}",
+                "let a = 1;
+let b = fn() {
+  let c = 2;
+  if(a) {
+    if(c) {
+      return a;
+    } else {
+      fn() {};
+    };
+  };
+};",
+            ),
+            (
+                "fn(){fn(){fn(){fn(){deep}}}}",
+                "fn() {
+  fn() {
+    fn() {
+      fn() {
+        deep;
+      };
+    };
+  };
+};",
+            ),
+        ];
+        for (input, expected) in input.into_iter() {
+            let input = formatter(input);
+            assert_eq!(input, expected);
+        }
+    }
+
+    #[test]
+    fn test_keep_required_parens() {
+        let input = [
+            ("a-(b-c)", "a - (b - c);"),
+            ("(a-b)-c", "a - b - c;"),
+            ("a/(b*c)", "a / (b * c);"),
+            ("-(a+b)", "-(a + b);"),
+            ("(-a)[0]", "(-a)[0];"),
+            ("(f+g)(1)", "(f + g)(1);"),
+        ];
+        for (input, expected) in input.into_iter() {
+            let input = formatter(input);
+            assert_eq!(input, expected);
+        }
+    }
+
+    #[test]
+    fn test_fixtures_idempotent() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/fixtures");
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let code = fs::read_to_string(&path).unwrap();
+            let formatted = formatter(&code);
+            assert_eq!(
+                parse(&formatted).stmts,
+                parse(&code).stmts,
+                "formatting changed the AST of {}",
+                path.display()
+            );
+            assert_eq!(
+                formatter(&formatted),
+                formatted,
+                "formatting is not idempotent for {}",
+                path.display()
+            );
+        }
+    }
+
+    mod generated {
+        use proptest::prelude::*;
+        use rmonkey_ast::builder::*;
+
+        use super::*;
+
+        fn leaf() -> impl Strategy<Value = Expr> {
+            prop_oneof![
+                prop::sample::select(vec!["x", "y", "len", "foo_bar"]).prop_map(ident),
+                any::<i32>().prop_map(|val| int(val.into())),
+                any::<bool>().prop_map(boolean),
+                "[a-z ]{0,8}".prop_map(|val| string(&val)),
+            ]
+        }
+
+        fn expression() -> impl Strategy<Value = Expr> {
+            leaf().prop_recursive(4, 64, 4, |inner| {
+                let stmts = prop::collection::vec(inner.clone().prop_map(expr), 0..3);
+                prop_oneof![
+                    (inner.clone(), inner.clone()).prop_map(|(l, r)| l.add(r)),
+                    (inner.clone(), inner.clone()).prop_map(|(l, r)| l.sub(r)),
+                    (inner.clone(), inner.clone()).prop_map(|(l, r)| l.mul(r)),
+                    (inner.clone(), inner.clone()).prop_map(|(l, r)| l.div(r)),
+                    (inner.clone(), inner.clone()).prop_map(|(l, r)| l.lt(r)),
+                    (inner.clone(), inner.clone()).prop_map(|(l, r)| l.equals(r)),
+                    inner.clone().prop_map(Expr::neg),
+                    inner.clone().prop_map(Expr::not),
+                    (inner.clone(), prop::collection::vec(inner.clone(), 0..4))
+                        .prop_map(|(callee, args)| callee.call(args)),
+                    (inner.clone(), inner.clone()).prop_map(|(l, i)| l.index(i)),
+                    prop::collection::vec(inner.clone(), 0..6).prop_map(array),
+                    prop::collection::vec(
+                        ("[a-z]{1,4}".prop_map(|k| string(&k)), inner.clone()),
+                        0..4
+                    )
+                    .prop_map(hash),
+                    (inner.clone(), stmts.clone(), stmts.clone())
+                        .prop_map(|(c, t, f)| if_else(c, t, f)),
+                    stmts.prop_map(|body| func(["a", "b"], body)),
+                ]
+            })
+        }
+
+        fn statement() -> impl Strategy<Value = Stmt> {
+            prop_oneof![
+                expression().prop_map(|val| let_("v", val)),
+                expression().prop_map(return_),
+                expression().prop_map(expr),
+            ]
+        }
+
+        proptest! {
+            #[test]
+            fn test_generated_idempotent(stmts in prop::collection::vec(statement(), 1..4)) {
+                let formatted = Formatter::default().fmt(program(stmts.clone()));
+                prop_assert_eq!(&parse(&formatted).stmts, &stmts, "{}", formatted);
+                prop_assert_eq!(formatter(&formatted), formatted);
+            }
+        }
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        let input = [
+            ("let x:int=1", "let x: int = 1;"),
+            (
+                "let f=fn(a:[int],b)->{string:fn(int)->bool}{a}",
+                "let f = fn(a: [int], b) -> {string: fn(int) -> bool} {\n  a;\n};",
+            ),
+        ];
+        for (input, expected) in input.into_iter() {
+            let formatted = formatter(input);
+            assert_eq!(formatted, expected);
+            assert_eq!(parse(&formatted).stmts, parse(input).stmts);
+        }
+    }
+
+    #[test]
+    fn test_modules() {
+        let input = [
+            (
+                r#"import"lib/m.monkey"as m"#,
+                r#"import "lib/m.monkey" as m;"#,
+            ),
+            ("export let a=m[\"a\"]", "export let a = m[\"a\"];"),
+        ];
+        for (input, expected) in input.into_iter() {
+            let formatted = formatter(input);
+            assert_eq!(formatted, expected);
+            assert_eq!(parse(&formatted).stmts, parse(input).stmts);
+        }
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let input = [
+            ("throw  \"a\"", "throw \"a\";"),
+            (
+                "let x=try{f()}catch(e){throw e;}finally{g()}",
+                "let x = try {\n  f();\n} catch (e) {\n  throw e;\n} finally {\n  g();\n};",
+            ),
+        ];
+        for (input, expected) in input.into_iter() {
+            let formatted = formatter(input);
+            assert_eq!(formatted, expected);
+            assert_eq!(parse(&formatted).stmts, parse(input).stmts);
+        }
+        let options = FormatOptions {
+            brace_style: BraceStyle::NextLine,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            formatter_with("try{1}catch(e){2}", options),
+            "try\n{\n  1;\n}\ncatch (e)\n{\n  2;\n};"
+        );
+    }
+
+    #[test]
+    fn test_match() {
+        let input = [
+            (
+                "match(x){0=>a,[h,...t] if h>1=>h,{\"k\":-1}=>b,_=>c}",
+                "match (x) {\n  0 => a,\n  [h, ...t] if h > 1 => h,\n  {\"k\": -1} => b,\n  _ => c\n};",
+            ),
+            ("let y = match (x) {}", "let y = match (x) {};"),
+        ];
+        for (input, expected) in input.into_iter() {
+            let formatted = formatter(input);
+            assert_eq!(formatted, expected);
+            assert_eq!(parse(&formatted).stmts, parse(input).stmts);
+        }
+        let options = FormatOptions {
+            brace_style: BraceStyle::NextLine,
+            trailing_comma: TrailingComma::Multiline,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            formatter_with("match(x){_=>1}", options),
+            "match (x)\n{\n  _ => 1,\n};"
+        );
+    }
+
+    #[test]
+    fn test_destructuring() {
+        let input = [
+            ("let [a,b,...rest]=xs", "let [a, b, ...rest] = xs;"),
+            (
+                "let {\"x\":x,\"y\":[_,y]}=p",
+                "let {\"x\": x, \"y\": [_, y]} = p;",
+            ),
+            (
+                "let f=fn([a,b],{\"k\":v}){a}",
+                "let f = fn([a, b], {\"k\": v}) {\n  a;\n};",
+            ),
+        ];
+        for (input, expected) in input.into_iter() {
+            let formatted = formatter(input);
+            assert_eq!(formatted, expected);
+            assert_eq!(parse(&formatted).stmts, parse(input).stmts);
+        }
+    }
+
+    #[test]
+    fn test_macro_literal() {
+        let input = [
+            (
+                "let unless=macro(c,a,b){quote(if(!(unquote(c))){unquote(a)}else{unquote(b)})}",
+                "let unless = macro(c, a, b) {
+  quote(if(!unquote(c)) {
+    unquote(a);
+  } else {
+    unquote(b);
+  });
+};",
+            ),
+            (
+                "let m=macro(){quote(1)}",
+                "let m = macro() {\n  quote(1);\n};",
+            ),
+        ];
+        for (input, expected) in input.into_iter() {
+            let formatted = formatter(input);
+            assert_eq!(formatted, expected);
+            assert_eq!(parse(&formatted).stmts, parse(input).stmts);
+        }
+    }
+
+    #[test]
+    fn test_propagate() {
+        let input = [
+            ("f( x )?", "f(x)?;"),
+            ("let y=-a?+m[\"k\"]?", "let y = -a? + m[\"k\"]?;"),
+            ("(-a)?", "(-a)?;"),
+        ];
+        for (input, expected) in input.into_iter() {
+            let formatted = formatter(input);
+            assert_eq!(formatted, expected);
+            assert_eq!(parse(&formatted).stmts, parse(input).stmts);
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = [
+            ("// head\nlet a=1;", "// head\nlet a = 1;"),
+            ("let a=1; // one\nlet b=2;", "let a = 1; // one\nlet b = 2;"),
+            (
+                "let f=fn(x){ // body\n  x // result\n  // end\n};",
+                "let f = fn(x) {
+  // body
+  x; // result
+  // end
+};",
+            ),
+            ("fn(){\n// todo\n}", "fn() {\n  // todo\n};"),
+            ("let a=[1, // one\n2];", "// one\nlet a = [1, 2];"),
+            ("let a=1;\n// trailing  \r\n", "let a = 1;\n// trailing"),
+        ];
+        for (input, expected) in input.into_iter() {
+            let formatted = Formatter::default().fmt_source(input).unwrap();
+            assert_eq!(formatted, expected);
+            assert_eq!(
+                Formatter::default().fmt_source(&formatted).unwrap(),
+                formatted
+            );
+        }
+    }
+
+    #[test]
+    fn test_trailing_comment_does_not_break_lines() {
+        let options = FormatOptions {
+            max_width: 20,
+            ..Default::default()
+        };
+        let formatted = Formatter::new(options)
+            .fmt_source("let a = [1, 2, 3]; // a long comment")
+            .unwrap();
+        assert_eq!(formatted, "let a = [1, 2, 3]; // a long comment");
+    }
+
+    #[test]
+    fn test_break_long_lines() {
+        let options = FormatOptions {
+            max_width: 30,
+            ..Default::default()
+        };
+        let input = [
+            (
+                "let numbers = [100000, 200000, 300000];",
+                "let numbers = [
+  100000,
+  200000,
+  300000
+];",
+            ),
+            (
+                r#"let point = {"x": 100000, "y": 2000};"#,
+                r#"let point = {
+  "x": 100000,
+  "y": 2000
+};"#,
+            ),
+            (
+                "let sum = first_value + second_value;",
+                "let sum = first_value +
+  second_value;",
+            ),
+            (
+                "map(numbers, fn(x) { x * 2 });",
+                "map(numbers, fn(x) {
+  x * 2;
+});",
+            ),
+            (
+                "let f = fn(alpha, beta, gamma, delta) { alpha };",
+                "let f = fn(
+  alpha,
+  beta,
+  gamma,
+  delta
+) {
+  alpha;
+};",
+            ),
+        ];
+        for (input, expected) in input.into_iter() {
+            let input = formatter_with(input, options.clone());
+            assert_eq!(input, expected);
+        }
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        // each level is laid out once, so formatting is linear in the depth
+        let depth = 40;
+        let arrays = format!("{}1{};", "[".repeat(depth), "]".repeat(depth));
+        let formatted = formatter(&arrays);
+        assert_eq!(formatter(&formatted), formatted);
+        assert!(formatted.starts_with("[\n  [\n    ["), "{formatted}");
+
+        let chain = format!("{}a{};", "a - (".repeat(depth), ")".repeat(depth));
+        let formatted = formatter(&chain);
+        assert_eq!(formatter(&formatted), formatted);
+        assert_eq!(parse(&formatted).stmts, parse(&chain).stmts);
+    }
+
+    #[test]
+    fn test_options() {
+        let input = "let f = fn(x) { if (x) { [1, 2] } else { [100000, 200000, 300000] } };";
+        let case = [
+            (
+                FormatOptions {
+                    max_width: 30,
+                    indent_size: 4,
+                    trailing_comma: TrailingComma::Multiline,
+                    ..Default::default()
+                },
+                "let f = fn(x) {
+    if(x) {
+        [1, 2];
+    } else {
+        [
+            100000,
+            200000,
+            300000,
+        ];
+    };
+};",
+            ),
+            (
+                FormatOptions {
+                    use_tabs: true,
+                    trailing_comma: TrailingComma::Always,
+                    brace_style: BraceStyle::NextLine,
+                    ..Default::default()
+                },
+                "let f = fn(x,)
+{
+\tif(x)
+\t{
+\t\t[1, 2,];
+\t}
+\telse
+\t{
+\t\t[100000, 200000, 300000,];
+\t};
+};",
+            ),
+        ];
+        for (options, expected) in case.into_iter() {
+            let formatted = formatter_with(input, options);
+            assert_eq!(formatted, expected);
+            assert_eq!(parse(&formatted).stmts, parse(input).stmts);
+        }
+    }
 }
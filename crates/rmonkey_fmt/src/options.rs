@@ -0,0 +1,140 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rmonkey_error::{RMonkeyError, Result};
+use serde::Deserialize;
+
+pub const CONFIG_FILE_NAME: &str = "rmonkey.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailingComma {
+    /// never add a trailing comma
+    Never,
+    /// add a trailing comma when a list is split over several lines
+    Multiline,
+    /// always add a trailing comma to non-empty lists
+    Always,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BraceStyle {
+    /// `fn(x) {`
+    SameLine,
+    /// `fn(x)` with `{` on the next line
+    NextLine,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatOptions {
+    pub max_width: usize,
+    pub indent_size: usize,
+    pub use_tabs: bool,
+    pub trailing_comma: TrailingComma,
+    pub brace_style: BraceStyle,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            max_width: 80,
+            indent_size: 2,
+            use_tabs: false,
+            trailing_comma: TrailingComma::Never,
+            brace_style: BraceStyle::SameLine,
+        }
+    }
+}
+
+/// layout of `rmonkey.toml`. other tools keep their settings in their own
+/// tables, so anything but `[format]` is ignored here.
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    format: FormatOptions,
+}
+
+impl FormatOptions {
+    /// parse the `[format]` table of an `rmonkey.toml`
+    pub fn from_toml(config: &str) -> Result<Self> {
+        toml::from_str::<ConfigFile>(config)
+            .map(|file| file.format)
+            .map_err(|err| RMonkeyError::Custom(format!("invalid {CONFIG_FILE_NAME}: {err}")))
+    }
+
+    /// load the options from the `rmonkey.toml` in `dir` or its closest
+    /// ancestor. returns the default options when there is none.
+    pub fn load(dir: &Path) -> Result<Self> {
+        match find_config(dir) {
+            Some(path) => {
+                let config = fs::read_to_string(&path).map_err(|err| {
+                    RMonkeyError::Custom(format!("failed to read {}: {err}", path.display()))
+                })?;
+                Self::from_toml(&config)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// the whitespace for `level` levels of indentation
+    pub fn indent_str(&self, level: usize) -> String {
+        if self.use_tabs {
+            "\t".repeat(level)
+        } else {
+            " ".repeat(level * self.indent_size)
+        }
+    }
+}
+
+/// find the `rmonkey.toml` in `dir` or its closest ancestor
+pub fn find_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let config = r#"
+        [format]
+        max_width = 100
+        use_tabs = true
+        trailing_comma = "multiline"
+        brace_style = "next_line"
+
+        [lint]
+        unused = "off"
+        "#;
+        let options = FormatOptions::from_toml(config).unwrap();
+        assert_eq!(
+            options,
+            FormatOptions {
+                max_width: 100,
+                indent_size: 2,
+                use_tabs: true,
+                trailing_comma: TrailingComma::Multiline,
+                brace_style: BraceStyle::NextLine,
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_toml_without_format_table() {
+        let options = FormatOptions::from_toml("").unwrap();
+        assert_eq!(options, FormatOptions::default());
+    }
+
+    #[test]
+    fn test_from_toml_unknown_key() {
+        let err = FormatOptions::from_toml("[format]\nwidth = 1").unwrap_err();
+        assert!(err.to_string().contains("unknown field `width`"), "{err}");
+    }
+}
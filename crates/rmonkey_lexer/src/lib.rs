@@ -1,5 +1,6 @@
 use std::str::Chars;
 
+use rmonkey_span::Span;
 use rmonkey_token::{look_up_ident, Token};
 
 #[derive(Debug)]
@@ -7,14 +8,24 @@ pub struct Lexer<'a> {
     pub input: Chars<'a>,
     pub cur: char,
     pub peek: char,
+    /// byte offset of `cur` in the original input
+    pos: usize,
+    /// span of the last token returned by `next_token()`
+    span: Span,
+    /// spans of the `//` comments skipped so far
+    comments: Vec<Span>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
+        let trimmed = input.trim();
         let mut lexer = Lexer {
-            input: input.trim().chars(),
+            input: trimmed.chars(),
             cur: '\u{0}',
             peek: '\u{0}',
+            pos: input.len() - input.trim_start().len(),
+            span: Span::default(),
+            comments: Vec::new(),
         };
         lexer.read_char();
         lexer.read_char();
@@ -34,19 +45,42 @@ impl<'a> Lexer<'a> {
     /// read next char of the input and return current char
     pub fn read_char(&mut self) -> char {
         let cur = self.cur;
+        if cur != '\u{0}' {
+            self.pos += cur.len_utf8();
+        }
         self.cur = self.peek;
         self.peek = self.input.next().unwrap_or('\u{0}');
         cur
     }
 
+    /// span of the token most recently returned by `next_token()`
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// spans of the comments skipped so far, including the `//`
+    pub fn comments(&self) -> &[Span] {
+        &self.comments
+    }
+
     /// return the current token and call `read_char()`
     pub fn next_token(&mut self) -> Token {
         self.skip_whitespace();
+        let start = self.pos;
+        let token = self.read_token();
+        self.span = Span::new(start, self.pos);
+        token
+    }
+
+    fn read_token(&mut self) -> Token {
         let token = match self.cur {
             '=' => {
                 if self.peek == '=' {
                     self.read_char();
                     Token::Eq
+                } else if self.peek == '>' {
+                    self.read_char();
+                    Token::FatArrow
                 } else {
                     Token::Assign
                 }
@@ -56,7 +90,14 @@ impl<'a> Lexer<'a> {
             ')' => Token::RParen,
             ',' => Token::Comma,
             '+' => Token::Plus,
-            '-' => Token::Minus,
+            '-' => {
+                if self.peek == '>' {
+                    self.read_char();
+                    Token::Arrow
+                } else {
+                    Token::Minus
+                }
+            }
             '*' => Token::Asterisk,
             '/' => Token::Slash,
             '!' => {
@@ -74,6 +115,20 @@ impl<'a> Lexer<'a> {
             '[' => Token::LBracket,
             ']' => Token::RBracket,
             ':' => Token::Colon,
+            '?' => Token::Question,
+            '.' => {
+                if self.peek == '.' {
+                    self.read_char();
+                    if self.peek == '.' {
+                        self.read_char();
+                        Token::Ellipsis
+                    } else {
+                        Token::Illegal
+                    }
+                } else {
+                    Token::Illegal
+                }
+            }
             '"' => self.read_string(),
             '\u{0}' => Token::Eof,
             c => {
@@ -90,9 +145,20 @@ impl<'a> Lexer<'a> {
         token
     }
 
+    /// skip whitespace and `//` comments up to the end of the line
     fn skip_whitespace(&mut self) {
-        while self.cur.is_whitespace() || self.cur == '\n' || self.cur == '\t' || self.cur == '\r' {
-            self.read_char();
+        loop {
+            while self.cur.is_whitespace() {
+                self.read_char();
+            }
+            if self.cur != '/' || self.peek != '/' {
+                return;
+            }
+            let start = self.pos;
+            while self.cur != '\n' && self.cur != '\u{0}' {
+                self.read_char();
+            }
+            self.comments.push(Span::new(start, self.pos));
         }
     }
 
@@ -107,13 +173,16 @@ impl<'a> Lexer<'a> {
         Token::Ident(ident)
     }
 
+    /// an integer, or `Illegal` if it doesn't fit in an i64
     fn read_number(&mut self) -> Token {
         let mut value = String::new();
         while self.cur.is_ascii_digit() {
             value.push(self.read_char());
         }
-        let value_i64 = value.parse::<i64>().unwrap();
-        Token::Int(value_i64)
+        match value.parse::<i64>() {
+            Ok(value_i64) => Token::Int(value_i64),
+            Err(_) => Token::Illegal,
+        }
     }
 
     fn read_string(&mut self) -> Token {
@@ -229,6 +298,9 @@ mod tests {
         let input = r#"
 !-/*5;
 5 < 10 > 5;
+- > ->;
+x?
+=> ... match macro
         "#;
 
         let tests = [
@@ -243,6 +315,17 @@ mod tests {
             (Token::Int(10), "10"),
             (Token::Gt, ">"),
             (Token::Int(5), "5"),
+            (Token::Semicolon, ";"),
+            (Token::Minus, "-"),
+            (Token::Gt, ">"),
+            (Token::Arrow, "->"),
+            (Token::Semicolon, ";"),
+            (Token::Ident("x".to_string()), "x"),
+            (Token::Question, "?"),
+            (Token::FatArrow, "=>"),
+            (Token::Ellipsis, "..."),
+            (Token::Match, "match"),
+            (Token::Macro, "macro"),
         ];
 
         let mut l = Lexer::new(input);
@@ -515,4 +598,64 @@ mod tests {
             assert_eq!(token.to_string(), *exp_literal);
         }
     }
+
+    #[test]
+    fn test_span() {
+        let input = "  let foo = \"bär\";\n  foo";
+        let tests = [
+            (Token::Let, "let"),
+            (Token::Ident("foo".to_owned()), "foo"),
+            (Token::Assign, "="),
+            (Token::String("bär".to_owned()), "\"bär\""),
+            (Token::Semicolon, ";"),
+            (Token::Ident("foo".to_owned()), "foo"),
+        ];
+
+        let mut l = Lexer::new(input);
+        for (exp, exp_source) in tests.iter() {
+            let token = l.next_token();
+            assert_eq!(token, *exp);
+            let span = l.span();
+            assert_eq!(&input[span.start..span.end], *exp_source);
+        }
+        assert_eq!(l.next_token(), Token::Eof);
+        assert_eq!(l.span(), Span::new(input.len(), input.len()));
+    }
+
+    #[test]
+    fn test_int_out_of_range() {
+        let input = "9223372036854775807 9223372036854775808;";
+        let mut l = Lexer::new(input);
+        assert_eq!(l.next_token(), Token::Int(i64::MAX));
+        assert_eq!(l.next_token(), Token::Illegal);
+        assert_eq!(&input[l.span().start..l.span().end], "9223372036854775808");
+        assert_eq!(l.next_token(), Token::Semicolon);
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "// head\nlet a = 4 / 2; // tail\n//\na";
+        let tests = [
+            Token::Let,
+            Token::Ident("a".to_owned()),
+            Token::Assign,
+            Token::Int(4),
+            Token::Slash,
+            Token::Int(2),
+            Token::Semicolon,
+            Token::Ident("a".to_owned()),
+            Token::Eof,
+        ];
+
+        let mut l = Lexer::new(input);
+        for exp in tests.iter() {
+            assert_eq!(l.next_token(), *exp);
+        }
+        let comments: Vec<&str> = l
+            .comments()
+            .iter()
+            .map(|span| &input[span.start..span.end])
+            .collect();
+        assert_eq!(comments, ["// head", "// tail", "//"]);
+    }
 }
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use rmonkey_error::{RMonkeyError, Result};
+use rmonkey_fmt::options::{find_config, CONFIG_FILE_NAME};
+use serde::Deserialize;
+
+use crate::{Level, Rule};
+
+/// the `[lint]` table of `rmonkey.toml`, mapping rule names to levels:
+///
+/// ```toml
+/// [lint]
+/// unused_binding = "off"
+/// empty_block = "error"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    levels: HashMap<Rule, Level>,
+}
+
+/// layout of `rmonkey.toml`. anything but `[lint]` is ignored here.
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    lint: HashMap<String, Level>,
+}
+
+impl LintConfig {
+    /// parse the `[lint]` table of an `rmonkey.toml`
+    pub fn from_toml(config: &str) -> Result<Self> {
+        let invalid =
+            |msg: String| RMonkeyError::Custom(format!("invalid {CONFIG_FILE_NAME}: {msg}"));
+        let file = toml::from_str::<ConfigFile>(config).map_err(|err| invalid(err.to_string()))?;
+        let mut levels = HashMap::new();
+        for (name, level) in file.lint {
+            let rule = Rule::from_name(&name)
+                .ok_or_else(|| invalid(format!("unknown lint rule `{name}`")))?;
+            levels.insert(rule, level);
+        }
+        Ok(LintConfig { levels })
+    }
+
+    /// load the configuration from the `rmonkey.toml` in `dir` or its
+    /// closest ancestor. returns the default configuration when there is none.
+    pub fn load(dir: &Path) -> Result<Self> {
+        match find_config(dir) {
+            Some(path) => {
+                let config = fs::read_to_string(&path).map_err(|err| {
+                    RMonkeyError::Custom(format!("failed to read {}: {err}", path.display()))
+                })?;
+                Self::from_toml(&config)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn set(&mut self, rule: Rule, level: Level) {
+        self.levels.insert(rule, level);
+    }
+
+    /// the configured level of `rule`, or its default level
+    pub fn level(&self, rule: Rule) -> Level {
+        self.levels
+            .get(&rule)
+            .copied()
+            .unwrap_or(rule.default_level())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let config = r#"
+        [format]
+        max_width = 100
+
+        [lint]
+        unused_binding = "off"
+        empty_block = "error"
+        "#;
+        let config = LintConfig::from_toml(config).unwrap();
+        assert_eq!(config.level(Rule::UnusedBinding), Level::Off);
+        assert_eq!(config.level(Rule::EmptyBlock), Level::Error);
+        assert_eq!(config.level(Rule::UndefinedName), Level::Error);
+        assert_eq!(config.level(Rule::RedundantParens), Level::Warn);
+    }
+
+    #[test]
+    fn test_from_toml_unknown_rule() {
+        let err = LintConfig::from_toml("[lint]\nunused = \"off\"").unwrap_err();
+        assert!(
+            err.to_string().contains("unknown lint rule `unused`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_from_toml_invalid_level() {
+        let err = LintConfig::from_toml("[lint]\nempty_block = \"deny\"").unwrap_err();
+        assert!(err.to_string().contains("unknown variant `deny`"), "{err}");
+    }
+}
@@ -0,0 +1,405 @@
+//! Lint rules for Monkey programs.
+//!
+//! [`lint`] runs every rule that is not turned off in the [`LintConfig`] and
+//! returns its diagnostics in source order. Some diagnostics carry a fix,
+//! which [`fix`] applies. A diagnostic is suppressed by a comment naming its
+//! rule, either at the end of the offending line or on the line above:
+//!
+//! ```text
+//! // rmonkey: allow(shadowed_builtin)
+//! let len = fn(x) { 0 };
+//! let a = (1); // rmonkey: allow(redundant_parens, unused_binding)
+//! ```
+
+pub mod config;
+mod parens;
+mod rules;
+mod suppress;
+
+use std::fmt;
+
+pub use config::LintConfig;
+use rmonkey_ast::span::Span;
+use rmonkey_error::{RMonkeyError, Result};
+use rmonkey_lexer::Lexer;
+use rmonkey_parser::Parser;
+use rmonkey_resolver::DiagnosticKind;
+use serde::Deserialize;
+use suppress::Suppressions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// an identifier that is neither bound nor a builtin
+    UndefinedName,
+    /// a `let` that is never referenced
+    UnusedBinding,
+    /// a `let` in a function body that rebinds one of its parameters
+    ShadowedParam,
+    /// a parameter name that appears twice in the same parameter list
+    DuplicateParam,
+    /// statements following a `return` in the same block
+    UnreachableCode,
+    /// an `if` whose condition is made of literals only
+    ConstantCondition,
+    /// `x == x` and the like
+    SelfComparison,
+    /// an `if` or `else` block without statements
+    EmptyBlock,
+    /// a `let` that hides a builtin such as `len`
+    ShadowedBuiltin,
+    /// parentheses that don't change how the code parses
+    RedundantParens,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 10] = [
+        Rule::UndefinedName,
+        Rule::UnusedBinding,
+        Rule::ShadowedParam,
+        Rule::DuplicateParam,
+        Rule::UnreachableCode,
+        Rule::ConstantCondition,
+        Rule::SelfComparison,
+        Rule::EmptyBlock,
+        Rule::ShadowedBuiltin,
+        Rule::RedundantParens,
+    ];
+
+    /// the name used in `rmonkey.toml` and in suppression comments
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UndefinedName => "undefined_name",
+            Rule::UnusedBinding => "unused_binding",
+            Rule::ShadowedParam => "shadowed_param",
+            Rule::DuplicateParam => "duplicate_param",
+            Rule::UnreachableCode => "unreachable_code",
+            Rule::ConstantCondition => "constant_condition",
+            Rule::SelfComparison => "self_comparison",
+            Rule::EmptyBlock => "empty_block",
+            Rule::ShadowedBuiltin => "shadowed_builtin",
+            Rule::RedundantParens => "redundant_parens",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    pub fn default_level(self) -> Level {
+        match self {
+            Rule::UndefinedName | Rule::DuplicateParam => Level::Error,
+            _ => Level::Warn,
+        }
+    }
+}
+
+impl From<DiagnosticKind> for Rule {
+    fn from(kind: DiagnosticKind) -> Self {
+        match kind {
+            DiagnosticKind::UndefinedName => Rule::UndefinedName,
+            DiagnosticKind::UnusedBinding => Rule::UnusedBinding,
+            DiagnosticKind::ShadowedParam => Rule::ShadowedParam,
+            DiagnosticKind::DuplicateParam => Rule::DuplicateParam,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Off,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Off => write!(f, "off"),
+            Level::Warn => write!(f, "warning"),
+            Level::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// replace the code in `span` with `text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub span: Span,
+    pub text: String,
+}
+
+impl Edit {
+    pub fn delete(span: Span) -> Self {
+        Edit {
+            span,
+            text: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub level: Level,
+    pub span: Span,
+    pub message: String,
+    /// edits that resolve the diagnostic. empty when there is no safe fix.
+    pub fix: Vec<Edit>,
+}
+
+/// run the enabled rules over `code`. fails if `code` doesn't parse.
+pub fn lint(code: &str, config: &LintConfig) -> Result<Vec<Diagnostic>> {
+    let mut parser = Parser::new(Lexer::new(code));
+    let program = parser.parse_program()?;
+
+    let mut diagnostics = rules::check(code, &program);
+    if config.level(Rule::RedundantParens) != Level::Off {
+        diagnostics.extend(parens::check(code, &program));
+    }
+
+    let suppressions = Suppressions::new(code, &program.comments);
+    diagnostics.retain_mut(|d| {
+        d.level = config.level(d.rule);
+        d.level != Level::Off && !suppressions.allows(d.rule, d.span.start)
+    });
+    diagnostics.sort_by_key(|d| (d.span.start, d.span.end));
+    Ok(diagnostics)
+}
+
+/// a bound on the rounds of [`fix`]. a round applies every fix that doesn't
+/// overlap another one, so only fixes nested in each other take more than
+/// one, like the pairs of `((a))`.
+const MAX_FIX_ROUNDS: usize = 100;
+
+/// apply the fixes of the enabled rules until none is left. fixes that
+/// overlap are applied one round after the other and the code linted again
+/// in between, as one fix may make another one obsolete. fails if the fixes
+/// don't settle within [`MAX_FIX_ROUNDS`].
+pub fn fix(code: &str, config: &LintConfig) -> Result<String> {
+    fix_rounds(code, config, MAX_FIX_ROUNDS)
+}
+
+fn fix_rounds(code: &str, config: &LintConfig, max_rounds: usize) -> Result<String> {
+    let mut code = code.to_string();
+    for _ in 0..max_rounds {
+        let diagnostics = lint(&code, config)?;
+        // the code each fix touches, which includes its diagnostic
+        let mut taken: Vec<Span> = Vec::new();
+        let mut edits = Vec::new();
+        for diagnostic in diagnostics.into_iter().filter(|d| !d.fix.is_empty()) {
+            let extent = diagnostic.fix.iter().fold(diagnostic.span, |extent, edit| {
+                Span::new(
+                    extent.start.min(edit.span.start),
+                    extent.end.max(edit.span.end),
+                )
+            });
+            if taken
+                .iter()
+                .all(|t| extent.end <= t.start || t.end <= extent.start)
+            {
+                taken.push(extent);
+                edits.extend(diagnostic.fix);
+            }
+        }
+        if edits.is_empty() {
+            return Ok(code);
+        }
+        code = apply(&code, edits);
+    }
+    Err(RMonkeyError::Custom(format!(
+        "fixes didn't settle after {max_rounds} rounds"
+    )))
+}
+
+/// apply non-overlapping edits to `code`
+fn apply(code: &str, mut edits: Vec<Edit>) -> String {
+    let mut code = code.to_string();
+    edits.sort_by_key(|edit| edit.span.start);
+    for edit in edits.into_iter().rev() {
+        code.replace_range(edit.span.start..edit.span.end, &edit.text);
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(code: &str) -> Vec<(Rule, String)> {
+        lint(code, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.rule, d.message))
+            .collect()
+    }
+
+    fn text(code: &str, span: Span) -> &str {
+        &code[span.start..span.end]
+    }
+
+    #[test]
+    fn test_resolver_rules() {
+        let code = "let x = 1;\nlet f = fn(a, a) { let a = 2; a };\nf(1, y);";
+        assert_eq!(
+            rules(code),
+            [
+                (Rule::UnusedBinding, "unused binding: x".to_string()),
+                (Rule::DuplicateParam, "duplicate parameter: a".to_string()),
+                (
+                    Rule::ShadowedParam,
+                    "`let a` shadows the parameter a".to_string()
+                ),
+                (Rule::UndefinedName, "identifier not found: y".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        let code = "let f = fn(x) { return x; x + 1; puts(x) };\nf(1);";
+        let diagnostics = lint(code, &LintConfig::default()).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, Rule::UnreachableCode);
+        assert_eq!(text(code, diagnostics[0].span), "x + 1; puts(x)");
+        assert_eq!(
+            fix(code, &LintConfig::default()).unwrap(),
+            "let f = fn(x) { return x; };\nf(1);"
+        );
+
+        let code = "let f = fn(x) { throw x; x };\ntry { f(1) } catch (e) { e };";
+        let diagnostics = lint(code, &LintConfig::default()).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "unreachable code after `throw`");
+        assert_eq!(text(code, diagnostics[0].span), "x");
+    }
+
+    #[test]
+    fn test_constant_condition() {
+        let code = "if (true) { 1 };\nif (1 < 2) { 1 };\nif (-1) { 1 };\nlet a = 1;\nif (a) { 1 };";
+        let diagnostics = lint(code, &LintConfig::default()).unwrap();
+        let spans: Vec<&str> = diagnostics.iter().map(|d| text(code, d.span)).collect();
+        assert_eq!(spans, ["if (true)", "if (1 < 2)", "if (-1)"]);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.rule == Rule::ConstantCondition));
+    }
+
+    #[test]
+    fn test_self_comparison() {
+        let code = "let a = [1];\na[0] == a[0];\na != a;\na == [1];\nrand() == rand();";
+        let diagnostics = lint(code, &LintConfig::default()).unwrap();
+        let found: Vec<(Rule, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.rule, text(code, d.span)))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Rule::SelfComparison, "=="),
+                (Rule::SelfComparison, "!="),
+                (Rule::UndefinedName, "rand"),
+                (Rule::UndefinedName, "rand"),
+            ]
+        );
+        assert_eq!(diagnostics[0].message, "`a[0]` is compared with itself");
+    }
+
+    #[test]
+    fn test_empty_block() {
+        let code = "let a = 1;\nif (a) {} else { 2 };\nif (a) { 1 } else {};\nif (a) { 1 } else { // todo\n};\nlet f = fn() {};\nf();";
+        let diagnostics = lint(code, &LintConfig::default()).unwrap();
+        let found: Vec<(&str, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), text(code, d.span)))
+            .collect();
+        assert_eq!(found, [("empty block", "{}"), ("empty `else` block", "{}")]);
+        assert!(diagnostics[0].fix.is_empty());
+        assert_eq!(
+            fix(code, &LintConfig::default()).unwrap(),
+            "let a = 1;\nif (a) {} else { 2 };\nif (a) { 1 };\nif (a) { 1 } else { // todo\n};\nlet f = fn() {};\nf();"
+        );
+    }
+
+    #[test]
+    fn test_shadowed_builtin() {
+        let code = "let len = fn(x) { 0 };\nlen([1]);\nlet f = fn(first) { first };\nf(1);";
+        assert_eq!(
+            rules(code),
+            [(
+                Rule::ShadowedBuiltin,
+                "`let len` shadows the builtin len".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_redundant_parens() {
+        let code = "let a = (1);\nlet b = (a + 1) * 2;\nlet c = ((a));\nreturn(-(b) + c);";
+        let diagnostics = lint(code, &LintConfig::default()).unwrap();
+        let spans: Vec<&str> = diagnostics.iter().map(|d| text(code, d.span)).collect();
+        assert_eq!(spans, ["(1)", "(a)", "(-(b) + c)", "(b)"]);
+        assert_eq!(
+            fix(code, &LintConfig::default()).unwrap(),
+            "let a = 1;\nlet b = (a + 1) * 2;\nlet c = a;\nreturn -b + c;"
+        );
+    }
+
+    #[test]
+    fn test_fix_rounds() {
+        let code = (0..50)
+            .map(|i| format!("({i});"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        // one round to fix them all and one to find nothing left
+        let fixed = fix_rounds(&code, &LintConfig::default(), 2).unwrap();
+        assert_eq!(fixed, code.replace(['(', ')'], ""));
+
+        let code = "let a = (((1)));";
+        assert_eq!(
+            fix_rounds(code, &LintConfig::default(), 4).unwrap(),
+            "let a = 1;"
+        );
+        assert_eq!(
+            fix_rounds(code, &LintConfig::default(), 3)
+                .unwrap_err()
+                .to_string(),
+            "custom error: fixes didn't settle after 3 rounds"
+        );
+    }
+
+    #[test]
+    fn test_config_levels() {
+        let code = "let len = (1);\nlen;";
+        let mut config = LintConfig::default();
+        config.set(Rule::RedundantParens, Level::Off);
+        config.set(Rule::ShadowedBuiltin, Level::Error);
+        let diagnostics = lint(code, &config).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, Rule::ShadowedBuiltin);
+        assert_eq!(diagnostics[0].level, Level::Error);
+    }
+
+    #[test]
+    fn test_suppression() {
+        let code = "// rmonkey: allow(shadowed_builtin)\n\nlet len = 1;\nlet first = (len); // rmonkey: allow(redundant_parens, shadowed_builtin)\nlet rest = first;\nrest;";
+        assert_eq!(
+            rules(code),
+            [(
+                Rule::ShadowedBuiltin,
+                "`let rest` shadows the builtin rest".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(lint("let = 1;", &LintConfig::default()).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+//! The `redundant_parens` rule.
+//!
+//! Instead of reimplementing the precedence rules, each pair of parentheses
+//! is blanked out and the code parsed again. The pair is redundant if the
+//! AST stays the same. Parentheses that belong to a call, a parameter list or
+//! an `if` change the AST or fail to parse without them.
+
+use rmonkey_ast::{span::Span, Program};
+use rmonkey_lexer::Lexer;
+use rmonkey_parser::Parser;
+use rmonkey_token::Token;
+
+use crate::{Diagnostic, Edit, Rule};
+
+pub(crate) fn check(code: &str, program: &Program) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (open, close) in pairs(code) {
+        let mut blanked = code.to_string();
+        blanked.replace_range(open.start..open.end, " ");
+        blanked.replace_range(close.start..close.end, " ");
+        let same = Parser::new(Lexer::new(&blanked))
+            .parse_program()
+            .is_ok_and(|p| p.stmts == program.stmts);
+        if same {
+            diagnostics.push(Diagnostic {
+                rule: Rule::RedundantParens,
+                level: Rule::RedundantParens.default_level(),
+                span: Span::new(open.start, close.end),
+                message: "redundant parentheses".to_string(),
+                fix: vec![remove(code, open, false), remove(code, close, true)],
+            });
+        }
+    }
+    diagnostics
+}
+
+/// matching `(` and `)`, except for the outer pair of `((...))`, which is
+/// reported once the inner pair has been removed
+fn pairs(code: &str) -> Vec<(Span, Span)> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+    loop {
+        match lexer.next_token() {
+            Token::Eof => break,
+            token => tokens.push((token, lexer.span())),
+        }
+    }
+
+    // index of the matching `)` of each `(`
+    let mut closing = vec![None; tokens.len()];
+    let mut open = Vec::new();
+    for (i, (token, _)) in tokens.iter().enumerate() {
+        match token {
+            Token::LParen => open.push(i),
+            Token::RParen => {
+                if let Some(o) = open.pop() {
+                    closing[o] = Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for (o, c) in closing.iter().enumerate() {
+        let Some(c) = *c else { continue };
+        let wraps_pair = c > o + 1 && closing[o + 1] == Some(c - 1);
+        if !wraps_pair {
+            pairs.push((tokens[o].1, tokens[c].1));
+        }
+    }
+    pairs
+}
+
+/// delete a parenthesis. a space takes its place where it separated a word
+/// outside the pair, as in `return(x)`, from the code inside.
+fn remove(code: &str, paren: Span, is_close: bool) -> Edit {
+    let outside = if is_close {
+        code[paren.end..].chars().next()
+    } else {
+        code[..paren.start].chars().next_back()
+    };
+    let is_word = outside.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    Edit {
+        span: paren,
+        text: if is_word { " " } else { "" }.to_string(),
+    }
+}
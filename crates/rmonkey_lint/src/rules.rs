@@ -0,0 +1,332 @@
+//! Rules that walk the AST.
+//!
+//! The AST has spans for statements, blocks and identifiers only. Other
+//! locations are recovered from tokens that map one to one to AST nodes:
+//! every `if` is an `Expr::If` and every comparison operator an
+//! `Expr::InfixExpr`. Walking the AST in source order visits them in the
+//! order they were lexed.
+
+use rmonkey_ast::{
+    operator::Infix,
+    span::{BlockSpans, Span},
+    Expr, Program, Stmt,
+};
+use rmonkey_fmt::codegen::print_expr;
+use rmonkey_lexer::Lexer;
+use rmonkey_object::builtin::builtins;
+use rmonkey_resolver::{resolve, BindingKind};
+use rmonkey_token::Token;
+
+use crate::{Diagnostic, Edit, Rule};
+
+pub(crate) fn check(code: &str, program: &Program) -> Vec<Diagnostic> {
+    let tokens = Tokens::new(code);
+    let mut checker = Checker {
+        code,
+        blocks: &program.blocks,
+        comments: &program.comments,
+        ifs: tokens.ifs.into_iter(),
+        comparisons: tokens.comparisons.into_iter(),
+        block: 0,
+        diagnostics: Vec::new(),
+    };
+
+    let resolution = resolve(program);
+    for d in resolution.diagnostics.iter() {
+        let rule = Rule::from(d.kind);
+        checker.report(rule, program.idents[d.ident], d.to_string(), Vec::new());
+    }
+    let builtins = builtins();
+    for binding in resolution.bindings.iter() {
+        if binding.kind == BindingKind::Let && builtins.contains_key(binding.name.as_str()) {
+            let message = format!("`let {0}` shadows the builtin {0}", binding.name);
+            checker.report(
+                Rule::ShadowedBuiltin,
+                program.idents[binding.ident],
+                message,
+                Vec::new(),
+            );
+        }
+    }
+
+    checker.check_stmts(&program.stmts, &program.spans);
+    checker.diagnostics
+}
+
+/// spans of the tokens the AST has no spans for, in source order
+struct Tokens {
+    ifs: Vec<Span>,
+    /// `==`, `!=`, `<` and `>`
+    comparisons: Vec<Span>,
+}
+
+impl Tokens {
+    fn new(code: &str) -> Self {
+        let mut lexer = Lexer::new(code);
+        let mut tokens = Tokens {
+            ifs: Vec::new(),
+            comparisons: Vec::new(),
+        };
+        loop {
+            match lexer.next_token() {
+                Token::Eof => break,
+                Token::If => tokens.ifs.push(lexer.span()),
+                Token::Eq | Token::NotEq | Token::Lt | Token::Gt => {
+                    tokens.comparisons.push(lexer.span())
+                }
+                _ => {}
+            }
+        }
+        tokens
+    }
+}
+
+struct Checker<'a> {
+    code: &'a str,
+    blocks: &'a [BlockSpans],
+    comments: &'a [Span],
+    ifs: std::vec::IntoIter<Span>,
+    comparisons: std::vec::IntoIter<Span>,
+    /// index of the next block or hash literal in `blocks`
+    block: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, rule: Rule, span: Span, message: String, fix: Vec<Edit>) {
+        self.diagnostics.push(Diagnostic {
+            rule,
+            level: rule.default_level(),
+            span,
+            message,
+            fix,
+        });
+    }
+
+    fn next_block(&mut self) -> BlockSpans {
+        let block = self.blocks.get(self.block).cloned().unwrap_or_default();
+        self.block += 1;
+        block
+    }
+
+    fn check_stmts(&mut self, stmts: &[Stmt], spans: &[Span]) {
+        let exit = stmts
+            .iter()
+            .position(|stmt| matches!(stmt, Stmt::ReturnStmt(_) | Stmt::Throw(_)));
+        if let Some(i) = exit {
+            if i + 1 < stmts.len() && spans.len() == stmts.len() {
+                let end = spans[stmts.len() - 1].end;
+                let keyword = match stmts[i] {
+                    Stmt::Throw(_) => "throw",
+                    _ => "return",
+                };
+                self.report(
+                    Rule::UnreachableCode,
+                    Span::new(spans[i + 1].start, end),
+                    format!("unreachable code after `{keyword}`"),
+                    vec![Edit::delete(Span::new(spans[i].end, end))],
+                );
+            }
+        }
+        for stmt in stmts.iter() {
+            self.check_stmt(stmt);
+        }
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::LetStmt { value, .. } => self.check_expr(value),
+            Stmt::ReturnStmt(expr) | Stmt::ExprStmt(expr) | Stmt::Throw(expr) => {
+                self.check_expr(expr)
+            }
+            Stmt::BlockStmt { .. } => {
+                self.check_block(stmt);
+            }
+            Stmt::Import { .. } => {}
+        }
+    }
+
+    /// check the block of an `if` or `fn` and return its spans
+    fn check_block(&mut self, block: &Stmt) -> BlockSpans {
+        let spans = self.next_block();
+        if let Stmt::BlockStmt { stmts } = block {
+            self.check_stmts(stmts, &spans.stmts);
+        }
+        spans
+    }
+
+    fn check_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Ident(_)
+            | Expr::IntLiteral(_)
+            | Expr::BoolLiteral(_)
+            | Expr::StringLiteral(_) => {}
+            Expr::PrefixExpr { right, .. } | Expr::Propagate { value: right } => {
+                self.check_expr(right)
+            }
+            Expr::InfixExpr { left, right, op } => {
+                self.check_expr(left);
+                let is_comparison = matches!(op, Infix::Eq | Infix::NotEq | Infix::Lt | Infix::Gt);
+                let op_span = if is_comparison {
+                    self.comparisons.next()
+                } else {
+                    None
+                };
+                self.check_expr(right);
+                if let Some(span) = op_span {
+                    if left == right && is_pure(left) {
+                        let message = format!("`{}` is compared with itself", print_expr(left));
+                        self.report(Rule::SelfComparison, span, message, Vec::new());
+                    }
+                }
+            }
+            Expr::If {
+                condition,
+                consequence,
+                alternative,
+            } => self.check_if(condition, consequence, alternative.as_deref()),
+            Expr::Func { body, .. } | Expr::Macro { body, .. } => {
+                self.check_block(body);
+            }
+            Expr::Call { callee, args } => {
+                self.check_expr(callee);
+                for arg in args.iter() {
+                    self.check_expr(arg);
+                }
+            }
+            Expr::Array { elements } => {
+                for element in elements.iter() {
+                    self.check_expr(element);
+                }
+            }
+            Expr::IndexExpr { left, index } => {
+                self.check_expr(left);
+                self.check_expr(index);
+            }
+            Expr::Slice {
+                left,
+                start,
+                end,
+                step,
+            } => {
+                self.check_expr(left);
+                for bound in [start, end, step].into_iter().flatten() {
+                    self.check_expr(bound);
+                }
+            }
+            Expr::HashLiteral { pairs } => {
+                self.next_block();
+                for (key, value) in pairs.iter() {
+                    self.check_expr(key);
+                    self.check_expr(value);
+                }
+            }
+            Expr::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.check_block(body);
+                if let Some(catch) = catch {
+                    self.check_block(&catch.body);
+                }
+                if let Some(finally) = finally {
+                    self.check_block(finally);
+                }
+            }
+            Expr::Match { value, arms } => {
+                self.check_expr(value);
+                for arm in arms.iter() {
+                    if let Some(guard) = &arm.guard {
+                        self.check_expr(guard);
+                    }
+                    self.check_expr(&arm.body);
+                }
+            }
+        }
+    }
+
+    fn check_if(&mut self, condition: &Expr, consequence: &Stmt, alternative: Option<&Stmt>) {
+        let if_span = self.ifs.next().unwrap_or_default();
+        self.check_expr(condition);
+        let cons = self.check_block(consequence);
+        if is_constant(condition) {
+            // from `if` up to the `{` of the consequence
+            let head = self.code[if_span.start..cons.span.start].trim_end();
+            let span = Span::new(if_span.start, if_span.start + head.len());
+            self.report(
+                Rule::ConstantCondition,
+                span,
+                "constant `if` condition".to_string(),
+                Vec::new(),
+            );
+        }
+        if self.is_empty(&cons) {
+            self.report(
+                Rule::EmptyBlock,
+                cons.span,
+                "empty block".to_string(),
+                Vec::new(),
+            );
+        }
+
+        let Some(alternative) = alternative else {
+            return;
+        };
+        let alt = self.check_block(alternative);
+        if self.is_empty(&alt) {
+            // drop ` else {}` as a whole
+            let fix = vec![Edit::delete(Span::new(cons.span.end, alt.span.end))];
+            self.report(
+                Rule::EmptyBlock,
+                alt.span,
+                "empty `else` block".to_string(),
+                fix,
+            );
+        }
+    }
+
+    /// whether a block has neither statements nor comments
+    fn is_empty(&self, block: &BlockSpans) -> bool {
+        let span = block.span;
+        block.stmts.is_empty()
+            && !self
+                .comments
+                .iter()
+                .any(|c| span.start < c.start && c.end <= span.end)
+    }
+}
+
+/// an expression made of literals only, whose value is known before running
+fn is_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::IntLiteral(_) | Expr::BoolLiteral(_) | Expr::StringLiteral(_) | Expr::Func { .. } => {
+            true
+        }
+        Expr::PrefixExpr { right, .. } => is_constant(right),
+        Expr::InfixExpr { left, right, .. } => is_constant(left) && is_constant(right),
+        _ => false,
+    }
+}
+
+/// an expression that evaluates to an equal value every time. calls may not,
+/// and each function literal creates a distinct function.
+fn is_pure(expr: &Expr) -> bool {
+    match expr {
+        Expr::Ident(_) | Expr::IntLiteral(_) | Expr::BoolLiteral(_) | Expr::StringLiteral(_) => {
+            true
+        }
+        Expr::PrefixExpr { right, .. } => is_pure(right),
+        Expr::InfixExpr { left, right, .. } | Expr::IndexExpr { left, index: right } => {
+            is_pure(left) && is_pure(right)
+        }
+        Expr::Slice {
+            left,
+            start,
+            end,
+            step,
+        } => is_pure(left) && [start, end, step].into_iter().flatten().all(|b| is_pure(b)),
+        Expr::Array { elements } => elements.iter().all(is_pure),
+        _ => false,
+    }
+}
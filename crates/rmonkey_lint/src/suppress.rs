@@ -0,0 +1,87 @@
+//! Inline suppression with `// rmonkey: allow(rule, ...)`.
+//!
+//! A comment that follows code applies to its own line. A comment on a line
+//! of its own applies to the next line with code.
+
+use std::collections::HashMap;
+
+use rmonkey_ast::span::Span;
+
+use crate::Rule;
+
+pub(crate) struct Suppressions {
+    /// byte offset of the first character of each line
+    line_starts: Vec<usize>,
+    /// rules allowed on each line
+    allowed: HashMap<usize, Vec<Rule>>,
+}
+
+impl Suppressions {
+    pub(crate) fn new(code: &str, comments: &[Span]) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(code.match_indices('\n').map(|(i, _)| i + 1));
+        let mut suppressions = Suppressions {
+            line_starts,
+            allowed: HashMap::new(),
+        };
+
+        for comment in comments.iter() {
+            let Some(rules) = parse_allow(&code[comment.start..comment.end]) else {
+                continue;
+            };
+            let line = suppressions.line(comment.start);
+            let line_start = suppressions.line_starts[line];
+            let target = if code[line_start..comment.start].trim().is_empty() {
+                suppressions.next_code_line(code, line)
+            } else {
+                Some(line)
+            };
+            if let Some(target) = target {
+                suppressions
+                    .allowed
+                    .entry(target)
+                    .or_default()
+                    .extend(rules);
+            }
+        }
+        suppressions
+    }
+
+    /// whether `rule` is allowed on the line of `offset`
+    pub(crate) fn allows(&self, rule: Rule, offset: usize) -> bool {
+        self.allowed
+            .get(&self.line(offset))
+            .is_some_and(|rules| rules.contains(&rule))
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// the first line after `line` that is neither blank nor a comment
+    fn next_code_line(&self, code: &str, line: usize) -> Option<usize> {
+        (line + 1..self.line_starts.len()).find(|&l| {
+            let end = self.line_starts.get(l + 1).copied().unwrap_or(code.len());
+            let text = code[self.line_starts[l]..end].trim();
+            !text.is_empty() && !text.starts_with("//")
+        })
+    }
+}
+
+/// the rules named by an `// rmonkey: allow(...)` comment. unknown names are
+/// ignored.
+fn parse_allow(comment: &str) -> Option<Vec<Rule>> {
+    let names = comment
+        .strip_prefix("//")?
+        .trim()
+        .strip_prefix("rmonkey:")?
+        .trim()
+        .strip_prefix("allow(")?
+        .strip_suffix(')')?;
+    Some(
+        names
+            .split(',')
+            .filter_map(|name| Rule::from_name(name.trim()))
+            .collect(),
+    )
+}
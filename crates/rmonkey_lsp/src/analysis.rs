@@ -0,0 +1,268 @@
+//! Static analysis of a single document.
+//!
+//! [`rmonkey_resolver`] addresses identifiers and braces by their index in
+//! source order. This module maps those indices to the spans the parser
+//! recorded. Type errors from [`rmonkey_typeck`] are reported at their
+//! statement.
+
+use rmonkey_ast::span::Span;
+use rmonkey_lexer::Lexer;
+use rmonkey_parser::Parser;
+use rmonkey_resolver::{resolve, Binding, BindingId, Reference, Resolution, Severity};
+use rmonkey_typeck::check;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    /// `None` when the document does not parse
+    resolution: Option<Resolution>,
+    /// span of every identifier, in source order
+    idents: Vec<Span>,
+    /// span of every `{ ... }` pair, in source order of the `{`
+    braces: Vec<Span>,
+    /// span of every top-level statement
+    stmts: Vec<Span>,
+}
+
+impl Analysis {
+    pub fn new(text: &str) -> Self {
+        let mut analysis = Analysis::default();
+        let mut parser = Parser::new(Lexer::new(text));
+        let program = match parser.parse_program() {
+            Ok(program) => program,
+            Err(err) => {
+                analysis.diagnostics.push(Diagnostic {
+                    span: parser.span(),
+                    severity: Severity::Error,
+                    message: err.to_string(),
+                });
+                return analysis;
+            }
+        };
+
+        analysis.idents = program.idents.clone();
+        analysis.braces = program.blocks.iter().map(|b| b.span).collect();
+        analysis.stmts = program.spans.clone();
+        let resolution = resolve(&program);
+        analysis.diagnostics = resolution
+            .diagnostics
+            .iter()
+            .map(|d| Diagnostic {
+                span: analysis.idents[d.ident],
+                severity: d.severity(),
+                message: d.to_string(),
+            })
+            .collect();
+        analysis
+            .diagnostics
+            .extend(check(&program).iter().map(|e| Diagnostic {
+                span: e.span,
+                severity: Severity::Error,
+                message: e.to_string(),
+            }));
+        analysis.resolution = Some(resolution);
+        analysis
+    }
+
+    /// whether the document parsed, so that bindings and references are known
+    pub fn is_resolved(&self) -> bool {
+        self.resolution.is_some()
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        self.resolution
+            .as_ref()
+            .map(|r| r.bindings.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// span of the name of a binding
+    pub fn binding_span(&self, binding: &Binding) -> Span {
+        self.idents[binding.ident]
+    }
+
+    /// span of the statement of a top-level `let`, or else of its name
+    pub fn binding_stmt_span(&self, binding: &Binding) -> Span {
+        binding
+            .stmt
+            .and_then(|i| self.stmts.get(i).copied())
+            .unwrap_or(self.idents[binding.ident])
+    }
+
+    /// the binding declared or referenced at `offset`
+    pub fn binding_at(&self, offset: usize) -> Option<BindingId> {
+        self.resolution.as_ref()?.binding_at(self.ident_at(offset)?)
+    }
+
+    /// the reference at `offset` and its span
+    pub fn reference_at(&self, offset: usize) -> Option<(&Reference, Span)> {
+        let ident = self.ident_at(offset)?;
+        let reference = self.resolution.as_ref()?.reference(ident)?;
+        Some((reference, self.idents[ident]))
+    }
+
+    /// spans of the references to `binding`
+    pub fn references_to(&self, binding: BindingId) -> Vec<Span> {
+        match &self.resolution {
+            Some(resolution) => resolution
+                .references_to(binding)
+                .map(|r| self.idents[r.ident])
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// bindings visible at `offset`, innermost first, without shadowed names
+    pub fn visible_at(&self, offset: usize) -> Vec<&Binding> {
+        let Some(resolution) = &self.resolution else {
+            return Vec::new();
+        };
+        let contains = |span: &Span| span.start < offset && offset < span.end;
+        // scopes are numbered in source order, so the last match is the
+        // innermost
+        let scope = resolution
+            .scopes
+            .iter()
+            .rposition(|s| s.brace.is_some_and(|b| contains(&self.braces[b])))
+            .unwrap_or(0);
+        let order = self.idents.partition_point(|span| span.start < offset);
+        resolution.visible(scope, order)
+    }
+
+    fn ident_at(&self, offset: usize) -> Option<usize> {
+        self.idents
+            .iter()
+            .position(|span| span.start <= offset && offset <= span.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rmonkey_resolver::Target;
+
+    use super::*;
+
+    fn span_of(text: &str, needle: &str, nth: usize) -> Span {
+        let start = text.match_indices(needle).nth(nth).unwrap().0;
+        Span::new(start, start + needle.len())
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let text = "let a = 1;\nlet = 2;";
+        let analysis = Analysis::new(text);
+        assert!(!analysis.is_resolved());
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert_eq!(analysis.diagnostics[0].span, span_of(text, "= 2", 0));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let text = "let x = 1;\nlet add = fn(a, b) { a + b };\nadd(1, z);";
+        let analysis = Analysis::new(text);
+        assert_eq!(
+            analysis.diagnostics,
+            vec![
+                Diagnostic {
+                    span: span_of(text, "x", 0),
+                    severity: Severity::Warning,
+                    message: "unused binding: x".to_string(),
+                },
+                Diagnostic {
+                    span: span_of(text, "z", 0),
+                    severity: Severity::Error,
+                    message: "identifier not found: z".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let text = "let f = fn(s: string) { len(s) };\nf(1);";
+        let analysis = Analysis::new(text);
+        assert_eq!(
+            analysis.diagnostics,
+            vec![Diagnostic {
+                span: span_of(text, "f(1);", 0),
+                severity: Severity::Error,
+                message: "type mismatch: expected string, found int".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_references() {
+        let text = r#"
+let x = 1;
+let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };
+let h = {"key": fib(x)};
+len(h);
+"#;
+        let analysis = Analysis::new(text);
+        let x = analysis.binding_at(span_of(text, "x", 0).start).unwrap();
+        assert_eq!(analysis.references_to(x), vec![span_of(text, "x", 1)]);
+
+        let fib = analysis.binding_at(span_of(text, "fib", 0).start).unwrap();
+        assert_eq!(analysis.references_to(fib).len(), 3);
+        assert_eq!(analysis.binding_at(span_of(text, "fib", 2).end), Some(fib));
+
+        let (len, span) = analysis
+            .reference_at(span_of(text, "len", 0).start)
+            .unwrap();
+        assert_eq!(len.target, Target::Builtin);
+        assert_eq!(span, span_of(text, "len", 0));
+    }
+
+    #[test]
+    fn test_annotations() {
+        let text = "let f = fn(a: {string: int}) -> int { a[\"k\"] };\nf({\"k\": 1});";
+        let analysis = Analysis::new(text);
+        assert!(
+            analysis.diagnostics.is_empty(),
+            "{:?}",
+            analysis.diagnostics
+        );
+        let a = analysis.binding_at(span_of(text, "a", 0).start).unwrap();
+        let start = text.find("a[").unwrap();
+        assert_eq!(analysis.references_to(a), vec![Span::new(start, start + 1)]);
+    }
+
+    #[test]
+    fn test_visible_at() {
+        let text = "let a = 1;\nlet f = fn(x) {\n  let b = {};\n  \n};\nlet c = 3;";
+        let analysis = Analysis::new(text);
+        let names = |offset: usize| -> Vec<String> {
+            analysis
+                .visible_at(offset)
+                .iter()
+                .map(|b| b.name.clone())
+                .collect()
+        };
+        let in_body = text.find("\n  \n").unwrap() + 3;
+        assert_eq!(names(in_body), ["b", "x", "c", "f", "a"]);
+        assert_eq!(names(text.len()), ["c", "f", "a"]);
+        assert_eq!(names(0), Vec::<String>::new());
+
+        // the parameter of a `catch` is only visible in its body
+        let text = "let a = 1;\ntry { a } catch (e) {\n  \n};\n";
+        let analysis = Analysis::new(text);
+        let names = |offset: usize| -> Vec<String> {
+            analysis
+                .visible_at(offset)
+                .iter()
+                .map(|b| b.name.clone())
+                .collect()
+        };
+        let in_catch = text.find("\n  \n").unwrap() + 3;
+        assert_eq!(names(in_catch), ["e", "a"]);
+        assert_eq!(names(text.len()), ["a"]);
+    }
+}
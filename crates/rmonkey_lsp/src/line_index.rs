@@ -0,0 +1,88 @@
+//! Conversion between byte offsets, which the lexer reports, and LSP
+//! positions, which count UTF-16 code units within a line.
+
+use lsp_types::{Position, Range};
+use rmonkey_ast::span::Span;
+
+#[derive(Debug)]
+pub struct LineIndex {
+    text: String,
+    /// byte offset of the first character of each line
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        for (i, c) in text.char_indices() {
+            if c == '\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        LineIndex {
+            text: text.to_string(),
+            line_starts,
+        }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let character = self.text[start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        Position::new(line as u32, character as u32)
+    }
+
+    /// the byte offset of `pos`, clamped to the end of its line
+    pub fn offset(&self, pos: Position) -> usize {
+        let Some(&start) = self.line_starts.get(pos.line as usize) else {
+            return self.text.len();
+        };
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= pos.character as usize || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start), self.position(span.end))
+    }
+
+    /// the range covering the whole text
+    pub fn full_range(&self) -> Range {
+        Range::new(Position::new(0, 0), self.position(self.text.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        let index = LineIndex::new("let a = 1;\nlet b = \"é\";\n");
+        assert_eq!(index.position(0), Position::new(0, 0));
+        assert_eq!(index.position(4), Position::new(0, 4));
+        assert_eq!(index.position(11), Position::new(1, 0));
+        // `é` is two bytes but one UTF-16 code unit
+        assert_eq!(index.position(22), Position::new(1, 10));
+        assert_eq!(index.position(100), Position::new(2, 0));
+    }
+
+    #[test]
+    fn test_offset() {
+        let index = LineIndex::new("let a = 1;\nlet b = \"é\";\n");
+        assert_eq!(index.offset(Position::new(0, 4)), 4);
+        assert_eq!(index.offset(Position::new(1, 0)), 11);
+        assert_eq!(index.offset(Position::new(1, 10)), 22);
+        assert_eq!(index.offset(Position::new(0, 99)), 10);
+        assert_eq!(index.offset(Position::new(9, 0)), 25);
+    }
+}
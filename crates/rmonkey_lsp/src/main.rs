@@ -0,0 +1,26 @@
+//! Language server for Monkey, speaking LSP over stdio.
+
+use std::process::ExitCode;
+
+use lsp_server::Connection;
+
+mod analysis;
+mod line_index;
+mod server;
+
+fn main() -> ExitCode {
+    let (connection, io_threads) = Connection::stdio();
+    let result = serde_json::to_value(server::capabilities())
+        .map_err(|err| err.into())
+        .and_then(|capabilities| Ok(connection.initialize(capabilities)?))
+        .and_then(|_| server::run(&connection));
+    drop(connection);
+    let result = result.and_then(|_| Ok(io_threads.join()?));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rmonkey_lsp: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
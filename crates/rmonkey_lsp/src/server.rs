@@ -0,0 +1,558 @@
+use std::{collections::HashMap, error::Error};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{
+        Completion, DocumentSymbolRequest, Formatting, GotoDefinition, HoverRequest, References,
+        Request as _,
+    },
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    DiagnosticSeverity, DocumentFormattingParams, DocumentSymbol, DocumentSymbolParams,
+    DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf,
+    PublishDiagnosticsParams, ReferenceParams, ServerCapabilities, SymbolKind,
+    TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url,
+};
+use rmonkey_fmt::{
+    options::{find_config, FormatOptions},
+    Formatter,
+};
+use rmonkey_object::builtin::builtins;
+
+use rmonkey_resolver::{Binding, BindingKind, Severity, Target};
+
+use crate::{analysis::Analysis, line_index::LineIndex};
+
+pub type ServerResult<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    }
+}
+
+/// serve requests on an initialized connection until the client shuts down
+pub fn run(connection: &Connection) -> ServerResult<()> {
+    let mut server = Server::default();
+    for msg in connection.receiver.iter() {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                connection.sender.send(server.handle_request(req).into())?;
+            }
+            Message::Notification(not) => {
+                if let Some(diagnostics) = server.handle_notification(not) {
+                    let not =
+                        Notification::new(PublishDiagnostics::METHOD.to_string(), diagnostics);
+                    connection.sender.send(not.into())?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+struct Document {
+    text: String,
+    index: LineIndex,
+    analysis: Analysis,
+    /// the latest analysis that parsed, to complete names while the
+    /// document is being edited into shape
+    last_resolved: Option<Analysis>,
+}
+
+impl Document {
+    fn new(text: String, last_resolved: Option<Analysis>) -> Self {
+        let analysis = Analysis::new(&text);
+        let last_resolved = match analysis.is_resolved() {
+            true => None,
+            false => last_resolved,
+        };
+        Document {
+            index: LineIndex::new(&text),
+            text,
+            analysis,
+            last_resolved,
+        }
+    }
+
+    /// the analysis to use for names, stale or not
+    fn resolved(&self) -> Option<&Analysis> {
+        if self.analysis.is_resolved() {
+            Some(&self.analysis)
+        } else {
+            self.last_resolved.as_ref()
+        }
+    }
+
+    fn diagnostics(&self, uri: Url) -> PublishDiagnosticsParams {
+        let diagnostics = self
+            .analysis
+            .diagnostics
+            .iter()
+            .map(|d| lsp_types::Diagnostic {
+                range: self.index.range(d.span),
+                severity: Some(match d.severity {
+                    Severity::Error => DiagnosticSeverity::ERROR,
+                    Severity::Warning => DiagnosticSeverity::WARNING,
+                }),
+                source: Some("rmonkey".to_string()),
+                message: d.message.clone(),
+                ..Default::default()
+            })
+            .collect();
+        PublishDiagnosticsParams::new(uri, diagnostics, None)
+    }
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    fn handle_notification(&mut self, not: Notification) -> Option<PublishDiagnosticsParams> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = not
+                    .extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)
+                    .ok()?;
+                let uri = params.text_document.uri;
+                let doc = Document::new(params.text_document.text, None);
+                let diagnostics = doc.diagnostics(uri.clone());
+                self.documents.insert(uri, doc);
+                Some(diagnostics)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = not
+                    .extract::<lsp_types::DidChangeTextDocumentParams>(
+                        DidChangeTextDocument::METHOD,
+                    )
+                    .ok()?;
+                let uri = params.text_document.uri;
+                // full sync, so the last change holds the whole text
+                let text = params.content_changes.into_iter().last()?.text;
+                let last_resolved =
+                    self.documents
+                        .remove(&uri)
+                        .and_then(|doc| match doc.analysis.is_resolved() {
+                            true => Some(doc.analysis),
+                            false => doc.last_resolved,
+                        });
+                let doc = Document::new(text, last_resolved);
+                let diagnostics = doc.diagnostics(uri.clone());
+                self.documents.insert(uri, doc);
+                Some(diagnostics)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = not
+                    .extract::<lsp_types::DidCloseTextDocumentParams>(DidCloseTextDocument::METHOD)
+                    .ok()?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                Some(PublishDiagnosticsParams::new(uri, Vec::new(), None))
+            }
+            _ => None,
+        }
+    }
+
+    fn handle_request(&mut self, req: Request) -> Response {
+        match req.method.as_str() {
+            HoverRequest::METHOD => self.respond::<HoverRequest>(req, Server::hover),
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(req, Server::definition),
+            References::METHOD => self.respond::<References>(req, Server::references),
+            DocumentSymbolRequest::METHOD => {
+                self.respond::<DocumentSymbolRequest>(req, Server::document_symbols)
+            }
+            Completion::METHOD => self.respond::<Completion>(req, Server::completion),
+            Formatting::METHOD => self.respond::<Formatting>(req, Server::format),
+            method => Response::new_err(
+                req.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request: {method}"),
+            ),
+        }
+    }
+
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        req: Request,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value::<R::Params>(req.params) {
+            Ok(params) => Response::new_ok(req.id, handler(self, params)),
+            Err(err) => Response::new_err(req.id, ErrorCode::InvalidParams as i32, err.to_string()),
+        }
+    }
+
+    /// the document and the byte offset of the position
+    fn locate(&self, params: &TextDocumentPositionParams) -> Option<(&Document, usize)> {
+        let doc = self.documents.get(&params.text_document.uri)?;
+        Some((doc, doc.index.offset(params.position)))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (doc, offset) = self.locate(&params.text_document_position_params)?;
+        let (detail, span) = match doc.analysis.binding_at(offset) {
+            Some(id) => {
+                let binding = &doc.analysis.bindings()[id];
+                let span = match doc.analysis.reference_at(offset) {
+                    Some((_, span)) => span,
+                    None => doc.analysis.binding_span(binding),
+                };
+                (binding.detail(), span)
+            }
+            None => {
+                let (reference, span) = doc.analysis.reference_at(offset)?;
+                if reference.target != Target::Builtin {
+                    return None;
+                }
+                (format!("builtin {}", reference.name), span)
+            }
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```monkey\n{detail}\n```"),
+            }),
+            range: Some(doc.index.range(span)),
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .clone();
+        let (doc, offset) = self.locate(&params.text_document_position_params)?;
+        let binding = &doc.analysis.bindings()[doc.analysis.binding_at(offset)?];
+        let span = doc.analysis.binding_span(binding);
+        let location = Location::new(uri, doc.index.range(span));
+        Some(GotoDefinitionResponse::Scalar(location))
+    }
+
+    fn references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let (doc, offset) = self.locate(&params.text_document_position)?;
+        let id = doc.analysis.binding_at(offset)?;
+        let mut spans = Vec::new();
+        if params.context.include_declaration {
+            spans.push(doc.analysis.binding_span(&doc.analysis.bindings()[id]));
+        }
+        spans.extend(doc.analysis.references_to(id));
+        let locations = spans
+            .into_iter()
+            .map(|span| Location::new(uri.clone(), doc.index.range(span)))
+            .collect();
+        Some(locations)
+    }
+
+    #[allow(deprecated)]
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let doc = self.documents.get(&params.text_document.uri)?;
+        if !doc.analysis.is_resolved() {
+            return None;
+        }
+        let bindings = doc.analysis.bindings();
+        let symbol = |binding: &Binding, range, children| DocumentSymbol {
+            name: binding.name.clone(),
+            detail: Some(binding.detail()),
+            kind: match (binding.kind, &binding.params) {
+                (BindingKind::Import, _) => SymbolKind::MODULE,
+                (_, Some(_)) => SymbolKind::FUNCTION,
+                (_, None) => SymbolKind::VARIABLE,
+            },
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range: doc.index.range(doc.analysis.binding_span(binding)),
+            children,
+        };
+
+        let symbols = bindings
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| {
+                matches!(b.kind, BindingKind::Let | BindingKind::Import) && b.container.is_none()
+            })
+            .map(|(id, binding)| {
+                let children: Vec<DocumentSymbol> = bindings
+                    .iter()
+                    .filter(|b| b.kind == BindingKind::Let && b.container == Some(id))
+                    .map(|b| symbol(b, doc.index.range(doc.analysis.binding_span(b)), None))
+                    .collect();
+                let range = doc.index.range(doc.analysis.binding_stmt_span(binding));
+                symbol(binding, range, Some(children).filter(|c| !c.is_empty()))
+            })
+            .collect();
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let (doc, offset) = self.locate(&params.text_document_position)?;
+        let bindings: Vec<&Binding> = if doc.analysis.is_resolved() {
+            doc.analysis.visible_at(offset)
+        } else {
+            doc.resolved()
+                .map(|analysis| analysis.bindings().iter().collect())
+                .unwrap_or_default()
+        };
+
+        let mut items: Vec<CompletionItem> = Vec::new();
+        for binding in bindings {
+            if items.iter().any(|item| item.label == binding.name) {
+                continue;
+            }
+            items.push(CompletionItem {
+                label: binding.name.clone(),
+                kind: Some(match binding.params {
+                    Some(_) => CompletionItemKind::FUNCTION,
+                    None => CompletionItemKind::VARIABLE,
+                }),
+                detail: Some(binding.detail()),
+                ..Default::default()
+            });
+        }
+        let mut names: Vec<&str> = builtins().into_keys().collect();
+        names.sort();
+        for name in names {
+            if items.iter().any(|item| item.label == name) {
+                continue;
+            }
+            items.push(CompletionItem {
+                label: name.to_string(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some(format!("builtin {name}")),
+                ..Default::default()
+            });
+        }
+        Some(CompletionResponse::Array(items))
+    }
+
+    /// `rmonkey.toml` wins over the editor's indentation settings
+    fn format(&self, params: DocumentFormattingParams) -> Option<Vec<TextEdit>> {
+        let uri = &params.text_document.uri;
+        let doc = self.documents.get(uri)?;
+        let dir = uri.to_file_path().ok();
+        let dir = dir.as_deref().and_then(|path| path.parent());
+        let options = match dir.and_then(find_config) {
+            Some(_) => FormatOptions::load(dir?).ok()?,
+            None => FormatOptions {
+                indent_size: params.options.tab_size as usize,
+                use_tabs: !params.options.insert_spaces,
+                ..Default::default()
+            },
+        };
+        let formatted = Formatter::new(options).fmt_source(&doc.text).ok()?;
+        let formatted = format!("{formatted}\n");
+        if formatted == doc.text {
+            return Some(Vec::new());
+        }
+        Some(vec![TextEdit::new(doc.index.full_range(), formatted)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use lsp_server::RequestId;
+    use lsp_types::{
+        notification::Exit, request::Shutdown, DidOpenTextDocumentParams, FormattingOptions,
+        Position, TextDocumentIdentifier, TextDocumentItem,
+    };
+    use serde::de::DeserializeOwned;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    struct Client {
+        connection: Connection,
+        next_id: i32,
+    }
+
+    impl Client {
+        fn request<R: lsp_types::request::Request>(&mut self, params: Value) -> R::Result
+        where
+            R::Result: DeserializeOwned,
+        {
+            self.next_id += 1;
+            let req = Request::new(RequestId::from(self.next_id), R::METHOD.to_string(), params);
+            self.connection.sender.send(req.into()).unwrap();
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(resp) => {
+                    assert!(resp.error.is_none(), "{:?}", resp.error);
+                    serde_json::from_value(resp.result.unwrap()).unwrap()
+                }
+                msg => panic!("expected a response, got {msg:?}"),
+            }
+        }
+
+        fn notify<N: lsp_types::notification::Notification>(&self, params: Value) {
+            let not = Notification::new(N::METHOD.to_string(), params);
+            self.connection.sender.send(not.into()).unwrap();
+        }
+    }
+
+    fn position(uri: &Url, line: u32, character: u32) -> Value {
+        json!({"textDocument": {"uri": uri}, "position": Position::new(line, character)})
+    }
+
+    #[test]
+    fn test_session() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || run(&server).unwrap());
+        let mut client = Client {
+            connection: client,
+            next_id: 0,
+        };
+
+        let uri = Url::parse("untitled:test.monkey").unwrap();
+        let text = "let add = fn(a, b) { a + b };\nlet x = add(1, 2);\nlen(y)\n";
+        client.notify::<DidOpenTextDocument>(
+            serde_json::to_value(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(uri.clone(), "monkey".into(), 1, text.into()),
+            })
+            .unwrap(),
+        );
+        let Message::Notification(not) = client.connection.receiver.recv().unwrap() else {
+            panic!("expected diagnostics");
+        };
+        let diagnostics: PublishDiagnosticsParams = serde_json::from_value(not.params).unwrap();
+        let diagnostics: Vec<(Option<DiagnosticSeverity>, &str)> = diagnostics
+            .diagnostics
+            .iter()
+            .map(|d| (d.severity, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                (Some(DiagnosticSeverity::WARNING), "unused binding: x"),
+                (Some(DiagnosticSeverity::ERROR), "identifier not found: y"),
+            ]
+        );
+
+        let hover = client
+            .request::<HoverRequest>(position(&uri, 1, 9))
+            .unwrap();
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markup");
+        };
+        assert_eq!(markup.value, "```monkey\nlet add = fn(a, b)\n```");
+
+        let definition = client.request::<GotoDefinition>(position(&uri, 1, 9));
+        let Some(GotoDefinitionResponse::Scalar(location)) = definition else {
+            panic!("expected a location");
+        };
+        assert_eq!(location.range.start, Position::new(0, 4));
+
+        let mut params = position(&uri, 0, 4);
+        params["context"] = json!({"includeDeclaration": true});
+        assert_eq!(client.request::<References>(params).unwrap().len(), 2);
+
+        let Some(CompletionResponse::Array(items)) =
+            client.request::<Completion>(position(&uri, 2, 0))
+        else {
+            panic!("expected completion items");
+        };
+        let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "x",
+                "add",
+                "all",
+                "any",
+                "chars",
+                "concat",
+                "contains",
+                "delete",
+                "ends_with",
+                "entries",
+                "enumerate",
+                "error",
+                "filter",
+                "find",
+                "first",
+                "flatten",
+                "format",
+                "from_entries",
+                "has",
+                "index_of",
+                "is_error",
+                "join",
+                "json_parse",
+                "json_stringify",
+                "keys",
+                "last",
+                "len",
+                "lower",
+                "map",
+                "math",
+                "merge",
+                "push",
+                "puts",
+                "random",
+                "range",
+                "reduce",
+                "replace",
+                "rest",
+                "reverse",
+                "slice",
+                "sort",
+                "split",
+                "starts_with",
+                "trim",
+                "unwrap",
+                "upper",
+                "values",
+                "zip"
+            ]
+        );
+
+        let symbols = client.request::<DocumentSymbolRequest>(
+            json!({"textDocument": TextDocumentIdentifier::new(uri.clone())}),
+        );
+        let Some(DocumentSymbolResponse::Nested(symbols)) = symbols else {
+            panic!("expected symbols");
+        };
+        let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["add", "x"]);
+
+        let edits = client.request::<Formatting>(
+            serde_json::to_value(DocumentFormattingParams {
+                text_document: TextDocumentIdentifier::new(uri.clone()),
+                options: FormattingOptions {
+                    tab_size: 4,
+                    insert_spaces: true,
+                    ..Default::default()
+                },
+                work_done_progress_params: Default::default(),
+            })
+            .unwrap(),
+        );
+        assert_eq!(
+            edits.unwrap()[0].new_text,
+            "let add = fn(a, b) {\n    a + b;\n};\nlet x = add(1, 2);\nlen(y);\n"
+        );
+
+        client.request::<Shutdown>(Value::Null);
+        client.notify::<Exit>(Value::Null);
+        handle.join().unwrap();
+    }
+}
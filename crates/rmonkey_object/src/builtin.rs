@@ -1,7 +1,28 @@
 use std::collections::HashMap;
 
 use crate::Object;
-use rmonkey_error::{RMonkeyError, Result};
+use rmonkey_error::{eval_error::EvalErrorKind, RMonkeyError, Result};
+
+mod array;
+mod hash;
+mod json;
+mod math;
+mod random;
+mod string;
+
+pub use random::Rng;
+
+/// what a builtin calls the functions it is given through, i.e. the
+/// evaluator
+pub trait Caller {
+    /// call a Monkey function or a builtin
+    fn call(&mut self, func: Object, args: Vec<Object>) -> Result<Object>;
+
+    /// the next number of the host's random generator
+    fn random(&mut self) -> u64;
+}
+
+pub type BuiltinFn = fn(&mut dyn Caller, Vec<Object>) -> Result<Object>;
 
 pub fn builtins() -> HashMap<&'static str, Object> {
     let mut builtin: HashMap<&'static str, Object> = HashMap::new();
@@ -11,10 +32,41 @@ pub fn builtins() -> HashMap<&'static str, Object> {
     builtin.insert("rest", Object::BuiltIn { func: rest });
     builtin.insert("push", Object::BuiltIn { func: push });
     builtin.insert("puts", Object::BuiltIn { func: puts });
+    builtin.insert("error", Object::BuiltIn { func: error });
+    builtin.insert("is_error", Object::BuiltIn { func: is_error });
+    builtin.insert("unwrap", Object::BuiltIn { func: unwrap });
+    for (name, func) in array::BUILTINS
+        .into_iter()
+        .chain(hash::BUILTINS)
+        .chain(string::BUILTINS)
+        .chain(json::BUILTINS)
+    {
+        builtin.insert(name, Object::BuiltIn { func });
+    }
+    let mut math = namespace("math", &math::BUILTINS);
+    if let Object::Module { exports, .. } = &mut math {
+        for (name, val) in math::CONSTANTS {
+            exports.insert(name.to_string(), Object::Float(val));
+        }
+    }
+    builtin.insert("math", math);
+    builtin.insert("random", namespace("random", &random::BUILTINS));
     builtin
 }
 
-pub fn len(args: Vec<Object>) -> Result<Object> {
+/// builtins grouped under a name, used like an imported module:
+/// `math["sqrt"](2)`
+fn namespace(path: &str, functions: &[(&str, BuiltinFn)]) -> Object {
+    Object::Module {
+        path: path.to_string(),
+        exports: functions
+            .iter()
+            .map(|&(name, func)| (name.to_string(), Object::BuiltIn { func }))
+            .collect(),
+    }
+}
+
+pub fn len(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
     if args.len() > 1 {
         return Err(RMonkeyError::Custom(format!(
             "wrong number of args. got={}, want=1",
@@ -23,20 +75,18 @@ pub fn len(args: Vec<Object>) -> Result<Object> {
     }
     match args.get(0) {
         Some(obj) => match obj {
-            Object::String(val) => Ok(Object::Int(val.len() as i64)),
+            Object::String(val) => Ok(Object::Int(val.chars().count() as i64)),
             Object::Array { elements } => Ok(Object::Int(elements.len() as i64)),
-            _ => {
-                Err(RMonkeyError::Custom(format!(
-                    "arg to `len` not supported, got {}",
-                    obj.obj_type()
-                )))
-            }
+            _ => Err(RMonkeyError::Custom(format!(
+                "arg to `len` not supported, got {}",
+                obj.obj_type()
+            ))),
         },
         None => Ok(Object::Null),
     }
 }
 
-pub fn first(args: Vec<Object>) -> Result<Object> {
+pub fn first(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
     if args.len() > 1 {
         return Err(RMonkeyError::Custom(format!(
             "wrong number of args. got={}, want=1",
@@ -54,7 +104,7 @@ pub fn first(args: Vec<Object>) -> Result<Object> {
     Ok(Object::Null)
 }
 
-pub fn last(args: Vec<Object>) -> Result<Object> {
+pub fn last(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
     if args.len() > 1 {
         return Err(RMonkeyError::Custom(format!(
             "wrong number of args. got={}, want=1",
@@ -80,7 +130,7 @@ pub fn last(args: Vec<Object>) -> Result<Object> {
     )))
 }
 
-pub fn rest(args: Vec<Object>) -> Result<Object> {
+pub fn rest(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
     if args.len() > 1 {
         return Err(RMonkeyError::Custom(format!(
             "wrong number of args. got={}, want=1",
@@ -109,7 +159,7 @@ pub fn rest(args: Vec<Object>) -> Result<Object> {
     )))
 }
 
-pub fn push(args: Vec<Object>) -> Result<Object> {
+pub fn push(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
     if args.len() > 2 {
         return Err(RMonkeyError::Custom(format!(
             "wrong number of args. got={}, want=1",
@@ -136,9 +186,115 @@ pub fn push(args: Vec<Object>) -> Result<Object> {
     )))
 }
 
-fn puts(args: Vec<Object>) -> Result<Object> {
+fn puts(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
     for a in args.iter() {
         println!("{a}");
     }
     Ok(Object::Null)
 }
+
+/// an error value with a message and an optional payload. unlike `throw`,
+/// it is returned like any other value; `?` passes it on to the caller.
+pub fn error(_: &mut dyn Caller, mut args: Vec<Object>) -> Result<Object> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(RMonkeyError::Custom(format!(
+            "wrong number of args. got={}, want=1 or 2",
+            args.len()
+        )));
+    }
+    let payload = if args.len() == 2 { args.pop() } else { None };
+    match args.remove(0) {
+        Object::String(message) => Ok(Object::Error {
+            kind: "Error".to_string(),
+            message,
+            stack: Vec::new(),
+            payload: payload.map(Box::new),
+        }),
+        obj => Err(RMonkeyError::Custom(format!(
+            "argument to `error` must be STRING, got {}",
+            obj.obj_type()
+        ))),
+    }
+}
+
+pub fn is_error(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(RMonkeyError::Custom(format!(
+            "wrong number of args. got={}, want=1",
+            args.len()
+        )));
+    }
+    Ok(Object::Bool(matches!(args[0], Object::Error { .. })))
+}
+
+/// the argument, unless it is an error value, which is raised instead
+pub fn unwrap(_: &mut dyn Caller, mut args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(RMonkeyError::Custom(format!(
+            "wrong number of args. got={}, want=1",
+            args.len()
+        )));
+    }
+    match args.remove(0) {
+        error @ Object::Error { .. } => Err(RMonkeyError::EvalError(EvalErrorKind::Unwrap {
+            error: error.to_string(),
+        })),
+        obj => Ok(obj),
+    }
+}
+
+/// fail unless there are between `min` and `max` arguments
+fn arity(args: &[Object], min: usize, max: usize) -> Result<()> {
+    if (min..=max).contains(&args.len()) {
+        return Ok(());
+    }
+    let want = match max - min {
+        0 => min.to_string(),
+        1 => format!("{min} or {max}"),
+        _ => format!("{min} to {max}"),
+    };
+    Err(RMonkeyError::Custom(format!(
+        "wrong number of args. got={}, want={want}",
+        args.len()
+    )))
+}
+
+fn array(name: &str, obj: Object) -> Result<Vec<Object>> {
+    match obj {
+        Object::Array { elements } => Ok(elements),
+        obj => Err(RMonkeyError::Custom(format!(
+            "argument to `{name}` must be ARRAY, got {}",
+            obj.obj_type()
+        ))),
+    }
+}
+
+fn function(name: &str, obj: Object) -> Result<Object> {
+    match obj {
+        Object::Func { .. } | Object::BuiltIn { .. } | Object::Native { .. } => Ok(obj),
+        obj => Err(RMonkeyError::Custom(format!(
+            "argument to `{name}` must be FUNCTION, got {}",
+            obj.obj_type()
+        ))),
+    }
+}
+
+fn int(name: &str, obj: Object) -> Result<i64> {
+    match obj {
+        Object::Int(val) => Ok(val),
+        obj => Err(RMonkeyError::Custom(format!(
+            "argument to `{name}` must be INTEGER, got {}",
+            obj.obj_type()
+        ))),
+    }
+}
+
+fn string(name: &str, obj: Object) -> Result<String> {
+    match obj {
+        Object::String(val) => Ok(val),
+        obj => Err(RMonkeyError::Custom(format!(
+            "argument to `{name}` must be STRING, got {}",
+            obj.obj_type()
+        ))),
+    }
+}
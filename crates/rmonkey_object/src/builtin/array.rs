@@ -0,0 +1,283 @@
+//! Builtins over arrays, among them the higher-order ones, which call the
+//! function they are given through the [`Caller`].
+
+use std::cmp::Ordering;
+
+use rmonkey_error::{RMonkeyError, Result};
+
+use super::{arity, array, function, int, BuiltinFn, Caller};
+use crate::Object;
+
+pub(super) const BUILTINS: [(&str, BuiltinFn); 14] = [
+    ("map", map),
+    ("filter", filter),
+    ("reduce", reduce),
+    ("find", find),
+    ("any", any),
+    ("all", all),
+    ("sort", sort),
+    ("reverse", reverse),
+    ("zip", zip),
+    ("enumerate", enumerate),
+    ("flatten", flatten),
+    ("range", range),
+    ("concat", concat),
+    ("slice", slice),
+];
+
+/// `f(x)` for each element `x`
+fn map(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    let (elements, f) = array_and_function("map", args)?;
+    let elements = elements
+        .into_iter()
+        .map(|element| caller.call(f.clone(), vec![element]))
+        .collect::<Result<_>>()?;
+    Ok(Object::Array { elements })
+}
+
+/// the elements for which `f` is truthy
+fn filter(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    let (elements, f) = array_and_function("filter", args)?;
+    let mut kept = Vec::new();
+    for element in elements {
+        if caller.call(f.clone(), vec![element.clone()])?.is_truthy() {
+            kept.push(element);
+        }
+    }
+    Ok(Object::Array { elements: kept })
+}
+
+/// `f(f(initial, a), b)` and so on. without `initial`, the first element
+/// is the initial value.
+fn reduce(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 3)?;
+    let mut args = args.into_iter();
+    let mut elements = array("reduce", args.next().unwrap())?.into_iter();
+    let f = function("reduce", args.next().unwrap())?;
+    let mut acc = match args.next() {
+        Some(initial) => initial,
+        None => elements.next().ok_or_else(|| {
+            RMonkeyError::Custom("`reduce` of an empty array needs an initial value".to_string())
+        })?,
+    };
+    for element in elements {
+        acc = caller.call(f.clone(), vec![acc, element])?;
+    }
+    Ok(acc)
+}
+
+/// the first element for which `f` is truthy, or null
+fn find(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    let (elements, f) = array_and_function("find", args)?;
+    for element in elements {
+        if caller.call(f.clone(), vec![element.clone()])?.is_truthy() {
+            return Ok(element);
+        }
+    }
+    Ok(Object::Null)
+}
+
+/// whether `f` is truthy for some element. stops at the first one.
+fn any(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    let (elements, f) = array_and_function("any", args)?;
+    for element in elements {
+        if caller.call(f.clone(), vec![element])?.is_truthy() {
+            return Ok(Object::Bool(true));
+        }
+    }
+    Ok(Object::Bool(false))
+}
+
+/// whether `f` is truthy for every element. stops at the first it isn't.
+fn all(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    let (elements, f) = array_and_function("all", args)?;
+    for element in elements {
+        if !caller.call(f.clone(), vec![element])?.is_truthy() {
+            return Ok(Object::Bool(false));
+        }
+    }
+    Ok(Object::Bool(true))
+}
+
+/// the elements in ascending order, which must be all integers or all
+/// strings, or in the order of `compare(a, b)`, an integer that is negative
+/// when `a` goes first, zero when they are equal and positive otherwise.
+/// the sort is stable.
+fn sort(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 2)?;
+    let mut args = args.into_iter();
+    let elements = array("sort", args.next().unwrap())?;
+    let elements = match args.next() {
+        Some(compare) => {
+            let compare = function("sort", compare)?;
+            merge_sort(elements, &mut |a, b| match caller
+                .call(compare.clone(), vec![a.clone(), b.clone()])?
+            {
+                Object::Int(val) => Ok(val.cmp(&0)),
+                obj => Err(RMonkeyError::Custom(format!(
+                    "the comparator of `sort` must return INTEGER, got {}",
+                    obj.obj_type()
+                ))),
+            })?
+        }
+        None => merge_sort(elements, &mut |a, b| match (a, b) {
+            (Object::Int(a), Object::Int(b)) => Ok(a.cmp(b)),
+            (Object::String(a), Object::String(b)) => Ok(a.cmp(b)),
+            (a, b) => Err(RMonkeyError::Custom(format!(
+                "`sort` cannot compare {} with {}",
+                a.obj_type(),
+                b.obj_type()
+            ))),
+        })?,
+    };
+    Ok(Object::Array { elements })
+}
+
+/// a stable merge sort with a comparison that can fail. `sort_by` can't stop
+/// at an error and may panic when a comparator is not a total order.
+fn merge_sort(
+    mut elements: Vec<Object>,
+    compare: &mut impl FnMut(&Object, &Object) -> Result<Ordering>,
+) -> Result<Vec<Object>> {
+    if elements.len() <= 1 {
+        return Ok(elements);
+    }
+    let right = elements.split_off(elements.len() / 2);
+    let left = merge_sort(elements, compare)?;
+    let right = merge_sort(right, compare)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // equal elements keep their order
+        let next = if compare(l, r)? == Ordering::Greater {
+            right.next()
+        } else {
+            left.next()
+        };
+        merged.extend(next);
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+fn reverse(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let mut elements = array("reverse", args.into_iter().next().unwrap())?;
+    elements.reverse();
+    Ok(Object::Array { elements })
+}
+
+/// `[a[i], b[i]]` pairs, as many as the shorter array has elements
+fn zip(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let mut args = args.into_iter();
+    let a = array("zip", args.next().unwrap())?;
+    let b = array("zip", args.next().unwrap())?;
+    let elements = a
+        .into_iter()
+        .zip(b)
+        .map(|(a, b)| Object::Array {
+            elements: vec![a, b],
+        })
+        .collect();
+    Ok(Object::Array { elements })
+}
+
+/// `[i, element]` pairs
+fn enumerate(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let elements = array("enumerate", args.into_iter().next().unwrap())?;
+    let elements = elements
+        .into_iter()
+        .enumerate()
+        .map(|(i, element)| Object::Array {
+            elements: vec![Object::Int(i as i64), element],
+        })
+        .collect();
+    Ok(Object::Array { elements })
+}
+
+/// the elements of the nested arrays, one level deep. other elements are
+/// kept as they are.
+fn flatten(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let elements = array("flatten", args.into_iter().next().unwrap())?;
+    let mut flat = Vec::new();
+    for element in elements {
+        match element {
+            Object::Array { elements } => flat.extend(elements),
+            element => flat.push(element),
+        }
+    }
+    Ok(Object::Array { elements: flat })
+}
+
+/// `range(end)`, `range(start, end)` or `range(start, end, step)`: the
+/// integers from `start`, 0 by default, up to but excluding `end`
+fn range(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 3)?;
+    let args = args
+        .into_iter()
+        .map(|arg| int("range", arg))
+        .collect::<Result<Vec<i64>>>()?;
+    let (start, end, step) = match args[..] {
+        [end] => (0, end, 1),
+        [start, end] => (start, end, 1),
+        [start, end, step] => (start, end, step),
+        _ => unreachable!(),
+    };
+    if step == 0 {
+        return Err(RMonkeyError::Custom(
+            "the step of `range` must not be 0".to_string(),
+        ));
+    }
+    let mut elements = Vec::new();
+    let mut i = start;
+    while (step > 0 && i < end) || (step < 0 && i > end) {
+        elements.push(Object::Int(i));
+        match i.checked_add(step) {
+            Some(next) => i = next,
+            None => break,
+        }
+    }
+    Ok(Object::Array { elements })
+}
+
+/// the elements of all the arrays, in order
+fn concat(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    let mut elements = Vec::new();
+    for arg in args {
+        elements.extend(array("concat", arg)?);
+    }
+    Ok(Object::Array { elements })
+}
+
+/// `slice(x, start)` or `slice(x, start, end)`: the elements of an array,
+/// or the characters of a string, from `start` up to but excluding `end`,
+/// by default the length. a negative index counts from the end, and indices
+/// are clamped to the length.
+fn slice(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 3)?;
+    let mut args = args.into_iter();
+    let seq = args.next().unwrap();
+    let start = int("slice", args.next().unwrap())?;
+    let end = args.next().map(|end| int("slice", end)).transpose()?;
+    seq.slice(Some(start), end, 1).ok_or_else(|| {
+        RMonkeyError::Custom(format!(
+            "argument to `slice` must be ARRAY or STRING, got {}",
+            seq.obj_type()
+        ))
+    })
+}
+
+/// the array and the function a builtin like `map` takes
+fn array_and_function(name: &str, args: Vec<Object>) -> Result<(Vec<Object>, Object)> {
+    arity(&args, 2, 2)?;
+    let mut args = args.into_iter();
+    let elements = array(name, args.next().unwrap())?;
+    let f = function(name, args.next().unwrap())?;
+    Ok((elements, f))
+}
@@ -0,0 +1,110 @@
+//! Builtins over hashes. like the ones over arrays, they return a new hash
+//! rather than change the one they are given.
+
+use rmonkey_error::{RMonkeyError, Result};
+
+use super::{arity, array, BuiltinFn, Caller};
+use crate::{IndexMap, Object};
+
+pub(super) const BUILTINS: [(&str, BuiltinFn); 7] = [
+    ("keys", keys),
+    ("values", values),
+    ("entries", entries),
+    ("has", has),
+    ("delete", delete),
+    ("merge", merge),
+    ("from_entries", from_entries),
+];
+
+fn keys(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let pairs = hash("keys", args.into_iter().next().unwrap())?;
+    Ok(Object::Array {
+        elements: pairs.into_keys().collect(),
+    })
+}
+
+fn values(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let pairs = hash("values", args.into_iter().next().unwrap())?;
+    Ok(Object::Array {
+        elements: pairs.into_values().collect(),
+    })
+}
+
+/// `[key, value]` pairs
+fn entries(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let pairs = hash("entries", args.into_iter().next().unwrap())?;
+    let elements = pairs
+        .into_iter()
+        .map(|(key, value)| Object::Array {
+            elements: vec![key, value],
+        })
+        .collect();
+    Ok(Object::Array { elements })
+}
+
+/// whether the hash has the key, even if its value is null
+fn has(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let mut args = args.into_iter();
+    let pairs = hash("has", args.next().unwrap())?;
+    let key = args.next().unwrap();
+    key.check_hashable()?;
+    Ok(Object::Bool(pairs.contains_key(&key)))
+}
+
+/// the hash without the key. the other pairs keep their order.
+fn delete(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let mut args = args.into_iter();
+    let mut pairs = hash("delete", args.next().unwrap())?;
+    let key = args.next().unwrap();
+    key.check_hashable()?;
+    pairs.shift_remove(&key);
+    Ok(Object::Hash(pairs))
+}
+
+/// the pairs of all the hashes. a key in a later hash replaces the value
+/// but keeps the place it had in the first hash with it.
+fn merge(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    let mut merged = IndexMap::new();
+    for arg in args {
+        merged.extend(hash("merge", arg)?);
+    }
+    Ok(Object::Hash(merged))
+}
+
+/// the hash of an array of `[key, value]` pairs, the inverse of `entries`
+fn from_entries(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let entries = array("from_entries", args.into_iter().next().unwrap())?;
+    let mut pairs = IndexMap::new();
+    for entry in entries {
+        match entry {
+            Object::Array { elements } if elements.len() == 2 => {
+                let mut elements = elements.into_iter();
+                let key = elements.next().unwrap();
+                key.check_hashable()?;
+                pairs.insert(key, elements.next().unwrap());
+            }
+            entry => {
+                return Err(RMonkeyError::Custom(format!(
+                    "an entry of `from_entries` must be a [key, value] pair, got {entry}"
+                )))
+            }
+        }
+    }
+    Ok(Object::Hash(pairs))
+}
+
+fn hash(name: &str, obj: Object) -> Result<IndexMap<Object, Object>> {
+    match obj {
+        Object::Hash(pairs) => Ok(pairs),
+        obj => Err(RMonkeyError::Custom(format!(
+            "argument to `{name}` must be HASH, got {}",
+            obj.obj_type()
+        ))),
+    }
+}
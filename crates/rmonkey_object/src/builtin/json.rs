@@ -0,0 +1,38 @@
+//! Builtins that read and write JSON, see [`crate::json`].
+
+use rmonkey_error::{RMonkeyError, Result};
+
+use super::{arity, int, string, BuiltinFn, Caller};
+use crate::{json, Object};
+
+const MAX_INDENT: i64 = json::MAX_INDENT as i64;
+
+pub(super) const BUILTINS: [(&str, BuiltinFn); 2] = [
+    ("json_parse", json_parse),
+    ("json_stringify", json_stringify),
+];
+
+fn json_parse(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let text = string("json_parse", args.into_iter().next().unwrap())?;
+    json::from_json(&text)
+}
+
+/// `json_stringify(obj)` on one line, or `json_stringify(obj, indent)` with
+/// each level indented by `indent` spaces, at most [`json::MAX_INDENT`]
+fn json_stringify(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 2)?;
+    let mut args = args.into_iter();
+    let obj = args.next().unwrap();
+    let indent = match args.next().map(|indent| int("json_stringify", indent)) {
+        Some(Ok(indent @ 0..=MAX_INDENT)) => Some(indent as usize),
+        Some(Ok(indent)) => {
+            return Err(RMonkeyError::Custom(format!(
+                "the indent of `json_stringify` must be from 0 to {MAX_INDENT}, got {indent}"
+            )))
+        }
+        Some(Err(err)) => return Err(err),
+        None => None,
+    };
+    Ok(Object::String(json::to_json(&obj, indent)?))
+}
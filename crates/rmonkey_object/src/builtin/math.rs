@@ -0,0 +1,190 @@
+//! The `math` namespace. integers stay integers where the result is one,
+//! and anything that involves a float gives a float.
+
+use rmonkey_error::{eval_error::EvalErrorKind, RMonkeyError, Result};
+
+use super::{arity, BuiltinFn, Caller};
+use crate::Object;
+
+pub(super) const BUILTINS: [(&str, BuiltinFn); 16] = [
+    ("abs", abs),
+    ("min", min),
+    ("max", max),
+    ("pow", pow),
+    ("sqrt", sqrt),
+    ("floor", floor),
+    ("ceil", ceil),
+    ("round", round),
+    ("sin", sin),
+    ("cos", cos),
+    ("tan", tan),
+    ("asin", asin),
+    ("acos", acos),
+    ("atan", atan),
+    ("atan2", atan2),
+    ("log", log),
+];
+
+pub(super) const CONSTANTS: [(&str, f64); 2] =
+    [("pi", std::f64::consts::PI), ("e", std::f64::consts::E)];
+
+fn abs(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    match args.into_iter().next().unwrap() {
+        Object::Int(val) => val
+            .checked_abs()
+            .map(Object::Int)
+            .ok_or_else(|| overflow(format!("abs({val})"))),
+        obj => Ok(Object::Float(number("abs", obj)?.abs())),
+    }
+}
+
+/// the smallest of one or more numbers
+fn min(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    extreme("min", args, |a, b| b < a)
+}
+
+/// the largest of one or more numbers
+fn max(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    extreme("max", args, |a, b| b > a)
+}
+
+/// the first of the numbers that no later one `beats`
+fn extreme(name: &str, args: Vec<Object>, beats: fn(f64, f64) -> bool) -> Result<Object> {
+    if args.is_empty() {
+        return Err(RMonkeyError::Custom(
+            "wrong number of args. got=0, want=at least 1".to_string(),
+        ));
+    }
+    let mut best: Option<(f64, Object)> = None;
+    for arg in args {
+        let val = number(name, arg.clone())?;
+        match &best {
+            Some((best_val, _)) if !beats(*best_val, val) => {}
+            _ => best = Some((val, arg)),
+        }
+    }
+    Ok(best.unwrap().1)
+}
+
+/// `pow(base, exp)`. an integer to a non-negative integer power is an
+/// integer.
+fn pow(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let mut args = args.into_iter();
+    match (args.next().unwrap(), args.next().unwrap()) {
+        (Object::Int(base), Object::Int(exp)) if exp >= 0 => u32::try_from(exp)
+            .ok()
+            .and_then(|exp| base.checked_pow(exp))
+            .map(Object::Int)
+            .ok_or_else(|| overflow(format!("pow({base}, {exp})"))),
+        (base, exp) => Ok(Object::Float(
+            number("pow", base)?.powf(number("pow", exp)?),
+        )),
+    }
+}
+
+fn sqrt(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    float_fn("sqrt", args, f64::sqrt)
+}
+
+/// the largest integer not above the number
+fn floor(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    rounding("floor", args, f64::floor)
+}
+
+/// the smallest integer not below the number
+fn ceil(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    rounding("ceil", args, f64::ceil)
+}
+
+/// the nearest integer, away from zero when halfway
+fn round(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    rounding("round", args, f64::round)
+}
+
+fn sin(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    float_fn("sin", args, f64::sin)
+}
+
+fn cos(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    float_fn("cos", args, f64::cos)
+}
+
+fn tan(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    float_fn("tan", args, f64::tan)
+}
+
+fn asin(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    float_fn("asin", args, f64::asin)
+}
+
+fn acos(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    float_fn("acos", args, f64::acos)
+}
+
+fn atan(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    float_fn("atan", args, f64::atan)
+}
+
+/// `atan2(y, x)`: the angle of the point `(x, y)`, from -pi to pi
+fn atan2(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let mut args = args.into_iter();
+    let y = number("atan2", args.next().unwrap())?;
+    let x = number("atan2", args.next().unwrap())?;
+    Ok(Object::Float(y.atan2(x)))
+}
+
+/// `log(x)`, the natural logarithm, or `log(x, base)`
+fn log(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 2)?;
+    let mut args = args.into_iter();
+    let x = number("log", args.next().unwrap())?;
+    Ok(Object::Float(match args.next() {
+        Some(base) => x.log(number("log", base)?),
+        None => x.ln(),
+    }))
+}
+
+/// a function of one number that gives a float
+fn float_fn(name: &str, args: Vec<Object>, f: fn(f64) -> f64) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let x = number(name, args.into_iter().next().unwrap())?;
+    Ok(Object::Float(f(x)))
+}
+
+/// an integer as it is, and a float rounded by `f` to an integer
+fn rounding(name: &str, args: Vec<Object>, f: fn(f64) -> f64) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    match args.into_iter().next().unwrap() {
+        Object::Int(val) => Ok(Object::Int(val)),
+        obj => {
+            let val = f(number(name, obj)?);
+            // the range of i64 is not exactly representable as f64, so the
+            // upper bound is exclusive
+            if val.is_finite() && val >= i64::MIN as f64 && val < i64::MAX as f64 {
+                Ok(Object::Int(val as i64))
+            } else {
+                Err(RMonkeyError::Custom(format!(
+                    "`{name}` of {val} is not an integer"
+                )))
+            }
+        }
+    }
+}
+
+fn number(name: &str, obj: Object) -> Result<f64> {
+    match obj {
+        Object::Int(val) => Ok(val as f64),
+        Object::Float(val) => Ok(val),
+        obj => Err(RMonkeyError::Custom(format!(
+            "argument to `{name}` must be INTEGER or FLOAT, got {}",
+            obj.obj_type()
+        ))),
+    }
+}
+
+fn overflow(expr: String) -> RMonkeyError {
+    RMonkeyError::EvalError(EvalErrorKind::Overflow { expr })
+}
@@ -0,0 +1,124 @@
+//! The `random` namespace. the numbers come from the [`Rng`] of the
+//! evaluator, through the [`Caller`], so a host that seeds it gets the same
+//! numbers on every run.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+use rmonkey_error::{RMonkeyError, Result};
+
+use super::{arity, array, int, BuiltinFn, Caller};
+use crate::Object;
+
+pub(super) const BUILTINS: [(&str, BuiltinFn); 4] = [
+    ("float", float),
+    ("int", int_between),
+    ("choice", choice),
+    ("shuffle", shuffle),
+];
+
+/// a small generator (splitmix64). the same seed gives the same numbers on
+/// every platform.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// seeded differently on each run where the platform has a source of
+/// randomness. `wasm32-unknown-unknown` has none, so there the seeds are
+/// the same each time the program starts and a host that wants different numbers must seed the
+/// generator itself, e.g. with `Evaluator::seed_random`.
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(RandomState::new().build_hasher().finish())
+    }
+}
+
+/// a float from 0 up to but excluding 1
+fn float(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 0, 0)?;
+    Ok(Object::Float(
+        (caller.random() >> 11) as f64 / (1u64 << 53) as f64,
+    ))
+}
+
+/// `int(start, end)`: an integer from `start` up to but excluding `end`
+fn int_between(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let mut args = args.into_iter();
+    let start = int("int", args.next().unwrap())?;
+    let end = int("int", args.next().unwrap())?;
+    if start >= end {
+        return Err(RMonkeyError::Custom(format!(
+            "`int` needs a start below the end, got {start} and {end}"
+        )));
+    }
+    let span = end.abs_diff(start);
+    Ok(Object::Int(start.wrapping_add(below(caller, span) as i64)))
+}
+
+/// a random element
+fn choice(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let mut elements = array("choice", args.into_iter().next().unwrap())?;
+    if elements.is_empty() {
+        return Err(RMonkeyError::Custom(
+            "`choice` of an empty array".to_string(),
+        ));
+    }
+    let i = below(caller, elements.len() as u64) as usize;
+    Ok(elements.swap_remove(i))
+}
+
+/// the elements in a random order
+fn shuffle(caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let mut elements = array("shuffle", args.into_iter().next().unwrap())?;
+    for i in (1..elements.len()).rev() {
+        let j = below(caller, i as u64 + 1) as usize;
+        elements.swap(i, j);
+    }
+    Ok(Object::Array { elements })
+}
+
+/// a number from 0 up to but excluding `n`, which must not be 0
+fn below(caller: &mut dyn Caller, n: u64) -> u64 {
+    // the high bits of a 128-bit product, which are close enough to uniform
+    // for any `n` far below 2^64
+    ((caller.random() as u128 * n as u128) >> 64) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn test_rng() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let first: Vec<u64> = (0..3).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..3).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(first, {
+            let mut c = Rng::new(43);
+            (0..3).map(|_| c.next_u64()).collect::<Vec<_>>()
+        });
+        // the first output of splitmix64 seeded with 0
+        assert_eq!(Rng::new(0).next_u64(), 0xe220_a839_7b1d_cdaf);
+    }
+}
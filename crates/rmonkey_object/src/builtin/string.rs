@@ -0,0 +1,174 @@
+//! Builtins over strings. indices and lengths count characters, not bytes.
+
+use rmonkey_error::{RMonkeyError, Result};
+
+use super::{arity, array, string, BuiltinFn, Caller};
+use crate::Object;
+
+pub(super) const BUILTINS: [(&str, BuiltinFn); 12] = [
+    ("split", split),
+    ("join", join),
+    ("trim", trim),
+    ("upper", upper),
+    ("lower", lower),
+    ("replace", replace),
+    ("contains", contains),
+    ("starts_with", starts_with),
+    ("ends_with", ends_with),
+    ("index_of", index_of),
+    ("chars", chars),
+    ("format", format),
+];
+
+/// the parts of the string between each `separator`. an empty separator
+/// splits it into characters.
+fn split(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let (val, separator) = two_strings("split", args)?;
+    if separator.is_empty() {
+        return Ok(chars_of(&val));
+    }
+    Ok(Object::Array {
+        elements: val
+            .split(separator.as_str())
+            .map(|part| Object::String(part.to_string()))
+            .collect(),
+    })
+}
+
+/// the elements with `separator` between them. elements that aren't strings
+/// are written the way they are displayed.
+fn join(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let mut args = args.into_iter();
+    let elements = array("join", args.next().unwrap())?;
+    let separator = string("join", args.next().unwrap())?;
+    let parts: Vec<String> = elements.iter().map(text).collect();
+    Ok(Object::String(parts.join(&separator)))
+}
+
+/// the string without leading and trailing whitespace
+fn trim(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let val = string("trim", args.into_iter().next().unwrap())?;
+    Ok(Object::String(val.trim().to_string()))
+}
+
+fn upper(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let val = string("upper", args.into_iter().next().unwrap())?;
+    Ok(Object::String(val.to_uppercase()))
+}
+
+fn lower(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let val = string("lower", args.into_iter().next().unwrap())?;
+    Ok(Object::String(val.to_lowercase()))
+}
+
+/// `replace(s, from, to)`: every `from` in `s` replaced by `to`
+fn replace(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 3, 3)?;
+    let mut args = args.into_iter();
+    let val = string("replace", args.next().unwrap())?;
+    let from = string("replace", args.next().unwrap())?;
+    let to = string("replace", args.next().unwrap())?;
+    Ok(Object::String(val.replace(&from, &to)))
+}
+
+fn contains(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let (val, part) = two_strings("contains", args)?;
+    Ok(Object::Bool(val.contains(&part)))
+}
+
+fn starts_with(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let (val, prefix) = two_strings("starts_with", args)?;
+    Ok(Object::Bool(val.starts_with(&prefix)))
+}
+
+fn ends_with(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let (val, suffix) = two_strings("ends_with", args)?;
+    Ok(Object::Bool(val.ends_with(&suffix)))
+}
+
+/// the index of the character where `part` first starts, or -1
+fn index_of(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 2, 2)?;
+    let (val, part) = two_strings("index_of", args)?;
+    Ok(Object::Int(match val.find(&part) {
+        Some(byte) => val[..byte].chars().count() as i64,
+        None => -1,
+    }))
+}
+
+/// the characters, each as a string of its own
+fn chars(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    arity(&args, 1, 1)?;
+    let val = string("chars", args.into_iter().next().unwrap())?;
+    Ok(chars_of(&val))
+}
+
+/// `format("{} is {}", a, b)`: the string with each `{}` replaced by the
+/// next argument, written like `join` writes elements. `{{` and `}}` are a
+/// literal `{` and `}`.
+fn format(_: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+    if args.is_empty() {
+        return Err(RMonkeyError::Custom(
+            "wrong number of args. got=0, want=at least 1".to_string(),
+        ));
+    }
+    let mut args = args.into_iter();
+    let template = string("format", args.next().unwrap())?;
+    let mut formatted = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('}')) => {
+                chars.next();
+                match args.next() {
+                    Some(arg) => formatted.push_str(&text(&arg)),
+                    None => {
+                        return Err(RMonkeyError::Custom(
+                            "`format` has more `{}` than arguments".to_string(),
+                        ))
+                    }
+                }
+            }
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                formatted.push(c);
+            }
+            _ => formatted.push(c),
+        }
+    }
+    if args.next().is_some() {
+        return Err(RMonkeyError::Custom(
+            "`format` has more arguments than `{}`".to_string(),
+        ));
+    }
+    Ok(Object::String(formatted))
+}
+
+fn two_strings(name: &str, args: Vec<Object>) -> Result<(String, String)> {
+    let mut args = args.into_iter();
+    let a = string(name, args.next().unwrap())?;
+    let b = string(name, args.next().unwrap())?;
+    Ok((a, b))
+}
+
+fn chars_of(val: &str) -> Object {
+    Object::Array {
+        elements: val.chars().map(|c| Object::String(c.to_string())).collect(),
+    }
+}
+
+/// a string as it is, and any other value as it is displayed
+fn text(obj: &Object) -> String {
+    match obj {
+        Object::String(val) => val.clone(),
+        obj => obj.to_string(),
+    }
+}
@@ -0,0 +1,623 @@
+//! Rust values to and from objects through serde. structs and maps become
+//! hashes in the order of their fields, sequences and tuples arrays, and
+//! enums are tagged like in JSON: a unit variant is its name as a string
+//! and any other variant a hash from its name to its contents.
+
+use std::fmt;
+
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, IntoDeserializer, Visitor,
+    },
+    ser::{self, Serialize},
+};
+
+use rmonkey_error::{RMonkeyError, Result};
+
+use crate::{IndexMap, Object};
+
+/// the object of a Rust value
+pub fn to_object<T: Serialize + ?Sized>(value: &T) -> Result<Object> {
+    value
+        .serialize(Serializer)
+        .map_err(|err| RMonkeyError::Convert(err.0))
+}
+
+/// the Rust value of an object, e.g. the result of `Evaluator::eval`
+pub fn from_object<T: DeserializeOwned>(obj: Object) -> Result<T> {
+    T::deserialize(Deserializer::new(obj)).map_err(|err| RMonkeyError::Convert(err.0))
+}
+
+/// why a value couldn't be converted
+#[derive(Debug)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+type ConvertResult<T> = std::result::Result<T, Error>;
+
+/// turns Rust values into objects, see [`to_object`]
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Object;
+    type Error = Error;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeHash;
+    type SerializeStruct = SerializeHash;
+    type SerializeStructVariant = SerializeHash;
+
+    fn serialize_bool(self, v: bool) -> ConvertResult<Object> {
+        Ok(Object::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> ConvertResult<Object> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> ConvertResult<Object> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> ConvertResult<Object> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> ConvertResult<Object> {
+        Ok(Object::Int(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> ConvertResult<Object> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> ConvertResult<Object> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> ConvertResult<Object> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> ConvertResult<Object> {
+        i64::try_from(v)
+            .map(Object::Int)
+            .map_err(|_| Error(format!("{v} is too large for an INTEGER")))
+    }
+
+    fn serialize_f32(self, v: f32) -> ConvertResult<Object> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> ConvertResult<Object> {
+        Ok(Object::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> ConvertResult<Object> {
+        Ok(Object::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> ConvertResult<Object> {
+        Ok(Object::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> ConvertResult<Object> {
+        Ok(Object::Array {
+            elements: v.iter().map(|&b| Object::Int(b.into())).collect(),
+        })
+    }
+
+    fn serialize_none(self) -> ConvertResult<Object> {
+        Ok(Object::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> ConvertResult<Object> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> ConvertResult<Object> {
+        Ok(Object::Null)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> ConvertResult<Object> {
+        Ok(Object::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> ConvertResult<Object> {
+        Ok(Object::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> ConvertResult<Object> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> ConvertResult<Object> {
+        Ok(tagged(variant, value.serialize(Serializer)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> ConvertResult<SerializeArray> {
+        Ok(SerializeArray {
+            variant: None,
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> ConvertResult<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> ConvertResult<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> ConvertResult<SerializeArray> {
+        Ok(SerializeArray {
+            variant: Some(variant),
+            elements: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> ConvertResult<SerializeHash> {
+        Ok(SerializeHash {
+            variant: None,
+            pairs: IndexMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> ConvertResult<SerializeHash> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> ConvertResult<SerializeHash> {
+        Ok(SerializeHash {
+            variant: Some(variant),
+            pairs: IndexMap::new(),
+            key: None,
+        })
+    }
+}
+
+/// a variant with contents, `{"Variant": contents}`
+fn tagged(variant: &str, contents: Object) -> Object {
+    Object::Hash(IndexMap::from([(
+        Object::String(variant.to_string()),
+        contents,
+    )]))
+}
+
+/// an array, or a variant with one, being serialized
+pub struct SerializeArray {
+    variant: Option<&'static str>,
+    elements: Vec<Object>,
+}
+
+impl SerializeArray {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> ConvertResult<()> {
+        self.elements.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> ConvertResult<Object> {
+        let array = Object::Array {
+            elements: self.elements,
+        };
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, array),
+            None => array,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> ConvertResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> ConvertResult<Object> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> ConvertResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> ConvertResult<Object> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> ConvertResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> ConvertResult<Object> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeArray {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> ConvertResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> ConvertResult<Object> {
+        self.finish()
+    }
+}
+
+/// a hash, or a variant with one, being serialized
+pub struct SerializeHash {
+    variant: Option<&'static str>,
+    pairs: IndexMap<Object, Object>,
+    /// the key of `serialize_key` waiting for its value
+    key: Option<Object>,
+}
+
+impl SerializeHash {
+    fn insert(&mut self, key: Object, value: Object) -> ConvertResult<()> {
+        key.check_hashable().map_err(|err| Error(err.to_string()))?;
+        self.pairs.insert(key, value);
+        Ok(())
+    }
+
+    fn finish(self) -> ConvertResult<Object> {
+        let hash = Object::Hash(self.pairs);
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, hash),
+            None => hash,
+        })
+    }
+}
+
+impl ser::SerializeMap for SerializeHash {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> ConvertResult<()> {
+        self.key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> ConvertResult<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("a map value without a key".to_string()))?;
+        let value = value.serialize(Serializer)?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> ConvertResult<Object> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeHash {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> ConvertResult<()> {
+        let value = value.serialize(Serializer)?;
+        self.insert(Object::String(key.to_string()), value)
+    }
+
+    fn end(self) -> ConvertResult<Object> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeHash {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> ConvertResult<()> {
+        let value = value.serialize(Serializer)?;
+        self.insert(Object::String(key.to_string()), value)
+    }
+
+    fn end(self) -> ConvertResult<Object> {
+        self.finish()
+    }
+}
+
+/// reads Rust values out of an object, see [`from_object`]
+pub struct Deserializer(Object);
+
+impl Deserializer {
+    /// a `ReturnValue`, which `Evaluator::eval` gives for a program that
+    /// ends in a `return`, is read as the value it holds
+    pub fn new(obj: Object) -> Self {
+        match obj {
+            Object::ReturnValue(val) => Deserializer(*val),
+            obj => Deserializer(obj),
+        }
+    }
+}
+
+impl IntoDeserializer<'_, Error> for Object {
+    type Deserializer = Deserializer;
+
+    fn into_deserializer(self) -> Deserializer {
+        Deserializer::new(self)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> ConvertResult<V::Value> {
+        match self.0 {
+            Object::Null => visitor.visit_unit(),
+            Object::Bool(val) => visitor.visit_bool(val),
+            Object::Int(val) => visitor.visit_i64(val),
+            Object::Float(val) => visitor.visit_f64(val),
+            Object::String(val) => visitor.visit_string(val),
+            Object::Array { elements } => {
+                let mut seq = SeqDeserializer::new(elements.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Object::Hash(pairs) => {
+                let mut map = MapDeserializer::new(pairs.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            obj => Err(Error(format!("a {} has no Rust value", obj.obj_type()))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> ConvertResult<V::Value> {
+        match self.0 {
+            Object::Null => visitor.visit_none(),
+            obj => visitor.visit_some(Deserializer(obj)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> ConvertResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> ConvertResult<V::Value> {
+        match self.0 {
+            Object::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Object::Hash(pairs) if pairs.len() == 1 => {
+                let (variant, contents) = pairs.into_iter().next().unwrap();
+                visitor.visit_enum(Enum { variant, contents })
+            }
+            obj => Err(Error(format!(
+                "expected a variant name or a hash of one variant, got {obj}"
+            ))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// `{variant: contents}`
+struct Enum {
+    variant: Object,
+    contents: Object,
+}
+
+impl<'de> de::EnumAccess<'de> for Enum {
+    type Error = Error;
+    type Variant = Deserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> ConvertResult<(V::Value, Deserializer)> {
+        let variant = seed.deserialize(Deserializer(self.variant))?;
+        Ok((variant, Deserializer(self.contents)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> ConvertResult<()> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> ConvertResult<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, visitor: V) -> ConvertResult<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> ConvertResult<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { w: i64, h: i64 },
+        Line(i64, i64),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        retries: u8,
+        ratio: f64,
+        tags: Vec<String>,
+        limit: Option<i64>,
+        shapes: Vec<Shape>,
+        scores: BTreeMap<String, i64>,
+        pair: (bool, char),
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config = Config {
+            name: "x".to_string(),
+            retries: 3,
+            ratio: 0.5,
+            tags: vec!["a".to_string()],
+            limit: None,
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(1.5),
+                Shape::Rect { w: 1, h: 2 },
+                Shape::Line(0, 1),
+            ],
+            scores: BTreeMap::from([("b".to_string(), 2)]),
+            pair: (true, 'c'),
+        };
+        let obj = to_object(&config).unwrap();
+        assert_eq!(
+            obj.to_string(),
+            r#"{"name": "x", "retries": 3, "ratio": 0.5, "tags": ["a"], "limit": null, "shapes": ["Point", {"Circle": 1.5}, {"Rect": {"w": 1, "h": 2}}, {"Line": [0, 1]}], "scores": {"b": 2}, "pair": [true, "c"]}"#
+        );
+        assert_eq!(from_object::<Config>(obj).unwrap(), config);
+
+        let returned = Object::ReturnValue(Box::new(Object::Int(1)));
+        assert_eq!(from_object::<Option<i64>>(returned).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            to_object(&u64::MAX).unwrap_err().to_string(),
+            "conversion error: 18446744073709551615 is too large for an INTEGER"
+        );
+        assert_eq!(
+            to_object(&BTreeMap::from([(None::<i64>, 1)]))
+                .unwrap_err()
+                .to_string(),
+            "conversion error: unusable as hash key: NULL"
+        );
+        assert_eq!(
+            from_object::<i64>(Object::String("1".to_string()))
+                .unwrap_err()
+                .to_string(),
+            r#"conversion error: invalid type: string "1", expected i64"#
+        );
+        assert_eq!(
+            from_object::<u8>(Object::Int(300)).unwrap_err().to_string(),
+            "conversion error: invalid value: integer `300`, expected u8"
+        );
+        assert_eq!(
+            from_object::<(i64,)>(Object::Array {
+                elements: vec![Object::Int(1), Object::Int(2)]
+            })
+            .unwrap_err()
+            .to_string(),
+            "conversion error: invalid length 2, expected 1 element in sequence"
+        );
+        assert_eq!(
+            from_object::<i64>(Object::BuiltIn {
+                func: crate::builtin::len
+            })
+            .unwrap_err()
+            .to_string(),
+            "conversion error: a BUILTIN has no Rust value"
+        );
+    }
+}
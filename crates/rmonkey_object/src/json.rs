@@ -0,0 +1,257 @@
+//! JSON to and from objects. objects become hashes that keep the order of
+//! their keys, integral numbers that fit become integers and the others
+//! floats. only those values, arrays, strings, booleans and null can be
+//! written back.
+
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use rmonkey_error::{eval_error::EvalErrorKind, RMonkeyError, Result};
+
+use crate::{IndexMap, Object};
+
+/// the object a JSON text stands for
+pub fn from_json(text: &str) -> Result<Object> {
+    match serde_json::from_str::<Json>(text) {
+        Ok(Json(obj)) => Ok(obj),
+        Err(err) => {
+            // the position is kept apart from the message
+            let position = format!(" at line {} column {}", err.line(), err.column());
+            let message = err.to_string();
+            Err(RMonkeyError::EvalError(EvalErrorKind::JsonParse {
+                message: message.trim_end_matches(&position).to_string(),
+                line: err.line(),
+                column: err.column(),
+            }))
+        }
+    }
+}
+
+/// the widest indent [`to_json`] takes
+pub const MAX_INDENT: usize = 16;
+
+/// the JSON text of an object, on one line, or over several with each
+/// level indented by `indent` spaces, at most [`MAX_INDENT`]
+pub fn to_json(obj: &Object, indent: Option<usize>) -> Result<String> {
+    if let Some(indent) = indent.filter(|indent| *indent > MAX_INDENT) {
+        return Err(RMonkeyError::Custom(format!(
+            "the indent must be from 0 to {MAX_INDENT}, got {indent}"
+        )));
+    }
+    let mut out = String::new();
+    write(&mut out, obj, indent, 0)?;
+    Ok(out)
+}
+
+fn write(out: &mut String, obj: &Object, indent: Option<usize>, depth: usize) -> Result<()> {
+    match obj {
+        Object::Null => out.push_str("null"),
+        Object::Bool(val) => out.push_str(&val.to_string()),
+        Object::Int(val) => out.push_str(&val.to_string()),
+        Object::Float(val) if !val.is_finite() => return Err(unencodable(val.to_string())),
+        Object::Float(val) => out.push_str(&serde_json::to_string(val).unwrap()),
+        Object::String(val) => out.push_str(&serde_json::to_string(val).unwrap()),
+        Object::Array { elements } => {
+            out.push('[');
+            for (i, element) in elements.iter().enumerate() {
+                separate(out, i, indent, depth + 1)?;
+                write(out, element, indent, depth + 1)?;
+            }
+            close(out, elements.is_empty(), indent, depth)?;
+            out.push(']');
+        }
+        Object::Hash(pairs) => {
+            out.push('{');
+            for (i, (key, val)) in pairs.iter().enumerate() {
+                let Object::String(key) = key else {
+                    return Err(unencodable(format!("{} hash key", key.obj_type())));
+                };
+                separate(out, i, indent, depth + 1)?;
+                out.push_str(&serde_json::to_string(key).unwrap());
+                out.push(':');
+                if indent.is_some() {
+                    out.push(' ');
+                }
+                write(out, val, indent, depth + 1)?;
+            }
+            close(out, pairs.is_empty(), indent, depth)?;
+            out.push('}');
+        }
+        obj => return Err(unencodable(obj.obj_type().to_string())),
+    }
+    Ok(())
+}
+
+/// what goes before the `i`th element of an array or pair of a hash
+fn separate(out: &mut String, i: usize, indent: Option<usize>, depth: usize) -> Result<()> {
+    if i > 0 {
+        out.push(',');
+    }
+    match indent {
+        Some(indent) => newline(out, indent, depth),
+        None => Ok(()),
+    }
+}
+
+/// what goes before the closing bracket of a non-empty array or hash
+fn close(out: &mut String, empty: bool, indent: Option<usize>, depth: usize) -> Result<()> {
+    match (indent, empty) {
+        (Some(indent), false) => newline(out, indent, depth),
+        _ => Ok(()),
+    }
+}
+
+fn newline(out: &mut String, indent: usize, depth: usize) -> Result<()> {
+    let width = indent.checked_mul(depth).ok_or_else(|| {
+        RMonkeyError::Custom(format!("indent too wide: {indent} spaces at depth {depth}"))
+    })?;
+    out.push('\n');
+    out.push_str(&" ".repeat(width));
+    Ok(())
+}
+
+fn unencodable(value: String) -> RMonkeyError {
+    RMonkeyError::EvalError(EvalErrorKind::Unencodable { value })
+}
+
+/// an object read from JSON
+struct Json(Object);
+
+impl<'de> Deserialize<'de> for Json {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonVisitor).map(Json)
+    }
+}
+
+struct JsonVisitor;
+
+impl<'de> Visitor<'de> for JsonVisitor {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<Object, E> {
+        Ok(Object::Null)
+    }
+
+    fn visit_bool<E: de::Error>(self, val: bool) -> std::result::Result<Object, E> {
+        Ok(Object::Bool(val))
+    }
+
+    fn visit_i64<E: de::Error>(self, val: i64) -> std::result::Result<Object, E> {
+        Ok(Object::Int(val))
+    }
+
+    fn visit_u64<E: de::Error>(self, val: u64) -> std::result::Result<Object, E> {
+        Ok(i64::try_from(val).map_or(Object::Float(val as f64), Object::Int))
+    }
+
+    fn visit_f64<E: de::Error>(self, val: f64) -> std::result::Result<Object, E> {
+        Ok(Object::Float(val))
+    }
+
+    fn visit_str<E: de::Error>(self, val: &str) -> std::result::Result<Object, E> {
+        Ok(Object::String(val.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, val: String) -> std::result::Result<Object, E> {
+        Ok(Object::String(val))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Object, A::Error> {
+        let mut elements = Vec::new();
+        while let Some(Json(element)) = seq.next_element()? {
+            elements.push(element);
+        }
+        Ok(Object::Array { elements })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Object, A::Error> {
+        let mut pairs = IndexMap::new();
+        while let Some((key, Json(val))) = map.next_entry::<String, Json>()? {
+            pairs.insert(Object::String(key), val);
+        }
+        Ok(Object::Hash(pairs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let case = [
+            ("null", "null"),
+            ("[true, 1, -2, 1.5, 1e2]", "[true, 1, -2, 1.5, 100.0]"),
+            ("18446744073709551615", "1.8446744073709552e19"),
+            (r#""a\"é""#, r#""a"é""#),
+            (r#"{"b": {"x": []}, "a": 1}"#, r#"{"b": {"x": []}, "a": 1}"#),
+        ];
+        for (input, expected) in case {
+            assert_eq!(from_json(input).unwrap().to_string(), expected, "{input}");
+        }
+        let case = [
+            (
+                "[1,\n 2",
+                "invalid JSON at line 2 column 2: EOF while parsing a list",
+            ),
+            (
+                "{1: 2}",
+                "invalid JSON at line 1 column 2: key must be a string",
+            ),
+            (
+                "[1] x",
+                "invalid JSON at line 1 column 5: trailing characters",
+            ),
+        ];
+        for (input, expected) in case {
+            assert_eq!(
+                from_json(input).unwrap_err().to_string(),
+                expected,
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let obj = from_json(r#"{"b": [1, 2.5, "x\n"], "a": {}, "c": [], "d": null}"#).unwrap();
+        assert_eq!(
+            to_json(&obj, None).unwrap(),
+            r#"{"b":[1,2.5,"x\n"],"a":{},"c":[],"d":null}"#
+        );
+        assert_eq!(
+            to_json(&obj, Some(2)).unwrap(),
+            "{\n  \"b\": [\n    1,\n    2.5,\n    \"x\\n\"\n  ],\n  \"a\": {},\n  \"c\": [],\n  \"d\": null\n}"
+        );
+        let case = [
+            (
+                Object::Array {
+                    elements: vec![Object::BuiltIn {
+                        func: crate::builtin::len,
+                    }],
+                },
+                "unencodable as JSON: BUILTIN",
+            ),
+            (Object::Float(f64::NAN), "unencodable as JSON: NaN"),
+            (
+                Object::Hash(IndexMap::from([(Object::Int(1), Object::Null)])),
+                "unencodable as JSON: INTEGER hash key",
+            ),
+        ];
+        for (obj, expected) in case {
+            assert_eq!(to_json(&obj, None).unwrap_err().to_string(), expected);
+        }
+        assert_eq!(
+            to_json(&obj, Some(usize::MAX)).unwrap_err().to_string(),
+            format!(
+                "custom error: the indent must be from 0 to 16, got {}",
+                usize::MAX
+            )
+        );
+    }
+}
@@ -1,57 +1,216 @@
-use rmonkey_ast::{Expr, Stmt};
-use rmonkey_error::Result;
+use builtin::BuiltinFn;
+use native::NativeFn;
+use rmonkey_ast::{pattern::Pattern, Expr, Stmt};
+use rmonkey_error::{eval_error::EvalErrorKind, RMonkeyError};
 use scope::Scope;
-use std::hash::Hash;
+use std::hash::{DefaultHasher, Hash};
+use std::rc::Rc;
 use std::{collections::HashMap, fmt, hash::Hasher};
 
+pub use convert::{from_object, to_object};
+pub use indexmap::IndexMap;
+
 pub mod builtin;
+pub mod convert;
+pub mod json;
+pub mod native;
 pub mod scope;
+mod sequence;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub enum Object {
     Int(i64),
+    /// a number with a fraction. there are no float literals; builtins like
+    /// `math["sqrt"]` make them.
+    Float(f64),
     Bool(bool),
     Null,
     String(String),
     BuiltIn {
-        func: fn(Vec<Object>) -> Result<Object>,
+        func: BuiltinFn,
+    },
+    /// a function of the host, see [`native`]
+    Native {
+        func: NativeFn,
     },
     ReturnValue(Box<Object>),
+    /// a closure. each evaluation of a function literal makes a new one,
+    /// which only its copies are equal to.
     Func {
-        params: Vec<Expr>,
-        body: Stmt,
+        params: Vec<Pattern>,
+        body: Rc<Stmt>,
         scope: Scope,
     },
     Array {
         elements: Vec<Object>,
     },
-    Hash(HashMap<Object, Object>),
+    /// pairs in the order their keys were first inserted
+    Hash(IndexMap<Object, Object>),
+    /// an imported module. only its exports are visible.
+    Module {
+        path: String,
+        exports: HashMap<String, Object>,
+    },
+    /// a runtime error caught by a `catch`, or an error value made by
+    /// `error`
+    Error {
+        kind: String,
+        message: String,
+        /// names of the functions that were being called when the error
+        /// was raised, innermost first
+        stack: Vec<String>,
+        /// any value given to `error` along with the message
+        payload: Option<Box<Object>>,
+    },
+    /// code made by `quote`, with its `unquote`s already evaluated
+    Quote(Box<Expr>),
+    /// a macro defined by a top-level `let`, which only exists while the
+    /// program is expanded
+    Macro {
+        params: Vec<String>,
+        body: Box<Stmt>,
+        scope: Scope,
+    },
+}
+
+/// equality is structural, except for functions, which are only equal to
+/// themselves. hashes are equal when they have the same pairs in any order.
+/// floats compare like `f64`, so NaN is not equal to itself, which is why
+/// they can't be hash keys.
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Int(a), Object::Int(b)) => a == b,
+            (Object::Float(a), Object::Float(b)) => a == b,
+            (Object::Bool(a), Object::Bool(b)) => a == b,
+            (Object::Null, Object::Null) => true,
+            (Object::String(a), Object::String(b)) => a == b,
+            (Object::BuiltIn { func: a }, Object::BuiltIn { func: b }) => {
+                std::ptr::fn_addr_eq(*a, *b)
+            }
+            (Object::Native { func: a }, Object::Native { func: b }) => a.ptr_eq(b),
+            (Object::ReturnValue(a), Object::ReturnValue(b)) => a == b,
+            (Object::Func { body: a, .. }, Object::Func { body: b, .. }) => Rc::ptr_eq(a, b),
+            (Object::Array { elements: a }, Object::Array { elements: b }) => a == b,
+            (Object::Hash(a), Object::Hash(b)) => a == b,
+            (
+                Object::Module {
+                    path: a,
+                    exports: a_exports,
+                },
+                Object::Module {
+                    path: b,
+                    exports: b_exports,
+                },
+            ) => a == b && a_exports == b_exports,
+            (
+                Object::Error {
+                    kind: a_kind,
+                    message: a_message,
+                    stack: a_stack,
+                    payload: a_payload,
+                },
+                Object::Error {
+                    kind: b_kind,
+                    message: b_message,
+                    stack: b_stack,
+                    payload: b_payload,
+                },
+            ) => {
+                a_kind == b_kind
+                    && a_message == b_message
+                    && a_stack == b_stack
+                    && a_payload == b_payload
+            }
+            (Object::Quote(a), Object::Quote(b)) => a == b,
+            // the scope of a macro holds the macro itself
+            (
+                Object::Macro {
+                    params: a_params,
+                    body: a_body,
+                    ..
+                },
+                Object::Macro {
+                    params: b_params,
+                    body: b_body,
+                    ..
+                },
+            ) => a_params == b_params && a_body == b_body,
+            _ => false,
+        }
+    }
 }
 
-#[allow(clippy::derived_hash_with_manual_eq)]
+impl Eq for Object {}
+
+/// consistent with `==` for the values that can be hash keys, see
+/// [`Object::check_hashable`]. the others are never hashed.
 impl Hash for Object {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        match *self {
-            Object::Int(ref i) => i.hash(state),
-            Object::Bool(ref b) => b.hash(state),
-            Object::String(ref s) => s.hash(state),
-            _ => "".hash(state),
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Object::Int(val) => val.hash(state),
+            Object::Bool(val) => val.hash(state),
+            Object::String(val) => val.hash(state),
+            Object::Array { elements } => elements.hash(state),
+            Object::Hash(pairs) => {
+                // the same whatever the order of the pairs
+                let mut sum: u64 = 0;
+                for pair in pairs.iter() {
+                    let mut hasher = DefaultHasher::new();
+                    pair.hash(&mut hasher);
+                    sum = sum.wrapping_add(hasher.finish());
+                }
+                sum.hash(state);
+            }
+            Object::Func { body, .. } => Rc::as_ptr(body).hash(state),
+            Object::BuiltIn { func } => (*func as usize).hash(state),
+            Object::Native { func } => func.addr().hash(state),
+            _ => {}
         }
     }
 }
 
 impl Object {
+    /// whether the value counts as true in a condition
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Object::Bool(false) | Object::Null)
+    }
+
+    /// fail unless the value can be a hash key: an integer, a boolean, a
+    /// string or a function, or an array or hash of those
+    pub fn check_hashable(&self) -> Result<(), RMonkeyError> {
+        match self {
+            Object::Int(_)
+            | Object::Bool(_)
+            | Object::String(_)
+            | Object::Func { .. }
+            | Object::BuiltIn { .. }
+            | Object::Native { .. } => Ok(()),
+            Object::Array { elements } => elements.iter().try_for_each(Object::check_hashable),
+            Object::Hash(pairs) => pairs.values().try_for_each(Object::check_hashable),
+            _ => Err(RMonkeyError::EvalError(EvalErrorKind::Unhashable {
+                ty: self.obj_type().to_string(),
+            })),
+        }
+    }
+
     pub fn obj_type(&self) -> &str {
         match self {
             Object::Int(_) => "INTEGER",
+            Object::Float(_) => "FLOAT",
             Object::Bool(_) => "BOOLEAN",
             Object::Null => "NULL",
             Object::ReturnValue(_) => "RETURN_VALUE",
             Object::Func { .. } => "FUNCTION",
             Object::String(_) => "STRING",
             Object::Array { .. } => "ARRAY",
-            Object::BuiltIn { .. } => "BUILTIN",
+            Object::BuiltIn { .. } | Object::Native { .. } => "BUILTIN",
             Object::Hash(_) => "HASH",
+            Object::Module { .. } => "MODULE",
+            Object::Error { .. } => "ERROR",
+            Object::Quote(_) => "QUOTE",
+            Object::Macro { .. } => "MACRO",
         }
     }
 }
@@ -60,6 +219,8 @@ impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Object::Int(val) => write!(f, "{val}"),
+            // always with a fraction, so it can't be mistaken for an integer
+            Object::Float(val) => write!(f, "{val:?}"),
             Object::Bool(val) => write!(f, "{val}"),
             Object::Null => write!(f, "null"),
             Object::String(val) => write!(f, "\"{val}\""),
@@ -77,7 +238,7 @@ impl fmt::Display for Object {
                     )
                 }
             }
-            Object::BuiltIn { .. } => write!(f, "[builtin func]"),
+            Object::BuiltIn { .. } | Object::Native { .. } => write!(f, "[builtin func]"),
             Object::Array { elements } => {
                 if elements.is_empty() {
                     write!(f, "[]")
@@ -93,6 +254,12 @@ impl fmt::Display for Object {
                 }
                 write!(f, "{{{}}}", s.join(", "))
             }
+            Object::Module { path, .. } => write!(f, "<module {path}>"),
+            Object::Error { kind, message, .. } => write!(f, "{kind}: {message}"),
+            Object::Quote(expr) => write!(f, "QUOTE({expr})"),
+            Object::Macro { params, body, .. } => {
+                write!(f, "macro({}){{{body}}}", params.join(", "))
+            }
         }
     }
 }
@@ -0,0 +1,393 @@
+//! Functions of the host. unlike builtins, which are plain function
+//! pointers, they are closures that may capture the host's state, and they
+//! take and return Rust values that are converted from and to objects.
+
+use std::{fmt, rc::Rc};
+
+use rmonkey_error::{RMonkeyError, Result};
+
+use crate::{builtin::Caller, Object};
+
+type Closure = dyn Fn(&mut dyn Caller, Vec<Object>) -> Result<Object>;
+
+/// a closure called like a builtin
+#[derive(Clone)]
+pub struct NativeFn(Rc<Closure>);
+
+impl NativeFn {
+    pub fn new(func: impl Fn(&mut dyn Caller, Vec<Object>) -> Result<Object> + 'static) -> Self {
+        NativeFn(Rc::new(func))
+    }
+
+    pub fn call(&self, caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object> {
+        (self.0)(caller, args)
+    }
+
+    /// whether both are the same closure
+    pub fn ptr_eq(&self, other: &NativeFn) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub(crate) fn addr(&self) -> usize {
+        Rc::as_ptr(&self.0) as *const () as usize
+    }
+}
+
+impl fmt::Debug for NativeFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFn({:#x})", self.addr())
+    }
+}
+
+/// a Rust value an argument of a native function is converted to
+pub trait FromObject: Sized {
+    /// the type in the message of a failed conversion, like `INTEGER`
+    fn type_name() -> String;
+
+    /// the value, or what the object was instead, like `STRING`
+    fn from_object(obj: Object) -> std::result::Result<Self, String>;
+}
+
+impl FromObject for Object {
+    fn type_name() -> String {
+        "any value".to_string()
+    }
+
+    fn from_object(obj: Object) -> std::result::Result<Self, String> {
+        Ok(obj)
+    }
+}
+
+impl FromObject for i64 {
+    fn type_name() -> String {
+        "INTEGER".to_string()
+    }
+
+    fn from_object(obj: Object) -> std::result::Result<Self, String> {
+        match obj {
+            Object::Int(val) => Ok(val),
+            obj => Err(obj.obj_type().to_string()),
+        }
+    }
+}
+
+/// an integer or a float
+impl FromObject for f64 {
+    fn type_name() -> String {
+        "INTEGER or FLOAT".to_string()
+    }
+
+    fn from_object(obj: Object) -> std::result::Result<Self, String> {
+        match obj {
+            Object::Int(val) => Ok(val as f64),
+            Object::Float(val) => Ok(val),
+            obj => Err(obj.obj_type().to_string()),
+        }
+    }
+}
+
+impl FromObject for bool {
+    fn type_name() -> String {
+        "BOOLEAN".to_string()
+    }
+
+    fn from_object(obj: Object) -> std::result::Result<Self, String> {
+        match obj {
+            Object::Bool(val) => Ok(val),
+            obj => Err(obj.obj_type().to_string()),
+        }
+    }
+}
+
+impl FromObject for String {
+    fn type_name() -> String {
+        "STRING".to_string()
+    }
+
+    fn from_object(obj: Object) -> std::result::Result<Self, String> {
+        match obj {
+            Object::String(val) => Ok(val),
+            obj => Err(obj.obj_type().to_string()),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn type_name() -> String {
+        format!("ARRAY of {}", T::type_name())
+    }
+
+    fn from_object(obj: Object) -> std::result::Result<Self, String> {
+        match obj {
+            Object::Array { elements } => elements
+                .into_iter()
+                .map(T::from_object)
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|got| format!("ARRAY containing {got}")),
+            obj => Err(obj.obj_type().to_string()),
+        }
+    }
+}
+
+/// `None` for null
+impl<T: FromObject> FromObject for Option<T> {
+    fn type_name() -> String {
+        format!("{} or NULL", T::type_name())
+    }
+
+    fn from_object(obj: Object) -> std::result::Result<Self, String> {
+        match obj {
+            Object::Null => Ok(None),
+            obj => T::from_object(obj).map(Some),
+        }
+    }
+}
+
+/// a Rust value a native function returns
+pub trait IntoObject {
+    fn into_object(self) -> Result<Object>;
+}
+
+impl IntoObject for Object {
+    fn into_object(self) -> Result<Object> {
+        Ok(self)
+    }
+}
+
+impl IntoObject for () {
+    fn into_object(self) -> Result<Object> {
+        Ok(Object::Null)
+    }
+}
+
+impl IntoObject for i64 {
+    fn into_object(self) -> Result<Object> {
+        Ok(Object::Int(self))
+    }
+}
+
+impl IntoObject for f64 {
+    fn into_object(self) -> Result<Object> {
+        Ok(Object::Float(self))
+    }
+}
+
+impl IntoObject for bool {
+    fn into_object(self) -> Result<Object> {
+        Ok(Object::Bool(self))
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self) -> Result<Object> {
+        Ok(Object::String(self))
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self) -> Result<Object> {
+        Ok(Object::String(self.to_string()))
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self) -> Result<Object> {
+        let elements = self
+            .into_iter()
+            .map(T::into_object)
+            .collect::<Result<_>>()?;
+        Ok(Object::Array { elements })
+    }
+}
+
+/// null for `None`
+impl<T: IntoObject> IntoObject for Option<T> {
+    fn into_object(self) -> Result<Object> {
+        match self {
+            Some(val) => val.into_object(),
+            None => Ok(Object::Null),
+        }
+    }
+}
+
+/// an error raised in the program, which it can `catch`
+impl<T: IntoObject> IntoObject for std::result::Result<T, String> {
+    fn into_object(self) -> Result<Object> {
+        self.map_err(RMonkeyError::Custom)?.into_object()
+    }
+}
+
+impl<T: IntoObject> IntoObject for Result<T> {
+    fn into_object(self) -> Result<Object> {
+        self?.into_object()
+    }
+}
+
+/// a closure that can be a native function, with `Args` the tuple of its
+/// argument types
+pub trait IntoNative<Args> {
+    /// the native function, which checks its arguments the way builtins do
+    /// and calls them `name` in its errors
+    fn into_native(self, name: &str) -> NativeFn;
+}
+
+macro_rules! impl_into_native {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> IntoNative<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + 'static,
+            R: IntoObject,
+            $($arg: FromObject,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn into_native(self, name: &str) -> NativeFn {
+                let name = name.to_string();
+                NativeFn::new(move |_, args| {
+                    let want = <[&str]>::len(&[$(stringify!($arg)),*]);
+                    if args.len() != want {
+                        return Err(RMonkeyError::Custom(format!(
+                            "wrong number of args. got={}, want={want}",
+                            args.len()
+                        )));
+                    }
+                    let mut args = args.into_iter();
+                    $(let $arg = convert::<$arg>(&name, args.next().unwrap())?;)*
+                    self($($arg),*).into_object()
+                })
+            }
+        }
+    };
+}
+
+impl_into_native!();
+impl_into_native!(A);
+impl_into_native!(A, B);
+impl_into_native!(A, B, C);
+impl_into_native!(A, B, C, D);
+impl_into_native!(A, B, C, D, E);
+
+fn convert<T: FromObject>(name: &str, obj: Object) -> Result<T> {
+    T::from_object(obj).map_err(|got| {
+        RMonkeyError::Custom(format!(
+            "argument to `{name}` must be {}, got {got}",
+            T::type_name()
+        ))
+    })
+}
+
+/// native functions grouped under one name, which the program uses like an
+/// imported module: `name["function"](...)`
+#[derive(Debug)]
+pub struct Namespace {
+    name: &'static str,
+    exports: Vec<(String, Object)>,
+}
+
+impl Namespace {
+    pub fn new(name: &'static str) -> Self {
+        Namespace {
+            name,
+            exports: Vec::new(),
+        }
+    }
+
+    pub fn register_fn<Args>(&mut self, name: &str, func: impl IntoNative<Args>) -> &mut Self {
+        let func = func.into_native(name);
+        self.exports
+            .push((name.to_string(), Object::Native { func }));
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn into_object(self) -> Object {
+        Object::Module {
+            path: self.name.to_string(),
+            exports: self.exports.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoCaller;
+
+    impl Caller for NoCaller {
+        fn call(&mut self, _: Object, _: Vec<Object>) -> Result<Object> {
+            unreachable!()
+        }
+
+        fn random(&mut self) -> u64 {
+            unreachable!()
+        }
+    }
+
+    fn call<Args>(func: impl IntoNative<Args>, args: Vec<Object>) -> String {
+        match func.into_native("f").call(&mut NoCaller, args) {
+            Ok(obj) => obj.to_string(),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    fn strings(vals: &[&str]) -> Object {
+        Object::Array {
+            elements: vals.iter().map(|v| Object::String(v.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn test_conversion() {
+        let add = |a: i64, b: i64| a + b;
+        assert_eq!(call(add, vec![Object::Int(1), Object::Int(2)]), "3");
+        assert_eq!(
+            call(add, vec![Object::Int(1)]),
+            "custom error: wrong number of args. got=1, want=2"
+        );
+        assert_eq!(
+            call(add, vec![Object::Int(1), Object::Bool(true)]),
+            "custom error: argument to `f` must be INTEGER, got BOOLEAN"
+        );
+
+        let join = |parts: Vec<String>, upper: bool| {
+            let joined = parts.join("-");
+            if upper {
+                joined.to_uppercase()
+            } else {
+                joined
+            }
+        };
+        assert_eq!(
+            call(join, vec![strings(&["a", "b"]), Object::Bool(true)]),
+            r#""A-B""#
+        );
+        assert_eq!(
+            call(
+                join,
+                vec![
+                    Object::Array {
+                        elements: vec![Object::Int(1)]
+                    },
+                    Object::Bool(true)
+                ]
+            ),
+            "custom error: argument to `f` must be ARRAY of STRING, got ARRAY containing INTEGER"
+        );
+
+        let half = |x: f64, fallback: Option<i64>| -> std::result::Result<f64, String> {
+            match fallback {
+                Some(_) if x == 0.0 => Err("nothing to halve".to_string()),
+                _ => Ok(x / 2.0),
+            }
+        };
+        assert_eq!(call(half, vec![Object::Int(3), Object::Null]), "1.5");
+        assert_eq!(
+            call(half, vec![Object::Int(0), Object::Int(1)]),
+            "custom error: nothing to halve"
+        );
+        assert_eq!(call(|| (), vec![]), "null");
+    }
+}
@@ -34,7 +34,7 @@ impl Scope {
             Some(val) => Some(val.clone()),
             None => {
                 if let Some(parent) = &self.parent {
-                    return parent.borrow().store.get(&key).cloned();
+                    return parent.borrow().get(key);
                 }
                 None
             }
@@ -0,0 +1,156 @@
+//! Indexing and slicing of arrays and strings. strings are sequences of
+//! characters, and negative indices count from the end, as in Python.
+
+use crate::Object;
+
+impl Object {
+    /// the number of elements of an array or characters of a string
+    pub fn length(&self) -> Option<usize> {
+        match self {
+            Object::Array { elements } => Some(elements.len()),
+            Object::String(val) => Some(val.chars().count()),
+            _ => None,
+        }
+    }
+
+    /// the element of an array, or the character of a string as a string,
+    /// at `index`. `None` past either end and for other values.
+    pub fn get_index(&self, index: i64) -> Option<Object> {
+        let index = resolve(index, self.length()?)?;
+        match self {
+            Object::Array { elements } => elements.get(index).cloned(),
+            Object::String(val) => val
+                .chars()
+                .nth(index)
+                .map(|c| Object::String(c.to_string())),
+            _ => None,
+        }
+    }
+
+    /// `self[start:end:step]` of an array or a string. the bounds left out
+    /// default to the whole sequence in the direction of `step`, and the
+    /// others are clamped to it. `None` for other values.
+    ///
+    /// `step` must not be 0.
+    pub fn slice(&self, start: Option<i64>, end: Option<i64>, step: i64) -> Option<Object> {
+        let indices = indices(self.length()?, start, end, step);
+        match self {
+            Object::Array { elements } => Some(Object::Array {
+                elements: indices.map(|i| elements[i].clone()).collect(),
+            }),
+            Object::String(val) => {
+                let chars: Vec<char> = val.chars().collect();
+                Some(Object::String(indices.map(|i| chars[i]).collect()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// `index`, counted from the end when negative, if it is within `len`
+fn resolve(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 {
+        index.checked_add(len as i64)?
+    } else {
+        index
+    };
+    usize::try_from(index).ok().filter(|&index| index < len)
+}
+
+/// the indices `[start:end:step]` takes from a sequence of `len` elements
+fn indices(
+    len: usize,
+    start: Option<i64>,
+    end: Option<i64>,
+    step: i64,
+) -> impl Iterator<Item = usize> {
+    assert_ne!(step, 0, "slice step cannot be zero");
+    let len = len as i64;
+    // a negative step walks from the last element down to the first, so
+    // its bounds may go one past the start
+    let (lower, upper) = if step > 0 { (0, len) } else { (-1, len - 1) };
+    let clamp = |bound: i64| {
+        if bound < 0 {
+            bound.saturating_add(len).max(lower)
+        } else {
+            bound.min(upper)
+        }
+    };
+    let (default_start, default_end) = if step > 0 {
+        (lower, upper)
+    } else {
+        (upper, lower)
+    };
+    let start = start.map_or(default_start, clamp);
+    let end = end.map_or(default_end, clamp);
+    let mut i = start;
+    std::iter::from_fn(move || {
+        if (step > 0 && i < end) || (step < 0 && i > end) {
+            let index = i as usize;
+            i = i.saturating_add(step);
+            Some(index)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn array(elements: &[i64]) -> Object {
+        Object::Array {
+            elements: elements.iter().map(|&e| Object::Int(e)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_get_index() {
+        let a = array(&[1, 2, 3]);
+        let case = [
+            (0, Some(Object::Int(1))),
+            (2, Some(Object::Int(3))),
+            (3, None),
+            (-1, Some(Object::Int(3))),
+            (-3, Some(Object::Int(1))),
+            (-4, None),
+            (i64::MIN, None),
+        ];
+        for (index, expected) in case {
+            assert_eq!(a.get_index(index), expected, "{index}");
+        }
+        let s = Object::String("héllo".to_string());
+        assert_eq!(s.get_index(1), Some(Object::String("é".to_string())));
+        assert_eq!(Object::Int(1).get_index(0), None);
+    }
+
+    #[test]
+    fn test_slice() {
+        let a = array(&[0, 1, 2, 3, 4]);
+        let case = [
+            ((Some(1), Some(3), 1), vec![1, 2]),
+            ((None, Some(2), 1), vec![0, 1]),
+            ((Some(-2), None, 1), vec![3, 4]),
+            ((None, None, 2), vec![0, 2, 4]),
+            ((None, None, -1), vec![4, 3, 2, 1, 0]),
+            ((Some(3), Some(0), -2), vec![3, 1]),
+            ((Some(-10), Some(10), 1), vec![0, 1, 2, 3, 4]),
+            ((Some(3), Some(1), 1), vec![]),
+            ((Some(10), None, -1), vec![4, 3, 2, 1, 0]),
+            ((Some(i64::MIN), Some(i64::MAX), i64::MAX), vec![0]),
+        ];
+        for ((start, end, step), expected) in case {
+            assert_eq!(
+                a.slice(start, end, step),
+                Some(array(&expected)),
+                "[{start:?}:{end:?}:{step}]"
+            );
+        }
+        let s = Object::String("héllo".to_string());
+        assert_eq!(
+            s.slice(None, None, -1),
+            Some(Object::String("olléh".to_string()))
+        );
+    }
+}
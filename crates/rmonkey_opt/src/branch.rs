@@ -0,0 +1,117 @@
+//! Dead branch elimination.
+//!
+//! An `if` whose condition is a literal is replaced by the branch it always
+//! takes. A statement `if` is replaced by the statements of that branch,
+//! since blocks share the scope they are in. An expression `if` is replaced
+//! when the branch is a single expression.
+
+use rmonkey_ast::{Expr, Program, Stmt};
+
+use crate::{
+    walk::{rewrite_stmts, truthiness, Rewrite},
+    Pass,
+};
+
+#[derive(Debug, Default)]
+pub struct DeadBranch;
+
+impl Pass for DeadBranch {
+    fn name(&self) -> &'static str {
+        "dead_branch"
+    }
+
+    fn run(&mut self, program: &mut Program) -> bool {
+        rewrite_stmts(self, &mut program.stmts)
+    }
+}
+
+/// the statements of the branch an `if` with a literal condition takes
+fn taken(expr: &Expr) -> Option<&[Stmt]> {
+    let Expr::If {
+        condition,
+        consequence,
+        alternative,
+    } = expr
+    else {
+        return None;
+    };
+    let branch = match truthiness(condition)? {
+        true => Some(consequence.as_ref()),
+        false => alternative.as_deref(),
+    };
+    match branch {
+        Some(Stmt::BlockStmt { stmts }) => Some(stmts),
+        Some(_) => None,
+        None => Some(&[]),
+    }
+}
+
+impl Rewrite for DeadBranch {
+    fn expr(&mut self, expr: &mut Expr) -> bool {
+        match taken(expr) {
+            Some([Stmt::ExprStmt(value)]) => {
+                *expr = value.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn stmts(&mut self, stmts: &mut Vec<Stmt>) -> bool {
+        let mut changed = false;
+        let mut i = 0;
+        while i < stmts.len() {
+            let branch = match &stmts[i] {
+                Stmt::ExprStmt(expr) => taken(expr),
+                _ => None,
+            };
+            // an empty branch evaluates to null, which only matters when it
+            // is the value of the block
+            match branch {
+                Some(branch) if !branch.is_empty() || i + 1 < stmts.len() => {
+                    let branch = branch.to_vec();
+                    let len = branch.len();
+                    stmts.splice(i..=i, branch);
+                    i += len;
+                    changed = true;
+                }
+                _ => i += 1,
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::assert_optimized;
+
+    #[test]
+    fn test_dead_branch() {
+        let tests = [
+            ("if (true) { 1 } else { 2 };", "1;"),
+            ("if (false) { 1 } else { 2 };", "2;"),
+            ("let a = if (1) { \"a\" };", "let a = \"a\";"),
+            (
+                "let f = fn() { if (true) { let x = 1; x + 1 } }; f();",
+                "let f = fn() { let x = 1; x + 1; };\nf();",
+            ),
+            (
+                "let f = fn() { if (true) { return 1; } 2 }; f();",
+                "let f = fn() { return 1; 2; };\nf();",
+            ),
+            ("if (false) { 1 }; 2;", "2;"),
+            // the value of the program is null
+            ("1; if (false) { 1 };", "1;\nif (false) { 1; };"),
+            ("let x = if (false) { 1 };", "let x = if (false) { 1; };"),
+            (
+                "let a = true; if (a) { 1 };",
+                "let a = true;\nif (a) { 1; };",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_optimized(&mut DeadBranch, input, expected);
+        }
+    }
+}
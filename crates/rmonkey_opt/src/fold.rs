@@ -0,0 +1,137 @@
+//! Constant folding.
+//!
+//! Operators applied to literals are replaced by their result, e.g.
+//! `2 * (5 + 10)` by `30`. Operations that fail at run time, like `1 / 0`,
+//! `1 + true` or an overflow, are left for the evaluator to report.
+
+use rmonkey_ast::{
+    operator::{Infix, Prefix},
+    Expr, Program,
+};
+
+use crate::{
+    walk::{int_value, rewrite_stmts, Rewrite},
+    Pass,
+};
+
+#[derive(Debug, Default)]
+pub struct ConstantFold;
+
+impl Pass for ConstantFold {
+    fn name(&self) -> &'static str {
+        "constant_fold"
+    }
+
+    fn run(&mut self, program: &mut Program) -> bool {
+        rewrite_stmts(self, &mut program.stmts)
+    }
+}
+
+impl Rewrite for ConstantFold {
+    fn expr(&mut self, expr: &mut Expr) -> bool {
+        let folded = match expr {
+            Expr::PrefixExpr { op, right } => fold_prefix(op, right),
+            Expr::InfixExpr { left, right, op } => fold_infix(op, left, right),
+            _ => None,
+        };
+        match folded {
+            Some(folded) => {
+                *expr = folded;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// an integer constant in the shape the parser gives it, with negative
+/// values as `-n`
+fn int(val: i64) -> Option<Expr> {
+    if val >= 0 {
+        return Some(Expr::IntLiteral(val));
+    }
+    Some(Expr::PrefixExpr {
+        op: Prefix::Minus,
+        right: Box::new(Expr::IntLiteral(val.checked_neg()?)),
+    })
+}
+
+fn fold_prefix(op: &Prefix, right: &Expr) -> Option<Expr> {
+    match op {
+        // `-n` is already folded
+        Prefix::Minus => match right {
+            Expr::PrefixExpr { .. } => int(int_value(right)?.checked_neg()?),
+            _ => None,
+        },
+        Prefix::Bang => match right {
+            Expr::BoolLiteral(val) => Some(Expr::BoolLiteral(!val)),
+            Expr::StringLiteral(_) => Some(Expr::BoolLiteral(false)),
+            _ => int_value(right).map(|_| Expr::BoolLiteral(false)),
+        },
+    }
+}
+
+fn fold_infix(op: &Infix, left: &Expr, right: &Expr) -> Option<Expr> {
+    if let (Some(l), Some(r)) = (int_value(left), int_value(right)) {
+        return match op {
+            Infix::Plus => int(l.checked_add(r)?),
+            Infix::Minus => int(l.checked_sub(r)?),
+            Infix::Asterisk => int(l.checked_mul(r)?),
+            Infix::Slash => int(l.checked_div(r)?),
+            Infix::Lt => Some(Expr::BoolLiteral(l < r)),
+            Infix::Gt => Some(Expr::BoolLiteral(l > r)),
+            Infix::Eq => Some(Expr::BoolLiteral(l == r)),
+            Infix::NotEq => Some(Expr::BoolLiteral(l != r)),
+        };
+    }
+    match (left, right, op) {
+        (Expr::BoolLiteral(l), Expr::BoolLiteral(r), Infix::Eq) => Some(Expr::BoolLiteral(l == r)),
+        (Expr::BoolLiteral(l), Expr::BoolLiteral(r), Infix::NotEq) => {
+            Some(Expr::BoolLiteral(l != r))
+        }
+        (Expr::StringLiteral(l), Expr::StringLiteral(r), Infix::Plus) => {
+            Some(Expr::StringLiteral(format!("{l}{r}")))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::assert_optimized;
+
+    #[test]
+    fn test_constant_fold() {
+        let tests = [
+            ("2 * (5 + 10);", "30;"),
+            ("(5 + 10 * 2 + 15 / 3) * 2 + -10;", "50;"),
+            ("-(3 - 5);", "2;"),
+            ("--5;", "5;"),
+            ("1 < 2 == true;", "true;"),
+            ("!!5;", "true;"),
+            ("!\"a\";", "false;"),
+            ("true != false;", "true;"),
+            (r#""foo" + "bar" + "baz";"#, r#""foobarbaz";"#),
+            (
+                "let f = fn(x) { x * (60 * 60) }; f(2);",
+                "let f = fn(x) { x * 3600; };\nf(2);",
+            ),
+            // left for the evaluator to report
+            ("1 + true;", "1 + true;"),
+            ("\"a\" - \"b\";", "\"a\" - \"b\";"),
+        ];
+        for (input, expected) in tests {
+            assert_optimized(&mut ConstantFold, input, expected);
+        }
+
+        // the evaluator panics on these, so they are not evaluated
+        let (max, one, zero) = (
+            Expr::IntLiteral(i64::MAX),
+            Expr::IntLiteral(1),
+            Expr::IntLiteral(0),
+        );
+        assert_eq!(fold_infix(&Infix::Plus, &max, &one), None);
+        assert_eq!(fold_infix(&Infix::Slash, &one, &zero), None);
+    }
+}
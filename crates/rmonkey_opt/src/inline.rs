@@ -0,0 +1,260 @@
+//! Inlining of small functions.
+//!
+//! A call to a function bound by a top-level `let` is replaced by the
+//! function's body with the arguments substituted for the parameters, e.g.
+//! `let add = fn(a, b) { a + b }; add(1, 2)` becomes `... 1 + 2`. Only calls
+//! that are sure to give the same result are inlined:
+//!
+//! - the name is bound once in the whole program and the call comes after
+//!   the `let` in source order
+//! - the body is a single small expression that refers only to the
+//!   parameters and to builtins, and holds no `let`, `return`, `throw`,
+//!   `try`, `match`, `?` or function
+//! - every argument is a literal or an identifier, so evaluating it once per
+//!   use is the same as evaluating it once. an identifier is only passed to
+//!   a parameter that is used, so that an undefined name still fails.
+//!
+//! The stack trace of an error caught from an inlined body no longer has the
+//! frame of the call.
+
+use std::collections::{HashMap, HashSet};
+
+use rmonkey_ast::{pattern::Pattern, Expr, Program, Stmt};
+use rmonkey_object::builtin::builtins;
+
+use crate::{
+    walk::{int_value, rewrite_expr, rewrite_stmt, Names, Rewrite},
+    Pass,
+};
+
+/// the most expressions a body may have to be inlined
+const MAX_SIZE: usize = 16;
+
+#[derive(Debug, Default)]
+pub struct Inline;
+
+impl Pass for Inline {
+    fn name(&self) -> &'static str {
+        "inline"
+    }
+
+    fn run(&mut self, program: &mut Program) -> bool {
+        let names = Names::of(&program.stmts);
+        let mut inliner = Inliner {
+            functions: HashMap::new(),
+        };
+        let mut changed = false;
+        for stmt in program.stmts.iter_mut() {
+            changed |= rewrite_stmt(&mut inliner, stmt);
+            if let Some((name, function)) = inlinable(stmt, &names) {
+                inliner.functions.insert(name, function);
+            }
+        }
+        changed
+    }
+}
+
+struct Function {
+    params: Vec<String>,
+    body: Expr,
+    /// the parameters used in the body
+    used: HashSet<String>,
+}
+
+/// the function bound by `stmt`, if calls to it can be inlined
+fn inlinable(stmt: &Stmt, names: &Names) -> Option<(String, Function)> {
+    let Stmt::LetStmt {
+        name: Pattern::Ident(name),
+        value: Expr::Func { params, body, .. },
+        ..
+    } = stmt
+    else {
+        return None;
+    };
+    if names.bound(name) != 1 {
+        return None;
+    }
+    let params: Vec<String> = params
+        .iter()
+        .map(|param| param.as_ident().map(str::to_string))
+        .collect::<Option<_>>()?;
+    let body = match body.as_ref() {
+        Stmt::BlockStmt { stmts } => match stmts.as_slice() {
+            [Stmt::ExprStmt(body) | Stmt::ReturnStmt(body)] => body,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let mut body = body.clone();
+    let mut scan = Scan::default();
+    rewrite_expr(&mut scan, &mut body);
+    if scan.complex || scan.size > MAX_SIZE {
+        return None;
+    }
+    let builtins = builtins();
+    let mut used = HashSet::new();
+    for ident in scan.idents {
+        if params.contains(&ident) {
+            used.insert(ident);
+        } else if !builtins.contains_key(ident.as_str()) || names.bound(&ident) > 0 {
+            return None;
+        }
+    }
+    Some((name.clone(), Function { params, body, used }))
+}
+
+/// size, identifiers and shape of a function body
+#[derive(Default)]
+struct Scan {
+    size: usize,
+    idents: Vec<String>,
+    /// whether the body holds a `let`, a `return`, a `throw`, a `try` or a
+    /// `match`, which bind names of their own, a `?`, which would return
+    /// from the caller, or a function
+    complex: bool,
+}
+
+impl Rewrite for Scan {
+    fn expr(&mut self, expr: &mut Expr) -> bool {
+        self.size += 1;
+        match expr {
+            Expr::Ident(name) => self.idents.push(name.clone()),
+            Expr::Func { .. }
+            | Expr::Macro { .. }
+            | Expr::Try { .. }
+            | Expr::Match { .. }
+            | Expr::Propagate { .. } => self.complex = true,
+            _ => {}
+        }
+        false
+    }
+
+    fn stmts(&mut self, stmts: &mut Vec<Stmt>) -> bool {
+        if stmts.iter().any(|stmt| !matches!(stmt, Stmt::ExprStmt(_))) {
+            self.complex = true;
+        }
+        false
+    }
+}
+
+struct Inliner {
+    functions: HashMap<String, Function>,
+}
+
+impl Inliner {
+    fn inline(&self, callee: &Expr, args: &[Expr]) -> Option<Expr> {
+        let Expr::Ident(callee) = callee else {
+            return None;
+        };
+        let function = self.functions.get(callee)?;
+        if args.len() != function.params.len() {
+            return None;
+        }
+        let mut substitute = Substitute {
+            args: HashMap::new(),
+        };
+        for (param, arg) in function.params.iter().zip(args.iter()) {
+            let simple = match arg {
+                Expr::Ident(_) => function.used.contains(param),
+                Expr::BoolLiteral(_) | Expr::StringLiteral(_) => true,
+                _ => int_value(arg).is_some(),
+            };
+            if !simple {
+                return None;
+            }
+            substitute.args.insert(param.clone(), arg.clone());
+        }
+        let mut body = function.body.clone();
+        rewrite_expr(&mut substitute, &mut body);
+        Some(body)
+    }
+}
+
+impl Rewrite for Inliner {
+    fn expr(&mut self, expr: &mut Expr) -> bool {
+        let Expr::Call { callee, args } = expr else {
+            return false;
+        };
+        match self.inline(callee, args) {
+            Some(body) => {
+                *expr = body;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// replaces parameters by arguments. the body binds no names, so nothing
+/// can be captured.
+struct Substitute {
+    args: HashMap<String, Expr>,
+}
+
+impl Rewrite for Substitute {
+    fn expr(&mut self, expr: &mut Expr) -> bool {
+        if let Expr::Ident(name) = expr {
+            if let Some(arg) = self.args.get(name) {
+                *expr = arg.clone();
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::assert_optimized;
+
+    #[test]
+    fn test_inline() {
+        let tests = [
+            (
+                "let add = fn(a, b) { a + b }; add(1, 2);",
+                "let add = fn(a, b) { a + b; };\n1 + 2;",
+            ),
+            (
+                "let sq = fn(x) { return x * x; }; let n = 3; sq(n);",
+                "let sq = fn(x) { return x * x; };\nlet n = 3;\nn * n;",
+            ),
+            (
+                "let size = fn(x) { len(x) }; let f = fn(s) { size(s) + 1 }; f(\"ab\");",
+                "let size = fn(x) { len(x); };\nlet f = fn(s) { len(s) + 1; };\nlen(\"ab\") + 1;",
+            ),
+            (
+                "let pick = fn(c) { if (c) { 1 } else { 2 } }; pick(false);",
+                "let pick = fn(c) { if (c) { 1; } else { 2; }; };\nif (false) { 1; } else { 2; };",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_optimized(&mut Inline, input, expected);
+        }
+
+        let kept = [
+            // recursive
+            "let f = fn(n) { if (n < 1) { 0; } else { f(n - 1); }; };\nf(3);",
+            // captures a binding
+            "let a = 1;\nlet f = fn(x) { x + a; };\nf(2);",
+            // bound twice
+            "let f = fn(x) { x; };\nlet f = fn(x) { x + 1; };\nf(1);",
+            // argument is not a literal or an identifier
+            "let f = fn(x) { x; };\nf(1 + 2);",
+            // an undefined name passed to an unused parameter still fails
+            "let f = fn(x) { 1; };\nf(y);",
+            // wrong number of arguments
+            "let f = fn(x, y) { x; };\nf(1);",
+            // more than an expression
+            "let f = fn(x) { let y = x; y; };\nf(1);",
+            // a `return` in the body would return from the caller
+            "let f = fn(x) { if (x) { return 1; }; };\nf(true);",
+            // a shadowed builtin
+            "let f = fn(x) { len(x); };\nlet g = fn(len) { len; };\nf(\"a\");",
+        ];
+        for input in kept {
+            assert_optimized(&mut Inline, input, input);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! AST optimisation.
+//!
+//! A [`PassManager`] runs a list of [`Pass`]es over a [`Program`] until none
+//! of them changes it any more. Every pass keeps the value the program
+//! evaluates to, including the error it fails with.
+//!
+//! ```
+//! use rmonkey_ast::builder::*;
+//!
+//! let mut program = program([
+//!     let_("add", func(["a", "b"], [expr(ident("a").add(ident("b")))])),
+//!     expr(ident("add").call([int(2), int(5).add(10)])),
+//! ]);
+//! rmonkey_opt::optimize(&mut program);
+//! assert_eq!(program.stmts, vec![expr(int(17))]);
+//! ```
+
+use rmonkey_ast::Program;
+
+mod branch;
+mod fold;
+mod inline;
+mod unused;
+mod walk;
+
+pub use branch::DeadBranch;
+pub use fold::ConstantFold;
+pub use inline::Inline;
+pub use unused::UnusedLet;
+
+/// the most times the passes are run, in case they keep undoing each other
+const MAX_ROUNDS: usize = 32;
+
+pub trait Pass {
+    fn name(&self) -> &'static str;
+
+    /// rewrite `program` and return whether anything changed
+    fn run(&mut self, program: &mut Program) -> bool;
+}
+
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    /// a manager without passes
+    pub fn new() -> Self {
+        PassManager { passes: Vec::new() }
+    }
+
+    pub fn with_pass(mut self, pass: impl Pass + 'static) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// names of the passes, in the order they run
+    pub fn passes(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    /// run every pass in turn until none changes `program`, and return
+    /// whether anything changed. the spans, blocks, comments and identifiers
+    /// of a changed program no longer match its statements, so they are
+    /// cleared.
+    pub fn run(&mut self, program: &mut Program) -> bool {
+        let mut changed = false;
+        for _ in 0..MAX_ROUNDS {
+            let mut round = false;
+            for pass in self.passes.iter_mut() {
+                round |= pass.run(program);
+            }
+            if !round {
+                break;
+            }
+            changed = true;
+        }
+        if changed {
+            program.spans.clear();
+            program.blocks.clear();
+            program.comments.clear();
+            program.idents.clear();
+        }
+        changed
+    }
+}
+
+impl Default for PassManager {
+    /// every pass, with inlining first so that folding sees the inlined
+    /// arguments
+    fn default() -> Self {
+        PassManager::new()
+            .with_pass(Inline)
+            .with_pass(ConstantFold)
+            .with_pass(DeadBranch)
+            .with_pass(UnusedLet)
+    }
+}
+
+/// run every pass over `program`
+pub fn optimize(program: &mut Program) -> bool {
+    PassManager::default().run(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use rmonkey_evaluator::Evaluator;
+    use rmonkey_fmt::codegen::print;
+    use rmonkey_lexer::Lexer;
+    use rmonkey_parser::Parser;
+
+    use super::*;
+
+    fn parse(input: &str) -> Program {
+        let mut parser = Parser::new(Lexer::new(input));
+        parser.parse_program().unwrap()
+    }
+
+    fn eval(program: Program) -> String {
+        match Evaluator::new().eval(program) {
+            Ok(value) => value.to_string(),
+            Err(err) => format!("error: {err}"),
+        }
+    }
+
+    /// run `pass` over `input` until it changes nothing, check that the
+    /// result prints as `expected` and evaluates to the same value
+    pub(crate) fn assert_optimized(pass: &mut dyn Pass, input: &str, expected: &str) {
+        let mut program = parse(input);
+        for _ in 0..MAX_ROUNDS {
+            if !pass.run(&mut program) {
+                break;
+            }
+        }
+        assert_eq!(print(&program).trim_end(), expected, "{input}");
+        assert_eq!(eval(program), eval(parse(input)), "{input}");
+    }
+
+    #[test]
+    fn test_optimize() {
+        let tests = [
+            (
+                "let double = fn(x) { x * 2 }; let f = fn(n) { double(n) + double(3) }; f(if (true) { 4 } else { 5 });",
+                "14;",
+            ),
+            (
+                "let max = fn(a, b) { if (a > b) { a } else { b } }; max(3, 4) * (2 - 1);",
+                "4;",
+            ),
+            (
+                "let debug = false; let log = fn(x) { puts(x) }; if (false) { log(1) }; \"a\" + \"b\";",
+                "\"ab\";",
+            ),
+        ];
+        for (input, expected) in tests {
+            let mut program = parse(input);
+            assert!(optimize(&mut program));
+            assert!(program.spans.is_empty());
+            assert_eq!(print(&program).trim_end(), expected, "{input}");
+            assert_eq!(eval(program), eval(parse(input)), "{input}");
+        }
+
+        let mut program = parse("let a = [1, 2]; a;");
+        assert!(!optimize(&mut program));
+        assert_eq!(program.spans.len(), 2);
+    }
+
+    #[test]
+    fn test_pass_manager() {
+        let mut manager = PassManager::new().with_pass(ConstantFold);
+        assert_eq!(manager.passes(), vec!["constant_fold"]);
+        let mut program = parse("if (1 < 2) { 3 + 4 };");
+        assert!(manager.run(&mut program));
+        assert_eq!(print(&program), "if (true) { 7; };");
+
+        assert_eq!(
+            PassManager::default().passes(),
+            vec!["inline", "constant_fold", "dead_branch", "unused_let"]
+        );
+    }
+}
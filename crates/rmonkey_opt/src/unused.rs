@@ -0,0 +1,86 @@
+//! Removal of unused `let` bindings.
+//!
+//! A `let` is removed when its name is never referenced anywhere in the
+//! program and evaluating its value can neither fail nor have an effect. A
+//! `let` that ends a block is kept, since it gives the block its null value,
+//! and so is an `export let`, which the importing programs may refer to.
+
+use rmonkey_ast::{pattern::Pattern, Program, Stmt};
+
+use crate::{
+    walk::{is_pure, rewrite_stmts, Names, Rewrite},
+    Pass,
+};
+
+#[derive(Debug, Default)]
+pub struct UnusedLet;
+
+impl Pass for UnusedLet {
+    fn name(&self) -> &'static str {
+        "unused_let"
+    }
+
+    fn run(&mut self, program: &mut Program) -> bool {
+        let mut remove = Remove {
+            names: Names::of(&program.stmts),
+        };
+        rewrite_stmts(&mut remove, &mut program.stmts)
+    }
+}
+
+struct Remove {
+    names: Names,
+}
+
+impl Rewrite for Remove {
+    fn stmts(&mut self, stmts: &mut Vec<Stmt>) -> bool {
+        let last = stmts.len().saturating_sub(1);
+        let len = stmts.len();
+        let mut i = 0;
+        stmts.retain(|stmt| {
+            let keep = i == last
+                || match stmt {
+                    Stmt::LetStmt {
+                        export,
+                        name: Pattern::Ident(name),
+                        value,
+                        ..
+                    } => *export || self.names.used(name) > 0 || !is_pure(value),
+                    _ => true,
+                };
+            i += 1;
+            keep
+        });
+        stmts.len() != len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::assert_optimized;
+
+    #[test]
+    fn test_unused_let() {
+        let tests = [
+            ("let a = 1; let b = 2; b;", "let b = 2;\nb;"),
+            (
+                "let f = fn(x) { let y = [1, {\"a\": fn() { 1 }}]; x }; f(1);",
+                "let f = fn(x) { x; };\nf(1);",
+            ),
+            // may fail or have an effect
+            ("let a = 1 + true; 1;", "let a = 1 + true;\n1;"),
+            ("let a = puts(1); 1;", "let a = puts(1);\n1;"),
+            ("let a = b; 1;", "let a = b;\n1;"),
+            // gives the program its value
+            ("1; let a = 1;", "1;\nlet a = 1;"),
+            // referenced only by a binding that is removed first
+            ("let a = 1; let f = fn() { a }; 2;", "2;"),
+            // may be referenced by an importing program
+            ("export let a = 1; 2;", "export let a = 1;\n2;"),
+        ];
+        for (input, expected) in tests {
+            assert_optimized(&mut UnusedLet, input, expected);
+        }
+    }
+}
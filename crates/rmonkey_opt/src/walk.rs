@@ -0,0 +1,308 @@
+//! Traversal shared by the passes.
+
+use std::collections::HashMap;
+
+use rmonkey_ast::{operator::Prefix, pattern::Pattern, Expr, Stmt};
+
+/// a rewrite applied bottom-up: every hook sees its node after the nodes
+/// inside it were rewritten. the hooks return whether they changed anything.
+pub(crate) trait Rewrite {
+    fn expr(&mut self, _expr: &mut Expr) -> bool {
+        false
+    }
+
+    /// called for the statements of the program and of every block
+    fn stmts(&mut self, _stmts: &mut Vec<Stmt>) -> bool {
+        false
+    }
+}
+
+pub(crate) fn rewrite_stmts(r: &mut impl Rewrite, stmts: &mut Vec<Stmt>) -> bool {
+    let mut changed = false;
+    for stmt in stmts.iter_mut() {
+        changed |= rewrite_stmt(r, stmt);
+    }
+    changed | r.stmts(stmts)
+}
+
+pub(crate) fn rewrite_stmt(r: &mut impl Rewrite, stmt: &mut Stmt) -> bool {
+    match stmt {
+        Stmt::LetStmt { value, .. } => rewrite_expr(r, value),
+        Stmt::ReturnStmt(expr) | Stmt::ExprStmt(expr) | Stmt::Throw(expr) => rewrite_expr(r, expr),
+        Stmt::BlockStmt { stmts } => rewrite_stmts(r, stmts),
+        Stmt::Import { .. } => false,
+    }
+}
+
+pub(crate) fn rewrite_expr(r: &mut impl Rewrite, expr: &mut Expr) -> bool {
+    let changed = match expr {
+        Expr::Ident(_) | Expr::IntLiteral(_) | Expr::BoolLiteral(_) | Expr::StringLiteral(_) => {
+            false
+        }
+        Expr::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            let mut changed = rewrite_expr(r, condition);
+            changed |= rewrite_stmt(r, consequence);
+            if let Some(alternative) = alternative {
+                changed |= rewrite_stmt(r, alternative);
+            }
+            changed
+        }
+        Expr::PrefixExpr { right, .. } | Expr::Propagate { value: right } => rewrite_expr(r, right),
+        Expr::InfixExpr { left, right, .. } => {
+            let changed = rewrite_expr(r, left);
+            changed | rewrite_expr(r, right)
+        }
+        Expr::Func { body, .. } | Expr::Macro { body, .. } => rewrite_stmt(r, body),
+        Expr::Call { callee, args } => {
+            let mut changed = rewrite_expr(r, callee);
+            for arg in args.iter_mut() {
+                changed |= rewrite_expr(r, arg);
+            }
+            changed
+        }
+        Expr::Array { elements } => {
+            let mut changed = false;
+            for element in elements.iter_mut() {
+                changed |= rewrite_expr(r, element);
+            }
+            changed
+        }
+        Expr::IndexExpr { left, index } => {
+            let changed = rewrite_expr(r, left);
+            changed | rewrite_expr(r, index)
+        }
+        Expr::Slice {
+            left,
+            start,
+            end,
+            step,
+        } => {
+            let mut changed = rewrite_expr(r, left);
+            for bound in [start, end, step].into_iter().flatten() {
+                changed |= rewrite_expr(r, bound);
+            }
+            changed
+        }
+        Expr::HashLiteral { pairs } => {
+            let mut changed = false;
+            for (key, value) in pairs.iter_mut() {
+                changed |= rewrite_expr(r, key);
+                changed |= rewrite_expr(r, value);
+            }
+            changed
+        }
+        Expr::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            let mut changed = rewrite_stmt(r, body);
+            if let Some(catch) = catch {
+                changed |= rewrite_stmt(r, &mut catch.body);
+            }
+            if let Some(finally) = finally {
+                changed |= rewrite_stmt(r, finally);
+            }
+            changed
+        }
+        Expr::Match { value, arms } => {
+            let mut changed = rewrite_expr(r, value);
+            for arm in arms.iter_mut() {
+                if let Some(guard) = &mut arm.guard {
+                    changed |= rewrite_expr(r, guard);
+                }
+                changed |= rewrite_expr(r, &mut arm.body);
+            }
+            changed
+        }
+    };
+    changed | r.expr(expr)
+}
+
+/// how often each name is bound by a `let`, an `import`, a parameter of a
+/// function or `catch` or a `match` pattern, and referenced, anywhere in a
+/// program
+#[derive(Debug, Default)]
+pub(crate) struct Names {
+    pub(crate) bound: HashMap<String, usize>,
+    pub(crate) used: HashMap<String, usize>,
+}
+
+impl Names {
+    pub(crate) fn of(stmts: &[Stmt]) -> Self {
+        let mut names = Names::default();
+        for stmt in stmts.iter() {
+            names.stmt(stmt);
+        }
+        names
+    }
+
+    pub(crate) fn bound(&self, name: &str) -> usize {
+        self.bound.get(name).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn used(&self, name: &str) -> usize {
+        self.used.get(name).copied().unwrap_or(0)
+    }
+
+    fn bind_pattern(&mut self, pattern: &Pattern) {
+        for name in pattern.names() {
+            self.bind_name(name);
+        }
+    }
+
+    fn bind_name(&mut self, name: &str) {
+        *self.bound.entry(name.to_string()).or_default() += 1;
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::LetStmt { name, value, .. } => {
+                self.bind_pattern(name);
+                self.expr(value);
+            }
+            Stmt::ReturnStmt(expr) | Stmt::ExprStmt(expr) | Stmt::Throw(expr) => self.expr(expr),
+            Stmt::BlockStmt { stmts } => {
+                for stmt in stmts.iter() {
+                    self.stmt(stmt);
+                }
+            }
+            Stmt::Import { alias, .. } => self.bind_name(alias),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Ident(name) => *self.used.entry(name.clone()).or_default() += 1,
+            Expr::IntLiteral(_) | Expr::BoolLiteral(_) | Expr::StringLiteral(_) => {}
+            Expr::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.expr(condition);
+                self.stmt(consequence);
+                if let Some(alternative) = alternative {
+                    self.stmt(alternative);
+                }
+            }
+            Expr::PrefixExpr { right, .. } | Expr::Propagate { value: right } => self.expr(right),
+            Expr::InfixExpr { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Func { params, body, .. } => {
+                for param in params.iter() {
+                    self.bind_pattern(param);
+                }
+                self.stmt(body);
+            }
+            Expr::Macro { params, body } => {
+                for param in params.iter() {
+                    self.bind_name(param);
+                }
+                self.stmt(body);
+            }
+            Expr::Call { callee, args } => {
+                self.expr(callee);
+                for arg in args.iter() {
+                    self.expr(arg);
+                }
+            }
+            Expr::Array { elements } => {
+                for element in elements.iter() {
+                    self.expr(element);
+                }
+            }
+            Expr::IndexExpr { left, index } => {
+                self.expr(left);
+                self.expr(index);
+            }
+            Expr::Slice {
+                left,
+                start,
+                end,
+                step,
+            } => {
+                self.expr(left);
+                for bound in [start, end, step].into_iter().flatten() {
+                    self.expr(bound);
+                }
+            }
+            Expr::HashLiteral { pairs } => {
+                for (key, value) in pairs.iter() {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            Expr::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.stmt(body);
+                if let Some(catch) = catch {
+                    self.bind_name(&catch.param);
+                    self.stmt(&catch.body);
+                }
+                if let Some(finally) = finally {
+                    self.stmt(finally);
+                }
+            }
+            Expr::Match { value, arms } => {
+                self.expr(value);
+                for arm in arms.iter() {
+                    self.bind_pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard);
+                    }
+                    self.expr(&arm.body);
+                }
+            }
+        }
+    }
+}
+
+/// whether evaluating `expr` can neither fail nor have an effect
+pub(crate) fn is_pure(expr: &Expr) -> bool {
+    match expr {
+        Expr::IntLiteral(_) | Expr::BoolLiteral(_) | Expr::StringLiteral(_) | Expr::Func { .. } => {
+            true
+        }
+        Expr::PrefixExpr { op, right } => match op {
+            Prefix::Minus => matches!(**right, Expr::IntLiteral(_)),
+            Prefix::Bang => is_pure(right),
+        },
+        Expr::Array { elements } => elements.iter().all(is_pure),
+        Expr::HashLiteral { pairs } => pairs.iter().all(|(k, v)| is_pure(k) && is_pure(v)),
+        _ => false,
+    }
+}
+
+/// the value of an integer constant, either a literal or a negated literal
+pub(crate) fn int_value(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::IntLiteral(val) => Some(*val),
+        Expr::PrefixExpr {
+            op: Prefix::Minus,
+            right,
+        } => match **right {
+            Expr::IntLiteral(val) => val.checked_neg(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// truthiness of a literal condition, `None` when it is not a literal
+pub(crate) fn truthiness(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::BoolLiteral(val) => Some(*val),
+        Expr::StringLiteral(_) => Some(true),
+        _ => int_value(expr).map(|_| true),
+    }
+}
@@ -1,7 +1,10 @@
 use rmonkey_ast::{
     operator::{Infix, Prefix},
+    pattern::Pattern,
     precedence::Precedence,
-    Expr, Program, Stmt,
+    span::{BlockSpans, Span},
+    ty::TypeExpr,
+    Catch, Expr, MatchArm, Program, Stmt,
 };
 use rmonkey_error::{RMonkeyError, Result};
 use rmonkey_lexer::Lexer;
@@ -12,6 +15,12 @@ pub struct Parser<'a> {
     l: Lexer<'a>,
     cur_token: Token,
     peek_token: Token,
+    cur_span: Span,
+    peek_span: Span,
+    /// blocks and hash literals parsed so far, in source order of the `{`
+    blocks: Vec<BlockSpans>,
+    /// identifiers parsed so far
+    idents: Vec<Span>,
 }
 
 impl<'a> Parser<'a> {
@@ -20,6 +29,10 @@ impl<'a> Parser<'a> {
             l,
             cur_token: Token::Illegal,
             peek_token: Token::Illegal,
+            cur_span: Span::default(),
+            peek_span: Span::default(),
+            blocks: Vec::new(),
+            idents: Vec::new(),
         };
         parser.next_token();
         parser.next_token();
@@ -37,10 +50,18 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// span of the current and the next token. after `parse_program()`
+    /// failed, this is where the error was detected.
+    pub fn span(&self) -> Span {
+        Span::new(self.cur_span.start, self.peek_span.end)
+    }
+
     fn next_token(&mut self) -> Token {
         let cur = self.cur_token.clone();
         self.cur_token = self.peek_token.clone();
+        self.cur_span = self.peek_span;
         self.peek_token = self.l.next_token();
+        self.peek_span = self.l.span();
         cur
     }
 
@@ -64,28 +85,71 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// like `expect_peek`, but fail with an error naming `t`
+    fn expect_next(&mut self, t: Token) -> Result<()> {
+        if self.expect_peek(t.clone()) {
+            Ok(())
+        } else {
+            Err(RMonkeyError::UnexpectedToken {
+                expected: t,
+                got: self.peek_token.clone(),
+            })
+        }
+    }
+
     pub fn parse_program(&mut self) -> Result<Program> {
         let mut stmts: Vec<Stmt> = vec![];
+        let mut spans: Vec<Span> = vec![];
         while self.cur_token != Token::Eof {
-            stmts.push(self.parse_stmt()?);
+            let start = self.cur_span.start;
+            let stmt = match self.cur_token {
+                Token::Import => self.parse_import_stmt()?,
+                Token::Export => self.parse_export_stmt()?,
+                _ => self.parse_stmt()?,
+            };
+            stmts.push(stmt);
+            spans.push(Span::new(start, self.cur_span.end));
             self.next_token();
         }
-        Ok(Program::new(stmts))
+        let mut program = Program::with_spans(stmts, spans);
+        program.blocks = std::mem::take(&mut self.blocks);
+        program.comments = self.l.comments().to_vec();
+        program.idents = std::mem::take(&mut self.idents);
+        Ok(program)
     }
 
     fn parse_stmt(&mut self) -> Result<Stmt> {
         match self.cur_token {
-            Token::Let => Ok(self.parse_let_stmt()?),
+            Token::Let => Ok(self.parse_let_stmt(false)?),
             Token::Return => Ok(self.parse_return_stmt()?),
+            Token::Throw => Ok(self.parse_throw_stmt()?),
+            Token::Import | Token::Export => Err(RMonkeyError::Custom(format!(
+                "`{}` is only allowed at the top level",
+                self.cur_token
+            ))),
             _ => Ok(self.parse_expr_stmt()?),
         }
     }
 
-    fn parse_let_stmt(&mut self) -> Result<Stmt> {
-        // consume `let`
+    fn parse_import_stmt(&mut self) -> Result<Stmt> {
+        // consume `import`
         self.next_token();
-        let ident = match &self.cur_token {
-            Token::Ident(val) => val.to_owned(),
+        let path = match &self.cur_token {
+            Token::String(path) => path.clone(),
+            tok => {
+                return Err(RMonkeyError::UnexpectedToken {
+                    expected: Token::String("path".to_string()),
+                    got: tok.clone(),
+                });
+            }
+        };
+        self.expect_next(Token::As)?;
+        self.next_token();
+        let alias = match &self.cur_token {
+            Token::Ident(val) => {
+                self.idents.push(self.cur_span);
+                val.to_owned()
+            }
             tok => {
                 return Err(RMonkeyError::UnexpectedToken {
                     expected: Token::Ident("Ident".to_string()),
@@ -93,6 +157,23 @@ impl<'a> Parser<'a> {
                 });
             }
         };
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+        Ok(Stmt::Import { path, alias })
+    }
+
+    fn parse_export_stmt(&mut self) -> Result<Stmt> {
+        // consume `export`
+        self.expect_next(Token::Let)?;
+        self.parse_let_stmt(true)
+    }
+
+    fn parse_let_stmt(&mut self, export: bool) -> Result<Stmt> {
+        // consume `let`
+        self.next_token();
+        let name = self.parse_binding()?;
+        let ty = self.parse_annotation()?;
         if !self.expect_peek(Token::Assign) {
             return Err(RMonkeyError::UnexpectedToken {
                 expected: Token::Assign,
@@ -108,7 +189,9 @@ impl<'a> Parser<'a> {
         }
 
         Ok(Stmt::LetStmt {
-            name: Expr::Ident(ident),
+            export,
+            name,
+            ty,
             value,
         })
     }
@@ -125,6 +208,18 @@ impl<'a> Parser<'a> {
         Ok(Stmt::ReturnStmt(return_val))
     }
 
+    fn parse_throw_stmt(&mut self) -> Result<Stmt> {
+        // consume `throw`
+        self.next_token();
+
+        let value = self.parse_expr(Precedence::Lowest)?;
+
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+        Ok(Stmt::Throw(value))
+    }
+
     fn parse_expr_stmt(&mut self) -> Result<Stmt> {
         let expr = self.parse_expr(Precedence::Lowest)?;
         if self.peek_token_is(Token::Semicolon) {
@@ -135,16 +230,31 @@ impl<'a> Parser<'a> {
 
     /// should call this if `self.cur_token == Token::LBrace`
     fn parse_block_stmt(&mut self) -> Result<Stmt> {
+        let index = self.open_block();
         self.next_token();
         let mut stmts: Vec<Stmt> = Vec::new();
+        let mut spans: Vec<Span> = Vec::new();
         while !self.cur_token_is(Token::RBrace) && !self.cur_token_is(Token::Eof) {
+            let start = self.cur_span.start;
             let stmt = self.parse_stmt()?;
             stmts.push(stmt);
+            spans.push(Span::new(start, self.cur_span.end));
             self.next_token();
         }
+        self.blocks[index].span.end = self.cur_span.end;
+        self.blocks[index].stmts = spans;
         Ok(Stmt::BlockStmt { stmts })
     }
 
+    /// record the block or hash literal starting at the current `{`
+    fn open_block(&mut self) -> usize {
+        self.blocks.push(BlockSpans {
+            span: self.cur_span,
+            stmts: Vec::new(),
+        });
+        self.blocks.len() - 1
+    }
+
     /// The function begins with the token associated with the syntax parsing function set to curToken.
     /// It then proceeds until the last token of the expression being processed is set to curToken.
     fn parse_expr(&mut self, prec: Precedence) -> Result<Expr> {
@@ -156,7 +266,10 @@ impl<'a> Parser<'a> {
             Token::Bang | Token::Minus => self.parse_prefix_expr()?,
             Token::LParen => self.parse_grouped_expr()?,
             Token::If => self.parse_if_expr()?,
+            Token::Try => self.parse_try_expr()?,
+            Token::Match => self.parse_match_expr()?,
             Token::Function => self.parse_func_literal()?,
+            Token::Macro => self.parse_macro_literal()?,
             Token::LBracket => self.parse_array_literal()?,
             Token::LBrace => self.parse_hash_literal()?,
             _ => {
@@ -171,6 +284,9 @@ impl<'a> Parser<'a> {
             left = match self.cur_token {
                 Token::LParen => self.parse_call_expr(left)?,
                 Token::LBracket => self.parse_index_expr(left)?,
+                Token::Question => Expr::Propagate {
+                    value: Box::new(left),
+                },
                 _ => self.parse_infix_expr(left)?,
             }
         }
@@ -178,6 +294,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_identifier(&mut self, val: String) -> Result<Expr> {
+        self.idents.push(self.cur_span);
         Ok(Expr::Ident(val))
     }
 
@@ -247,6 +364,196 @@ impl<'a> Parser<'a> {
         })
     }
 
+    fn parse_try_expr(&mut self) -> Result<Expr> {
+        self.expect_next(Token::LBrace)?;
+        let body = self.parse_block_stmt()?;
+
+        let catch = if self.expect_peek(Token::Catch) {
+            self.expect_next(Token::LParen)?;
+            self.next_token();
+            let param = match &self.cur_token {
+                Token::Ident(val) => {
+                    self.idents.push(self.cur_span);
+                    val.to_owned()
+                }
+                tok => {
+                    return Err(RMonkeyError::UnexpectedToken {
+                        expected: Token::Ident("Ident".to_string()),
+                        got: tok.clone(),
+                    });
+                }
+            };
+            self.expect_next(Token::RParen)?;
+            self.expect_next(Token::LBrace)?;
+            let body = self.parse_block_stmt()?;
+            Some(Box::new(Catch {
+                param,
+                body: Box::new(body),
+            }))
+        } else {
+            None
+        };
+
+        let finally = if catch.is_none() || self.peek_token_is(Token::Finally) {
+            self.expect_next(Token::Finally)?;
+            self.expect_next(Token::LBrace)?;
+            Some(Box::new(self.parse_block_stmt()?))
+        } else {
+            None
+        };
+
+        Ok(Expr::Try {
+            body: Box::new(body),
+            catch,
+            finally,
+        })
+    }
+
+    fn parse_match_expr(&mut self) -> Result<Expr> {
+        self.expect_next(Token::LParen)?;
+        self.next_token();
+        let value = self.parse_expr(Precedence::Lowest)?;
+        self.expect_next(Token::RParen)?;
+        self.expect_next(Token::LBrace)?;
+
+        let mut arms: Vec<MatchArm> = Vec::new();
+        while !self.peek_token_is(Token::RBrace) {
+            self.next_token();
+            let pattern = self.parse_pattern()?;
+            let covering = arms
+                .iter()
+                .find(|arm| arm.guard.is_none() && arm.pattern.covers(&pattern));
+            if let Some(arm) = covering {
+                return Err(RMonkeyError::Custom(format!(
+                    "unreachable match arm: `{pattern}` is always matched by `{}` before",
+                    arm.pattern
+                )));
+            }
+            let guard = if self.expect_peek(Token::If) {
+                self.next_token();
+                Some(self.parse_expr(Precedence::Lowest)?)
+            } else {
+                None
+            };
+            self.expect_next(Token::FatArrow)?;
+            self.next_token();
+            let body = self.parse_expr(Precedence::Lowest)?;
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+            // the comma is optional after the last arm
+            if !self.peek_token_is(Token::RBrace) {
+                self.expect_next(Token::Comma)?;
+            }
+        }
+        // consume `}`
+        self.next_token();
+
+        Ok(Expr::Match {
+            value: Box::new(value),
+            arms,
+        })
+    }
+
+    /// parse what a `let` or a parameter binds: a name, `_`, or an array or
+    /// hash pattern destructuring the value
+    fn parse_binding(&mut self) -> Result<Pattern> {
+        match &self.cur_token {
+            Token::Ident(_) | Token::LBracket | Token::LBrace => self.parse_pattern(),
+            tok => Err(RMonkeyError::UnexpectedToken {
+                expected: Token::Ident("Ident".to_string()),
+                got: tok.clone(),
+            }),
+        }
+    }
+
+    /// parse the pattern starting at the current token, ending on its last
+    /// token
+    fn parse_pattern(&mut self) -> Result<Pattern> {
+        if let Some(value) = self.parse_literal_pattern() {
+            return Ok(Pattern::Literal(value));
+        }
+        match self.cur_token.clone() {
+            Token::Ident(name) if name == "_" => Ok(Pattern::Wildcard),
+            Token::Ident(name) => {
+                self.idents.push(self.cur_span);
+                Ok(Pattern::Ident(name))
+            }
+            Token::LBracket => {
+                let mut elements = Vec::new();
+                let mut rest = None;
+                while !self.peek_token_is(Token::RBracket) {
+                    self.next_token();
+                    if self.cur_token_is(Token::Ellipsis) {
+                        self.next_token();
+                        let pattern = self.parse_pattern()?;
+                        if !pattern.is_irrefutable() {
+                            return Err(RMonkeyError::Custom(format!(
+                                "the rest of an array pattern must be a name or `_`, got `{pattern}`"
+                            )));
+                        }
+                        rest = Some(Box::new(pattern));
+                        // allow a trailing comma
+                        self.expect_peek(Token::Comma);
+                        break;
+                    }
+                    elements.push(self.parse_pattern()?);
+                    if !self.peek_token_is(Token::RBracket) {
+                        self.expect_next(Token::Comma)?;
+                    }
+                }
+                self.expect_next(Token::RBracket)?;
+                Ok(Pattern::Array { elements, rest })
+            }
+            Token::LBrace => {
+                let mut pairs = Vec::new();
+                while !self.peek_token_is(Token::RBrace) {
+                    self.next_token();
+                    let key = self.parse_literal_pattern().ok_or_else(|| {
+                        RMonkeyError::Custom(format!(
+                            "a hash pattern key must be a literal, got {}",
+                            self.cur_token
+                        ))
+                    })?;
+                    self.expect_next(Token::Colon)?;
+                    self.next_token();
+                    pairs.push((key, self.parse_pattern()?));
+                    if !self.peek_token_is(Token::RBrace) {
+                        self.expect_next(Token::Comma)?;
+                    }
+                }
+                self.next_token();
+                Ok(Pattern::Hash { pairs })
+            }
+            tok => Err(RMonkeyError::Custom(format!(
+                "expected a pattern, but got {tok}"
+            ))),
+        }
+    }
+
+    /// parse an integer, boolean or string literal, with a `-` for a
+    /// negative integer, if the current token starts one
+    fn parse_literal_pattern(&mut self) -> Option<Expr> {
+        match self.cur_token.clone() {
+            Token::Int(val) => Some(Expr::IntLiteral(val)),
+            Token::True | Token::False => Some(Expr::BoolLiteral(self.cur_token_is(Token::True))),
+            Token::String(val) => Some(Expr::StringLiteral(val)),
+            Token::Minus => match self.peek_token {
+                Token::Int(val) => {
+                    self.next_token();
+                    Some(Expr::PrefixExpr {
+                        op: Prefix::Minus,
+                        right: Box::new(Expr::IntLiteral(val)),
+                    })
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     fn parse_func_literal(&mut self) -> Result<Expr> {
         if !self.expect_peek(Token::LParen) {
             return Err(RMonkeyError::UnexpectedToken {
@@ -255,49 +562,82 @@ impl<'a> Parser<'a> {
             });
         }
 
-        let params = self.parse_func_params()?;
+        let (params, param_types) = self.parse_func_params()?;
+        let ret = if self.peek_token_is(Token::Arrow) {
+            self.next_token();
+            self.next_token();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
         self.next_token();
         let body = self.parse_block_stmt()?;
         Ok(Expr::Func {
             params,
+            param_types,
+            ret,
             body: Box::new(body),
         })
     }
 
-    fn parse_func_params(&mut self) -> Result<Vec<Expr>> {
+    /// parse `macro(a, b) { }`. the parameters are names, since they are
+    /// bound to code, not values.
+    fn parse_macro_literal(&mut self) -> Result<Expr> {
+        self.expect_next(Token::LParen)?;
+        let mut params = Vec::new();
+        while !self.peek_token_is(Token::RParen) {
+            self.next_token();
+            match self.cur_token.clone() {
+                Token::Ident(name) => {
+                    self.idents.push(self.cur_span);
+                    params.push(name);
+                }
+                tok => {
+                    return Err(RMonkeyError::UnexpectedToken {
+                        expected: Token::Ident("Ident".to_string()),
+                        got: tok,
+                    })
+                }
+            }
+            if !self.peek_token_is(Token::RParen) {
+                self.expect_next(Token::Comma)?;
+            }
+        }
+        // consume `)`
+        self.next_token();
+        self.expect_next(Token::LBrace)?;
+        let body = self.parse_block_stmt()?;
+        Ok(Expr::Macro {
+            params,
+            body: Box::new(body),
+        })
+    }
+
+    /// parse `(a, b: int, [c, d])`. the annotations are empty unless a
+    /// parameter is annotated.
+    fn parse_func_params(&mut self) -> Result<(Vec<Pattern>, Vec<Option<TypeExpr>>)> {
+        let mut params: Vec<Pattern> = Vec::new();
+        let mut types: Vec<Option<TypeExpr>> = Vec::new();
         if self.peek_token_is(Token::RParen) {
             self.next_token();
-            return Ok(Vec::new());
+            return Ok((params, types));
         }
 
         // consume `(`
         self.next_token();
-        let mut params: Vec<Expr> = Vec::new();
-        let first_param = match &self.cur_token {
-            Token::Ident(val) => Expr::Ident(val.to_owned()),
-            _ => {
-                return Err(RMonkeyError::UnexpectedToken {
-                    expected: Token::Ident("Ident".to_string()),
-                    got: self.cur_token.clone(),
-                })
-            }
-        };
-        params.push(first_param);
+        loop {
+            params.push(self.parse_binding()?);
+            types.push(self.parse_annotation()?);
 
-        while self.peek_token_is(Token::Comma) {
-            self.next_token();
+            if !self.peek_token_is(Token::Comma) {
+                break;
+            }
             self.next_token();
-            match &self.cur_token {
-                Token::Ident(val) => {
-                    params.push(Expr::Ident(val.to_owned()));
-                }
-                _ => {
-                    return Err(RMonkeyError::UnexpectedToken {
-                        expected: Token::Ident("Ident".to_string()),
-                        got: self.cur_token.clone(),
-                    })
-                }
+            // allow a trailing comma
+            if self.peek_token_is(Token::RParen) {
+                break;
             }
+            self.next_token();
         }
 
         if !self.expect_peek(Token::RParen) {
@@ -307,7 +647,72 @@ impl<'a> Parser<'a> {
             });
         }
 
-        Ok(params)
+        if types.iter().all(Option::is_none) {
+            types.clear();
+        }
+        Ok((params, types))
+    }
+
+    /// parse `: <type>` if the next token is `:`
+    fn parse_annotation(&mut self) -> Result<Option<TypeExpr>> {
+        if !self.peek_token_is(Token::Colon) {
+            return Ok(None);
+        }
+        self.next_token();
+        self.next_token();
+        Ok(Some(self.parse_type()?))
+    }
+
+    /// parse a type such as `int`, `[string]`, `{string: int}` or
+    /// `fn(int) -> bool`, ending on its last token
+    fn parse_type(&mut self) -> Result<TypeExpr> {
+        match self.cur_token.clone() {
+            Token::Ident(name) => TypeExpr::from_name(&name)
+                .ok_or_else(|| RMonkeyError::Custom(format!("unknown type: {name}"))),
+            Token::LBracket => {
+                self.next_token();
+                let element = self.parse_type()?;
+                self.expect_next(Token::RBracket)?;
+                Ok(TypeExpr::Array {
+                    element: Box::new(element),
+                })
+            }
+            Token::LBrace => {
+                self.next_token();
+                let key = self.parse_type()?;
+                self.expect_next(Token::Colon)?;
+                self.next_token();
+                let value = self.parse_type()?;
+                self.expect_next(Token::RBrace)?;
+                Ok(TypeExpr::Hash {
+                    key: Box::new(key),
+                    value: Box::new(value),
+                })
+            }
+            Token::Function => {
+                self.expect_next(Token::LParen)?;
+                let mut params = Vec::new();
+                while !self.peek_token_is(Token::RParen) {
+                    self.next_token();
+                    params.push(self.parse_type()?);
+                    if !self.peek_token_is(Token::RParen) {
+                        self.expect_next(Token::Comma)?;
+                    }
+                }
+                self.next_token();
+                self.expect_next(Token::Arrow)?;
+                self.next_token();
+                let ret = self.parse_type()?;
+                Ok(TypeExpr::Func {
+                    params,
+                    ret: Box::new(ret),
+                })
+            }
+            got => Err(RMonkeyError::UnexpectedToken {
+                expected: Token::Ident("type".to_string()),
+                got,
+            }),
+        }
     }
 
     fn parse_call_expr(&mut self, func: Expr) -> Result<Expr> {
@@ -332,6 +737,10 @@ impl<'a> Parser<'a> {
 
         while self.peek_token_is(Token::Comma) {
             self.next_token();
+            // allow a trailing comma
+            if self.peek_token_is(Token::RParen) {
+                break;
+            }
             self.next_token();
             let arg = self.parse_expr(Precedence::Lowest)?;
             args.push(arg);
@@ -407,6 +816,10 @@ impl<'a> Parser<'a> {
 
         while self.peek_token_is(Token::Comma) {
             self.next_token();
+            // allow a trailing comma
+            if self.peek_token_is(end.clone()) {
+                break;
+            }
             self.next_token();
             list.push(self.parse_expr(Precedence::Lowest)?)
         }
@@ -421,23 +834,61 @@ impl<'a> Parser<'a> {
         Ok(list)
     }
 
+    /// `left[index]`, or a slice `left[start:end:step]`
     fn parse_index_expr(&mut self, left: Expr) -> Result<Expr> {
-        self.next_token();
-        let index = self.parse_expr(Precedence::Lowest)?;
+        let start = self.parse_slice_bound()?;
+        if !self.peek_token_is(Token::Colon) {
+            if !self.expect_peek(Token::RBracket) {
+                return Err(RMonkeyError::UnexpectedToken {
+                    expected: Token::RBracket,
+                    got: self.cur_token.clone(),
+                });
+            }
+            // `left[]`
+            let Some(index) = start else {
+                return Err(RMonkeyError::InvalidPrefix {
+                    got: Token::RBracket,
+                });
+            };
+            return Ok(Expr::IndexExpr {
+                left: Box::new(left),
+                index,
+            });
+        }
 
+        self.next_token();
+        let end = self.parse_slice_bound()?;
+        let step = if self.peek_token_is(Token::Colon) {
+            self.next_token();
+            self.parse_slice_bound()?
+        } else {
+            None
+        };
         if !self.expect_peek(Token::RBracket) {
             return Err(RMonkeyError::UnexpectedToken {
                 expected: Token::RBracket,
                 got: self.cur_token.clone(),
             });
         }
-        Ok(Expr::IndexExpr {
+        Ok(Expr::Slice {
             left: Box::new(left),
-            index: Box::new(index),
+            start,
+            end,
+            step,
         })
     }
 
+    /// the expression after a `[` or a `:` of a slice, unless it is left out
+    fn parse_slice_bound(&mut self) -> Result<Option<Box<Expr>>> {
+        if self.peek_token_is(Token::Colon) || self.peek_token_is(Token::RBracket) {
+            return Ok(None);
+        }
+        self.next_token();
+        Ok(Some(Box::new(self.parse_expr(Precedence::Lowest)?)))
+    }
+
     fn parse_hash_literal(&mut self) -> Result<Expr> {
+        let index = self.open_block();
         let mut pairs: Vec<(Expr, Expr)> = Vec::new();
         while !self.peek_token_is(Token::RBrace) {
             self.next_token();
@@ -470,6 +921,7 @@ impl<'a> Parser<'a> {
             });
         }
 
+        self.blocks[index].span.end = self.cur_span.end;
         Ok(Expr::HashLiteral { pairs })
     }
 }
@@ -649,6 +1101,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_slice_expr() {
+        let case = [
+            ("a[1:3]", "(a[1:3])"),
+            ("a[:n]", "(a[:n])"),
+            ("a[1:]", "(a[1:])"),
+            ("a[:]", "(a[:])"),
+            ("a[::2]", "(a[::2])"),
+            ("a[-1:0:-1]", "(a[(-1):0:(-1)])"),
+            ("a[1:3][0]", "((a[1:3])[0])"),
+            ("a[i + 1:len(a)]", "(a[(i + 1):len(a)])"),
+        ];
+        for (input, expected) in case {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            assert_eq!(program.stmts[0].to_string(), expected, "{input}");
+        }
+        for input in ["a[]", "a[1:2:3:4]", "a[1"] {
+            assert!(
+                Parser::new(Lexer::new(input)).parse_program().is_err(),
+                "{input}"
+            );
+        }
+    }
+
     #[test]
     fn test_hash_literal() {
         let input = r#"
@@ -669,4 +1145,365 @@ mod tests {
             assert_eq!(p.to_string(), expected[i]);
         }
     }
+
+    #[test]
+    fn test_stmt_span() {
+        let input = "let a = 1;\nfn(x) { x }(a)\nreturn a";
+        let expected = ["let a = 1;", "fn(x) { x }(a)", "return a"];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.spans.len(), expected.len());
+        for (i, span) in program.spans.iter().enumerate() {
+            assert_eq!(&input[span.start..span.end], expected[i]);
+        }
+    }
+
+    #[test]
+    fn test_error_span() {
+        let input = "let a = 1;\nlet = 2;";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        assert!(p.parse_program().is_err());
+        let span = p.span();
+        assert_eq!(&input[span.start..span.end], "= 2");
+    }
+
+    #[test]
+    fn test_int_out_of_range() {
+        let input = "let a = 99999999999999999999;";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let err = p.parse_program().unwrap_err();
+        assert_eq!(err.to_string(), "custom error: failed to parse expression");
+        let span = p.span();
+        assert!(input[span.start..].starts_with("99999999999999999999"));
+    }
+
+    #[test]
+    fn test_blocks_and_comments() {
+        let input = "// head\nif (x) { let a = {}; a } // tail\nfn() {};";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let text = |span: rmonkey_ast::span::Span| &input[span.start..span.end];
+
+        assert_eq!(program.blocks.len(), 3);
+        assert_eq!(text(program.blocks[0].span), "{ let a = {}; a }");
+        let stmts: Vec<&str> = program.blocks[0].stmts.iter().map(|s| text(*s)).collect();
+        assert_eq!(stmts, ["let a = {};", "a"]);
+        assert_eq!(text(program.blocks[1].span), "{}");
+        assert!(program.blocks[1].stmts.is_empty());
+        assert_eq!(text(program.blocks[2].span), "{}");
+
+        let comments: Vec<&str> = program.comments.iter().map(|s| text(*s)).collect();
+        assert_eq!(comments, ["// head", "// tail"]);
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        let input = "let x: int = 1;\nlet f = fn(a: string, b: [int], c) -> {string: fn(int, bool) -> any} { a };\nfn(a) { a };";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmts: Vec<String> = program.stmts.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            stmts,
+            [
+                "let x: int = 1",
+                "let f = fn(a: string, b: [int], c) -> {string: fn(int, bool) -> any}{a}",
+                "fn(a){a}",
+            ]
+        );
+        match &program.stmts[2] {
+            rmonkey_ast::Stmt::ExprStmt(rmonkey_ast::Expr::Func { param_types, .. }) => {
+                assert!(param_types.is_empty())
+            }
+            stmt => panic!("unexpected statement: {stmt:?}"),
+        }
+
+        for (input, err) in [
+            ("let x: foo = 1;", "custom error: unknown type: foo"),
+            (
+                "fn(a:) { a }",
+                "unexpected token error: expected type, but got )",
+            ),
+            (
+                "let f: fn(int) = 1;",
+                "unexpected token error: expected ->, but got =",
+            ),
+        ] {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err().to_string(), err);
+        }
+    }
+
+    #[test]
+    fn test_ident_spans() {
+        let input = "let add = fn(a: int, b) -> int { a + c };";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let idents: Vec<&str> = program
+            .idents
+            .iter()
+            .map(|s| &input[s.start..s.end])
+            .collect();
+        assert_eq!(idents, ["add", "a", "b", "a", "c"]);
+    }
+
+    #[test]
+    fn test_trailing_comma() {
+        let input = r#"
+        [1, 2,]
+        add(a, b,)
+        fn(x, y,) { x }
+        {"one": 1,}
+        "#;
+        let expected = ["[1, 2]", "add(a, b)", "fn(x, y){x}", r#"{"one": 1}"#];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), expected.len());
+        for (i, p) in program.stmts.iter().enumerate() {
+            assert_eq!(p.to_string(), expected[i]);
+        }
+    }
+
+    #[test]
+    fn test_modules() {
+        let input = r#"import "lib/math.monkey" as math;
+        export let quad = fn(x) { math["double"](math["double"](x)) };
+        "#;
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmts: Vec<String> = program.stmts.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            stmts,
+            [
+                r#"import "lib/math.monkey" as math"#,
+                r#"export let quad = fn(x){(math["double"])((math["double"])(x))}"#,
+            ]
+        );
+
+        for (input, err) in [
+            (
+                "fn() { import \"a\" as a; }",
+                "custom error: `import` is only allowed at the top level",
+            ),
+            (
+                "if (true) { export let a = 1; }",
+                "custom error: `export` is only allowed at the top level",
+            ),
+            (
+                "import a as a;",
+                "unexpected token error: expected path, but got a",
+            ),
+            (
+                "import \"a\" a;",
+                "unexpected token error: expected as, but got a",
+            ),
+            (
+                "export fn() {};",
+                "unexpected token error: expected let, but got fn",
+            ),
+        ] {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err().to_string(), err, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let input = r#"
+        throw "boom";
+        let x = try { f() } catch (e) { e };
+        try { 1 } finally { 2 }
+        try { 1 } catch (err) { 2 } finally { 3 }
+        "#;
+        let expected = [
+            r#"throw "boom""#,
+            "let x = try{f()}catch(e){e}",
+            "try{1}finally{2}",
+            "try{1}catch(err){2}finally{3}",
+        ];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmts: Vec<String> = program.stmts.iter().map(|s| s.to_string()).collect();
+        assert_eq!(stmts, expected);
+        assert_eq!(program.blocks.len(), 7);
+
+        for (input, err) in [
+            (
+                "try { 1 }",
+                "unexpected token error: expected finally, but got Eof",
+            ),
+            (
+                "try { 1 } catch { 2 }",
+                "unexpected token error: expected (, but got {",
+            ),
+            (
+                "try { 1 } catch (1) { 2 }",
+                "unexpected token error: expected Ident, but got 1",
+            ),
+            ("try 1", "unexpected token error: expected {, but got 1"),
+        ] {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err().to_string(), err, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_propagate() {
+        let case = [
+            ("f(x)?", "(f(x)?)"),
+            ("a? + b?", "((a?) + (b?))"),
+            ("-x?", "(-(x?))"),
+            (r#"m["k"]?"#, r#"((m["k"])?)"#),
+            ("f()?(1)", "(f()?)(1)"),
+            ("let y = x?;", "let y = (x?)"),
+        ];
+        for (input, expected) in case.iter() {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program().unwrap();
+            assert_eq!(program.stmts[0].to_string(), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_match() {
+        let input = r#"
+        match (x) { 0 => a, -1 => b, "s" => c, true => d, _ => e }
+        match (xs) { [] => 0, [_] => 1, [first, ...rest,] => first }
+        match (h) { {"type": "x", "v": v} => v, n if n > 10 => n, }
+        match (x) {}
+        "#;
+        let expected = [
+            r#"match(x){0 => a, -1 => b, "s" => c, true => d, _ => e}"#,
+            "match(xs){[] => 0, [_] => 1, [first, ...rest] => first}",
+            r#"match(h){{"type": "x", "v": v} => v, n if (n > 10) => n}"#,
+            "match(x){}",
+        ];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmts: Vec<String> = program.stmts.iter().map(|s| s.to_string()).collect();
+        assert_eq!(stmts, expected);
+        // every binding of a pattern is an identifier
+        assert_eq!(program.idents.len(), 17);
+        // match and hash pattern braces are not blocks
+        assert!(program.blocks.is_empty());
+
+        for (input, err) in [
+            (
+                "match (x) { _ => 1, 2 => 2 }",
+                "custom error: unreachable match arm: `2` is always matched by `_` before",
+            ),
+            (
+                "match (x) { [a, ...r] => 1, [1, 2] => 2 }",
+                "custom error: unreachable match arm: `[1, 2]` is always matched by `[a, ...r]` before",
+            ),
+            (
+                r#"match (x) { 1 => 1, 1 => 2 }"#,
+                "custom error: unreachable match arm: `1` is always matched by `1` before",
+            ),
+            (
+                "match (x) { [...[a]] => 1 }",
+                "custom error: the rest of an array pattern must be a name or `_`, got `[a]`",
+            ),
+            (
+                "match (x) { {k: 1} => 1 }",
+                "custom error: a hash pattern key must be a literal, got k",
+            ),
+            ("match (x) { f() => 1 }", "unexpected token error: expected =>, but got ("),
+            ("match (x) { 1 => 1 2 => 2 }", "unexpected token error: expected ,, but got 2"),
+            ("match (x) { (1) => 1 }", "custom error: expected a pattern, but got ("),
+        ] {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err().to_string(), err, "{input}");
+        }
+        // a guarded arm leaves the next one reachable
+        let l = Lexer::new("match (x) { n if n > 1 => 1, n => 2 }");
+        assert!(Parser::new(l).parse_program().is_ok());
+    }
+
+    #[test]
+    fn test_destructuring() {
+        let input = r#"
+        let [a, b, ...rest] = arr;
+        let {"x": x, "y": y} = point;
+        let _ = f();
+        let f = fn([a, _], {"k": v}, c) { a };
+        "#;
+        let expected = [
+            "let [a, b, ...rest] = arr",
+            r#"let {"x": x, "y": y} = point"#,
+            "let _ = f()",
+            r#"let f = fn([a, _], {"k": v}, c){a}"#,
+        ];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmts: Vec<String> = program.stmts.iter().map(|s| s.to_string()).collect();
+        assert_eq!(stmts, expected);
+        assert_eq!(program.idents.len(), 13);
+
+        for (input, err) in [
+            (
+                "let 1 = x;",
+                "unexpected token error: expected Ident, but got 1",
+            ),
+            (
+                "let [(a)] = x;",
+                "custom error: expected a pattern, but got (",
+            ),
+            (
+                "fn(a, 1) { a }",
+                "unexpected token error: expected Ident, but got 1",
+            ),
+        ] {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err().to_string(), err, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_macro_literal() {
+        let input = "let m = macro(x, y) { x + y; }; macro() { quote(1) }";
+        let expected = ["let m = macro(x, y){(x + y)}", "macro(){quote(1)}"];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let stmts: Vec<String> = program.stmts.iter().map(|s| s.to_string()).collect();
+        assert_eq!(stmts, expected);
+        assert_eq!(program.idents.len(), 6);
+        assert_eq!(program.blocks.len(), 2);
+
+        for (input, err) in [
+            (
+                "macro([a]) { a }",
+                "unexpected token error: expected Ident, but got [",
+            ),
+            (
+                "macro(a b) { a }",
+                "unexpected token error: expected ,, but got b",
+            ),
+            (
+                "macro(a) a",
+                "unexpected token error: expected {, but got a",
+            ),
+        ] {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            assert_eq!(p.parse_program().unwrap_err().to_string(), err, "{input}");
+        }
+    }
 }
@@ -0,0 +1,891 @@
+//! Static name resolution.
+//!
+//! [`resolve`] builds the scope tree of a program, binds every identifier to
+//! the `let` or parameter it refers to, and reports names that would fail or
+//! look suspicious at run time, without running anything.
+//!
+//! The AST has no positions below statement level, so identifiers are
+//! addressed by their index among all `Expr::Ident`s in source order. The
+//! parser creates exactly one `Expr::Ident` per identifier token, so the
+//! index is also the index of the token.
+
+use std::{collections::HashSet, fmt};
+
+use rmonkey_ast::{
+    operator::{Infix, Prefix},
+    pattern::Pattern,
+    Expr, MatchArm, Program, Stmt,
+};
+use rmonkey_object::builtin::builtins;
+
+/// names the evaluator handles itself rather than looking them up
+const SPECIAL_FORMS: [&str; 2] = ["quote", "unquote"];
+
+pub type ScopeId = usize;
+pub type BindingId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Global,
+    Function,
+    /// the body of a `catch`, which holds its parameter
+    Catch,
+    /// the guard and body of a `match` arm, which hold the names of its
+    /// pattern
+    Arm,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    /// index of the `{` of the function or `catch` body, counted among every
+    /// `{` of blocks and hash literals in source order. `None` for the global
+    /// scope.
+    pub brace: Option<usize>,
+    /// bindings of this scope. the position of a binding is its slot.
+    pub bindings: Vec<BindingId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Let,
+    Param,
+    /// the alias of an `import`
+    Import,
+    /// the parameter of a `catch`
+    Catch,
+    /// a name bound by the pattern of a `match` arm
+    Pattern,
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: String,
+    pub kind: BindingKind,
+    /// index of the identifier the name is bound at
+    pub ident: usize,
+    pub scope: ScopeId,
+    /// position among the bindings of its scope
+    pub slot: usize,
+    /// index of the statement in `Program::stmts`, for top-level `let`s and
+    /// `import`s
+    pub stmt: Option<usize>,
+    /// the top-level `let` whose value this binding is nested in
+    pub container: Option<BindingId>,
+    /// what the bound value is, when it can be told without running
+    pub value_kind: Option<&'static str>,
+    /// parameter names when the value is a function literal
+    pub params: Option<Vec<String>>,
+    /// number of identifiers before the point the binding becomes visible
+    pub visible_from: usize,
+    /// an `export let`, which other modules may use
+    pub export: bool,
+}
+
+impl Binding {
+    /// one-line description of the binding
+    pub fn detail(&self) -> String {
+        match (self.kind, &self.params, self.value_kind) {
+            (BindingKind::Param, ..) => format!("{} (parameter)", self.name),
+            (BindingKind::Import, ..) => format!("{} (module)", self.name),
+            (BindingKind::Catch, ..) => format!("{} (exception)", self.name),
+            (BindingKind::Pattern, ..) => format!("{} (pattern)", self.name),
+            (BindingKind::Let, Some(params), _) => {
+                format!("let {} = fn({})", self.name, params.join(", "))
+            }
+            (BindingKind::Let, None, Some(kind)) => format!("let {}: {kind}", self.name),
+            (BindingKind::Let, None, None) => format!("let {}", self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Binding(BindingId),
+    Builtin,
+    Undefined,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub ident: usize,
+    pub scope: ScopeId,
+    pub target: Target,
+    /// number of scopes between the reference and its binding. together
+    /// with the binding's slot it locates the value without a name lookup.
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// an identifier that is neither bound nor a builtin
+    UndefinedName,
+    /// a `let` that is never referenced. names starting with `_` are exempt.
+    UnusedBinding,
+    /// a `let` in a function body that rebinds one of its parameters
+    ShadowedParam,
+    /// a parameter name that appears twice in the same parameter list
+    DuplicateParam,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub name: String,
+    /// index of the offending identifier
+    pub ident: usize,
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            DiagnosticKind::UndefinedName | DiagnosticKind::DuplicateParam => Severity::Error,
+            DiagnosticKind::UnusedBinding | DiagnosticKind::ShadowedParam => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = &self.name;
+        match self.kind {
+            DiagnosticKind::UndefinedName => write!(f, "identifier not found: {name}"),
+            DiagnosticKind::UnusedBinding => write!(f, "unused binding: {name}"),
+            DiagnosticKind::ShadowedParam => write!(f, "`let {name}` shadows the parameter {name}"),
+            DiagnosticKind::DuplicateParam => write!(f, "duplicate parameter: {name}"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Resolution {
+    /// scope 0 is the global scope
+    pub scopes: Vec<Scope>,
+    pub bindings: Vec<Binding>,
+    pub references: Vec<Reference>,
+    /// sorted by identifier
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub fn resolve(program: &Program) -> Resolution {
+    let mut resolver = Resolver {
+        resolution: Resolution::default(),
+        idents: 0,
+        braces: 0,
+        scope: 0,
+        container: None,
+    };
+    resolver.resolution.scopes.push(Scope {
+        kind: ScopeKind::Global,
+        parent: None,
+        brace: None,
+        bindings: Vec::new(),
+    });
+    for (i, stmt) in program.stmts.iter().enumerate() {
+        match stmt {
+            Stmt::LetStmt {
+                export,
+                name,
+                value,
+                ..
+            } => {
+                for id in resolver.resolve_let(name, value) {
+                    resolver.resolution.bindings[id].stmt = Some(i);
+                    resolver.resolution.bindings[id].export = *export;
+                }
+            }
+            Stmt::Import { alias, .. } => {
+                let ident = resolver.next_ident();
+                resolver.bind(Binding {
+                    name: alias.clone(),
+                    kind: BindingKind::Import,
+                    ident,
+                    scope: 0,
+                    slot: resolver.resolution.scopes[0].bindings.len(),
+                    stmt: Some(i),
+                    container: None,
+                    value_kind: Some("MODULE"),
+                    params: None,
+                    visible_from: ident + 1,
+                    export: false,
+                });
+            }
+            stmt => resolver.resolve_stmt(stmt),
+        }
+    }
+
+    let mut resolution = resolver.resolution;
+    resolution.link();
+    resolution.check_unused();
+    resolution.diagnostics.sort_by_key(|d| d.ident);
+    resolution
+}
+
+impl Resolution {
+    /// the binding declared or referenced at identifier `ident`
+    pub fn binding_at(&self, ident: usize) -> Option<BindingId> {
+        if let Some(id) = self.bindings.iter().position(|b| b.ident == ident) {
+            return Some(id);
+        }
+        match self.reference(ident)?.target {
+            Target::Binding(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// the reference at identifier `ident`
+    pub fn reference(&self, ident: usize) -> Option<&Reference> {
+        self.references.iter().find(|r| r.ident == ident)
+    }
+
+    pub fn references_to(&self, binding: BindingId) -> impl Iterator<Item = &Reference> {
+        self.references
+            .iter()
+            .filter(move |r| r.target == Target::Binding(binding))
+    }
+
+    /// bindings visible in `scope` after `order` identifiers, innermost
+    /// first, without shadowed names
+    pub fn visible(&self, mut scope: ScopeId, order: usize) -> Vec<&Binding> {
+        let mut seen = HashSet::new();
+        let mut visible = Vec::new();
+        let mut innermost = true;
+        loop {
+            for &id in self.scopes[scope].bindings.iter().rev() {
+                let binding = &self.bindings[id];
+                let declared = !innermost || binding.visible_from <= order;
+                if declared && seen.insert(binding.name.as_str()) {
+                    visible.push(binding);
+                }
+            }
+            match self.scopes[scope].parent {
+                Some(parent) => scope = parent,
+                None => return visible,
+            }
+            innermost = false;
+        }
+    }
+
+    fn link(&mut self) {
+        let builtins = builtins();
+        for i in 0..self.references.len() {
+            let (target, depth) = match self.lookup(&self.references[i]) {
+                Some((id, depth)) => (Target::Binding(id), depth),
+                None if builtins.contains_key(self.references[i].name.as_str())
+                    || SPECIAL_FORMS.contains(&self.references[i].name.as_str()) =>
+                {
+                    (Target::Builtin, 0)
+                }
+                None => {
+                    let reference = &self.references[i];
+                    self.diagnostics.push(Diagnostic {
+                        kind: DiagnosticKind::UndefinedName,
+                        name: reference.name.clone(),
+                        ident: reference.ident,
+                    });
+                    (Target::Undefined, 0)
+                }
+            };
+            self.references[i].target = target;
+            self.references[i].depth = depth;
+        }
+    }
+
+    /// only bindings made before the reference count. function bodies run
+    /// later, so in the scopes enclosing a function a binding made after it,
+    /// e.g. the function's own name, counts as well.
+    fn lookup(&self, reference: &Reference) -> Option<(BindingId, usize)> {
+        let mut scope = Some(reference.scope);
+        let mut depth = 0;
+        let mut in_function = false;
+        while let Some(id) = scope {
+            let candidates: Vec<BindingId> = self.scopes[id]
+                .bindings
+                .iter()
+                .copied()
+                .filter(|&b| self.bindings[b].name == reference.name)
+                .collect();
+            let before = candidates
+                .iter()
+                .rev()
+                .find(|&&b| self.bindings[b].visible_from <= reference.ident);
+            let found = match before {
+                Some(&b) => Some(b),
+                None if in_function => candidates.first().copied(),
+                None => None,
+            };
+            if let Some(b) = found {
+                return Some((b, depth));
+            }
+            in_function |= self.scopes[id].kind == ScopeKind::Function;
+            scope = self.scopes[id].parent;
+            depth += 1;
+        }
+        None
+    }
+
+    fn check_unused(&mut self) {
+        let used: HashSet<BindingId> = self
+            .references
+            .iter()
+            .filter_map(|r| match r.target {
+                Target::Binding(id) => Some(id),
+                _ => None,
+            })
+            .collect();
+        for (id, binding) in self.bindings.iter().enumerate() {
+            if !matches!(binding.kind, BindingKind::Param | BindingKind::Catch)
+                && !binding.export
+                && !binding.name.starts_with('_')
+                && !used.contains(&id)
+            {
+                self.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::UnusedBinding,
+                    name: binding.name.clone(),
+                    ident: binding.ident,
+                });
+            }
+        }
+    }
+}
+
+struct Resolver {
+    resolution: Resolution,
+    /// number of identifiers walked so far
+    idents: usize,
+    /// number of `{` walked so far
+    braces: usize,
+    scope: ScopeId,
+    container: Option<BindingId>,
+}
+
+impl Resolver {
+    fn next_ident(&mut self) -> usize {
+        self.idents += 1;
+        self.idents - 1
+    }
+
+    fn next_brace(&mut self) -> usize {
+        self.braces += 1;
+        self.braces - 1
+    }
+
+    fn bind(&mut self, binding: Binding) -> BindingId {
+        let id = self.resolution.bindings.len();
+        self.resolution.scopes[binding.scope].bindings.push(id);
+        self.resolution.bindings.push(binding);
+        id
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::LetStmt { name, value, .. } => {
+                self.resolve_let(name, value);
+            }
+            Stmt::ReturnStmt(expr) | Stmt::ExprStmt(expr) | Stmt::Throw(expr) => {
+                self.resolve_expr(expr)
+            }
+            Stmt::BlockStmt { .. } => {
+                self.resolve_block(stmt);
+            }
+            // the parser only accepts `import` at the top level
+            Stmt::Import { .. } => {}
+        }
+    }
+
+    /// bind the names of a `let`, which may destructure its value
+    fn resolve_let(&mut self, name: &Pattern, value: &Expr) -> Vec<BindingId> {
+        let mut ids = Vec::new();
+        for name in name.names() {
+            let name = name.to_string();
+            let ident = self.next_ident();
+            let scope = &self.resolution.scopes[self.scope];
+            let shadows_param = scope.bindings.iter().any(|&b| {
+                let binding = &self.resolution.bindings[b];
+                binding.kind == BindingKind::Param && binding.name == name
+            });
+            if shadows_param {
+                self.resolution.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::ShadowedParam,
+                    name: name.clone(),
+                    ident,
+                });
+            }
+            ids.push(self.bind(Binding {
+                name,
+                kind: BindingKind::Let,
+                ident,
+                scope: self.scope,
+                slot: self.resolution.scopes[self.scope].bindings.len(),
+                stmt: None,
+                container: self.container,
+                value_kind: None,
+                params: None,
+                // the name is bound once the value is evaluated
+                visible_from: usize::MAX,
+                export: false,
+            }));
+        }
+        // what the value is only tells about a plain name
+        if name.as_ident().is_some() {
+            let binding = &mut self.resolution.bindings[ids[0]];
+            binding.value_kind = value_kind(value);
+            binding.params = match value {
+                Expr::Func { params, .. } => {
+                    Some(params.iter().map(|param| param.to_string()).collect())
+                }
+                _ => None,
+            };
+        }
+        let container = self.container;
+        if self.scope == 0 {
+            if let Some(&id) = ids.first() {
+                self.container = Some(id);
+            }
+        }
+        self.resolve_expr(value);
+        self.container = container;
+        for &id in ids.iter() {
+            self.resolution.bindings[id].visible_from = self.idents;
+        }
+        ids
+    }
+
+    fn resolve_block(&mut self, block: &Stmt) -> usize {
+        let brace = self.next_brace();
+        if let Stmt::BlockStmt { stmts } = block {
+            for stmt in stmts.iter() {
+                self.resolve_stmt(stmt);
+            }
+        }
+        brace
+    }
+
+    fn resolve_func(&mut self, params: &[Pattern], body: &Stmt) {
+        let parent = self.scope;
+        self.scope = self.resolution.scopes.len();
+        self.resolution.scopes.push(Scope {
+            kind: ScopeKind::Function,
+            parent: Some(parent),
+            brace: None,
+            bindings: Vec::new(),
+        });
+
+        for name in params.iter().flat_map(|param| param.names()) {
+            let name = name.to_string();
+            let ident = self.next_ident();
+            let scope = &self.resolution.scopes[self.scope];
+            if scope
+                .bindings
+                .iter()
+                .any(|&b| self.resolution.bindings[b].name == name)
+            {
+                self.resolution.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::DuplicateParam,
+                    name: name.clone(),
+                    ident,
+                });
+            }
+            self.bind(Binding {
+                name,
+                kind: BindingKind::Param,
+                ident,
+                scope: self.scope,
+                slot: scope.bindings.len(),
+                stmt: None,
+                container: self.container,
+                value_kind: None,
+                params: None,
+                visible_from: self.idents,
+                export: false,
+            });
+        }
+
+        let brace = self.resolve_block(body);
+        self.resolution.scopes[self.scope].brace = Some(brace);
+        self.scope = parent;
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Ident(name) => {
+                let ident = self.next_ident();
+                self.resolution.references.push(Reference {
+                    name: name.to_string(),
+                    ident,
+                    scope: self.scope,
+                    target: Target::Undefined,
+                    depth: 0,
+                });
+            }
+            Expr::IntLiteral(_) | Expr::BoolLiteral(_) | Expr::StringLiteral(_) => {}
+            Expr::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_block(consequence);
+                if let Some(alt) = alternative {
+                    self.resolve_block(alt);
+                }
+            }
+            Expr::PrefixExpr { right, .. } | Expr::Propagate { value: right } => {
+                self.resolve_expr(right)
+            }
+            Expr::InfixExpr { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Func { params, body, .. } => self.resolve_func(params, body),
+            Expr::Call { callee, args } => {
+                self.resolve_expr(callee);
+                args.iter().for_each(|arg| self.resolve_expr(arg));
+            }
+            Expr::Array { elements } => elements.iter().for_each(|e| self.resolve_expr(e)),
+            Expr::IndexExpr { left, index } => {
+                self.resolve_expr(left);
+                self.resolve_expr(index);
+            }
+            Expr::Slice {
+                left,
+                start,
+                end,
+                step,
+            } => {
+                self.resolve_expr(left);
+                for bound in [start, end, step].into_iter().flatten() {
+                    self.resolve_expr(bound);
+                }
+            }
+            Expr::HashLiteral { pairs } => {
+                self.next_brace();
+                for (key, value) in pairs.iter() {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            Expr::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.resolve_block(body);
+                if let Some(catch) = catch {
+                    self.resolve_catch(&catch.param, &catch.body);
+                }
+                if let Some(finally) = finally {
+                    self.resolve_block(finally);
+                }
+            }
+            Expr::Match { value, arms } => {
+                self.resolve_expr(value);
+                for arm in arms.iter() {
+                    self.resolve_arm(arm);
+                }
+            }
+            // the parameters of a macro are bound to code, which is still
+            // a value while the macro runs
+            Expr::Macro { params, body } => {
+                let params: Vec<Pattern> = params.iter().cloned().map(Pattern::Ident).collect();
+                self.resolve_func(&params, body);
+            }
+        }
+    }
+
+    /// the names of the pattern are bound in a scope of its own, which the
+    /// guard and the body share
+    fn resolve_arm(&mut self, arm: &MatchArm) {
+        let parent = self.scope;
+        self.scope = self.resolution.scopes.len();
+        self.resolution.scopes.push(Scope {
+            kind: ScopeKind::Arm,
+            parent: Some(parent),
+            brace: None,
+            bindings: Vec::new(),
+        });
+        let names = arm.pattern.names();
+        // visible once the whole pattern matched
+        let visible_from = self.idents + names.len();
+        for name in names {
+            let ident = self.next_ident();
+            self.bind(Binding {
+                name: name.to_string(),
+                kind: BindingKind::Pattern,
+                ident,
+                scope: self.scope,
+                slot: self.resolution.scopes[self.scope].bindings.len(),
+                stmt: None,
+                container: self.container,
+                value_kind: None,
+                params: None,
+                visible_from,
+                export: false,
+            });
+        }
+        if let Some(guard) = &arm.guard {
+            self.resolve_expr(guard);
+        }
+        self.resolve_expr(&arm.body);
+        self.scope = parent;
+    }
+
+    /// the parameter is bound in a scope of its own, which the body shares
+    fn resolve_catch(&mut self, param: &str, body: &Stmt) {
+        let parent = self.scope;
+        self.scope = self.resolution.scopes.len();
+        self.resolution.scopes.push(Scope {
+            kind: ScopeKind::Catch,
+            parent: Some(parent),
+            brace: None,
+            bindings: Vec::new(),
+        });
+        let ident = self.next_ident();
+        self.bind(Binding {
+            name: param.to_string(),
+            kind: BindingKind::Catch,
+            ident,
+            scope: self.scope,
+            slot: 0,
+            stmt: None,
+            container: self.container,
+            value_kind: None,
+            params: None,
+            visible_from: self.idents,
+            export: false,
+        });
+        let brace = self.resolve_block(body);
+        self.resolution.scopes[self.scope].brace = Some(brace);
+        self.scope = parent;
+    }
+}
+
+/// the type of `expr`, when it is evident from the literal or operator
+fn value_kind(expr: &Expr) -> Option<&'static str> {
+    match expr {
+        Expr::IntLiteral(_) => Some("integer"),
+        Expr::BoolLiteral(_) => Some("boolean"),
+        Expr::StringLiteral(_) => Some("string"),
+        Expr::Func { .. } => Some("function"),
+        Expr::Macro { .. } => Some("macro"),
+        Expr::Array { .. } => Some("array"),
+        Expr::HashLiteral { .. } => Some("hash"),
+        Expr::PrefixExpr { op, right } => match op {
+            Prefix::Bang => Some("boolean"),
+            Prefix::Minus => value_kind(right).filter(|&kind| kind == "integer"),
+        },
+        Expr::InfixExpr { left, right, op } => match op {
+            Infix::Eq | Infix::NotEq | Infix::Lt | Infix::Gt => Some("boolean"),
+            _ => match (value_kind(left), value_kind(right)) {
+                (Some("integer"), Some("integer")) => Some("integer"),
+                (Some("string"), Some("string")) if *op == Infix::Plus => Some("string"),
+                _ => None,
+            },
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rmonkey_lexer::Lexer;
+    use rmonkey_parser::Parser;
+
+    use super::*;
+
+    fn resolve_code(input: &str) -> Resolution {
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        resolve(&p.parse_program().unwrap())
+    }
+
+    fn messages(resolution: &Resolution) -> Vec<String> {
+        resolution
+            .diagnostics
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_undefined_name() {
+        let case = [
+            ("let a = 1; a + b;", vec!["identifier not found: b"]),
+            (
+                "let m = macro(a) { quote(unquote(a) + b) }; m(1);",
+                vec!["identifier not found: b"],
+            ),
+            ("len([1]); puts(x);", vec!["identifier not found: x"]),
+            (
+                "let f = fn() { g(); let g = 1; }; f();",
+                vec!["identifier not found: g", "unused binding: g"],
+            ),
+            (
+                "let a = a;",
+                vec!["unused binding: a", "identifier not found: a"],
+            ),
+            (
+                "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) } }; fib(3);",
+                vec![],
+            ),
+            ("let f = fn() { later }; let later = 1; f();", vec![]),
+            ("if (true) { let x = 1; } x;", vec![]),
+            ("m; import \"m\" as m; m;", vec!["identifier not found: m"]),
+            (
+                "try { throw 1; } catch (e) { e }; e;",
+                vec!["identifier not found: e"],
+            ),
+            (
+                "try { throw 1; } catch (e) { f() }; let f = fn() { 1 }; f();",
+                vec!["identifier not found: f"],
+            ),
+            (
+                "try { e } catch (e) { 1 };",
+                vec!["identifier not found: e"],
+            ),
+            (
+                "match (1) { n if n > 0 => n, _ => 0 }; n;",
+                vec!["identifier not found: n"],
+            ),
+            (
+                "match (1) { n if n > 0 => n, _ => n };",
+                vec!["identifier not found: n"],
+            ),
+            (
+                "match ([1]) { [a, ...r] => r + b };",
+                vec!["unused binding: a", "identifier not found: b"],
+            ),
+        ];
+        for (input, expected) in case.into_iter() {
+            assert_eq!(messages(&resolve_code(input)), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_unused_binding() {
+        let case = [
+            ("let a = 1;", vec!["unused binding: a"]),
+            ("let _a = 1;", vec![]),
+            ("let a = 1; let a = a;", vec!["unused binding: a"]),
+            (
+                "let f = fn(x) { let y = 1; x };",
+                vec!["unused binding: f", "unused binding: y"],
+            ),
+            // other modules may use exports
+            ("export let a = 1;", vec![]),
+            ("import \"m\" as m;", vec!["unused binding: m"]),
+            // a `catch` may ignore what was thrown
+            ("try { 1 } catch (e) { 2 };", vec![]),
+            ("match (1) { x => 2, };", vec!["unused binding: x"]),
+            ("match (1) { _x => 2, };", vec![]),
+            ("let [a, _b, ...c] = [1]; c;", vec!["unused binding: a"]),
+            ("let {\"k\": v} = {}; let _ = 1;", vec!["unused binding: v"]),
+        ];
+        for (input, expected) in case.into_iter() {
+            assert_eq!(messages(&resolve_code(input)), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_params() {
+        let case = [
+            (
+                "let f = fn(x, y, x) { x + y }; f;",
+                vec!["duplicate parameter: x"],
+            ),
+            (
+                "let f = fn(x) { let x = x + 1; x }; f;",
+                vec!["`let x` shadows the parameter x"],
+            ),
+            ("let x = 1; let f = fn(x) { x }; f(x);", vec![]),
+            (
+                "let f = fn([x, y], {\"k\": x}) { let [y] = [x]; y }; f;",
+                vec!["duplicate parameter: x", "`let y` shadows the parameter y"],
+            ),
+        ];
+        for (input, expected) in case.into_iter() {
+            assert_eq!(messages(&resolve_code(input)), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_severity() {
+        let resolution = resolve_code("let a = 1; b;");
+        let severities: Vec<Severity> = resolution
+            .diagnostics
+            .iter()
+            .map(|d| d.severity())
+            .collect();
+        assert_eq!(severities, [Severity::Warning, Severity::Error]);
+    }
+
+    #[test]
+    fn test_slots() {
+        let input = "let a = 1; let f = fn(x, y) { let z = a; x + y + z }; f(a, a);";
+        let resolution = resolve_code(input);
+        assert!(resolution.diagnostics.is_empty());
+        assert_eq!(resolution.scopes.len(), 2);
+        assert_eq!(resolution.scopes[1].brace, Some(0));
+
+        let slot_of = |ident: usize| {
+            let reference = resolution.reference(ident).unwrap();
+            let Target::Binding(id) = reference.target else {
+                panic!("{ident} is not bound");
+            };
+            (reference.depth, resolution.bindings[id].slot)
+        };
+        // `a` in the body is one scope up, slot 0 of the global scope
+        assert_eq!(slot_of(5), (1, 0));
+        // `x`, `y` and `z` are slots 0, 1 and 2 of the function scope
+        assert_eq!(slot_of(6), (0, 0));
+        assert_eq!(slot_of(7), (0, 1));
+        assert_eq!(slot_of(8), (0, 2));
+        assert_eq!(slot_of(9), (0, 1));
+    }
+
+    #[test]
+    fn test_visible() {
+        let resolution = resolve_code("let a = 1; let f = fn(x) { let b = 2; b }; f(a);");
+        let names = |scope, order| -> Vec<&str> {
+            resolution
+                .visible(scope, order)
+                .iter()
+                .map(|b| b.name.as_str())
+                .collect()
+        };
+        assert_eq!(names(0, 0), Vec::<&str>::new());
+        assert_eq!(names(1, 3), ["x", "f", "a"]);
+        assert_eq!(names(1, 4), ["b", "x", "f", "a"]);
+        assert_eq!(names(0, 8), ["f", "a"]);
+    }
+
+    #[test]
+    fn test_binding_detail() {
+        let input =
+            "let add = fn(a, b) { a + b }; let s = \"a\" + \"b\"; let n = -1; let r = add(1, 2); import \"m\" as m; try { 1 } catch (e) { e }; match (1) { [v] => v };";
+        let resolution = resolve_code(input);
+        let details: Vec<String> = resolution.bindings.iter().map(|b| b.detail()).collect();
+        assert_eq!(
+            details,
+            [
+                "let add = fn(a, b)",
+                "a (parameter)",
+                "b (parameter)",
+                "let s: string",
+                "let n: integer",
+                "let r",
+                "m (module)",
+                "e (exception)",
+                "v (pattern)",
+            ]
+        );
+    }
+}
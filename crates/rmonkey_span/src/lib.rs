@@ -0,0 +1,16 @@
+//! Positions in the source text, shared by the lexer and the AST.
+
+use serde::{Deserialize, Serialize};
+
+/// byte range `[start, end)` of a node in the source text
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
@@ -35,6 +35,23 @@ pub enum Token {
     Eq,
     NotEq,
     Colon,
+    /// `->` before the return type of a function
+    Arrow,
+    /// postfix `?`, which returns an error value early
+    Question,
+    Import,
+    Export,
+    As,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+    Match,
+    /// `=>` between the pattern and the value of a `match` arm
+    FatArrow,
+    /// `...` before the rest of an array pattern
+    Ellipsis,
+    Macro,
 }
 
 impl Token {
@@ -49,7 +66,7 @@ impl Token {
             Token::Slash => Precedence::Product,
             Token::Asterisk => Precedence::Product,
             Token::LParen => Precedence::Call,
-            Token::LBracket => Precedence::Index,
+            Token::LBracket | Token::Question => Precedence::Index,
             _ => Precedence::Lowest,
         }
     }
@@ -87,6 +104,19 @@ impl Token {
             Token::NotEq => "NotEq",
             Token::String(_) => "STRING",
             Token::Colon => "Colon",
+            Token::Arrow => "Arrow",
+            Token::Question => "Question",
+            Token::Import => "Import",
+            Token::Export => "Export",
+            Token::As => "As",
+            Token::Throw => "Throw",
+            Token::Try => "Try",
+            Token::Catch => "Catch",
+            Token::Finally => "Finally",
+            Token::Match => "Match",
+            Token::FatArrow => "FatArrow",
+            Token::Ellipsis => "Ellipsis",
+            Token::Macro => "Macro",
         }
     }
 }
@@ -125,6 +155,19 @@ impl fmt::Display for Token {
             Token::Eq => write!(f, "=="),
             Token::NotEq => write!(f, "!="),
             Token::Colon => write!(f, ":"),
+            Token::Arrow => write!(f, "->"),
+            Token::Question => write!(f, "?"),
+            Token::Import => write!(f, "import"),
+            Token::Export => write!(f, "export"),
+            Token::As => write!(f, "as"),
+            Token::Throw => write!(f, "throw"),
+            Token::Try => write!(f, "try"),
+            Token::Catch => write!(f, "catch"),
+            Token::Finally => write!(f, "finally"),
+            Token::Match => write!(f, "match"),
+            Token::FatArrow => write!(f, "=>"),
+            Token::Ellipsis => write!(f, "..."),
+            Token::Macro => write!(f, "macro"),
         }
     }
 }
@@ -136,7 +179,16 @@ static KEYWORDS: phf::Map<&'static str, Token> = phf_map! {
     "false" => Token::False,
     "if" => Token::If,
     "else" => Token::Else,
-    "return" => Token::Return
+    "return" => Token::Return,
+    "import" => Token::Import,
+    "export" => Token::Export,
+    "as" => Token::As,
+    "throw" => Token::Throw,
+    "try" => Token::Try,
+    "catch" => Token::Catch,
+    "finally" => Token::Finally,
+    "match" => Token::Match,
+    "macro" => Token::Macro
 };
 
 pub fn look_up_ident(ident: &str) -> Option<Token> {
@@ -0,0 +1,717 @@
+use std::collections::HashMap;
+
+use rmonkey_ast::{
+    operator::{Infix, Prefix},
+    pattern::Pattern,
+    span::Span,
+    Expr, Program, Stmt,
+};
+
+use crate::{
+    types::{Scheme, Type, TypeVar},
+    TypeError, TypeErrorKind,
+};
+
+const BUILTINS: [&str; 6] = ["len", "first", "last", "rest", "push", "puts"];
+
+/// a check on a type that may still be a variable. variables left unsolved
+/// at the end of the program pass.
+#[derive(Debug, Clone, Copy)]
+enum Deferred {
+    /// operand of `+`, `<` or `>`, an integer or a string
+    Operand(&'static str),
+    /// argument of `len`
+    Len,
+}
+
+pub(crate) struct Checker<'a> {
+    program: &'a Program,
+    /// the type each variable is bound to, if any
+    subst: Vec<Option<Type>>,
+    /// variables bound since the start of the program, so that a failed
+    /// `try_unify` can unbind them
+    trail: Vec<TypeVar>,
+    /// one scope for the program, one per function and one per `catch`
+    /// and `match` arm
+    scopes: Vec<HashMap<String, Scheme>>,
+    /// return type of each enclosing function
+    returns: Vec<Type>,
+    deferred: Vec<(Deferred, Type, Span)>,
+    errors: Vec<TypeError>,
+    /// index of the next block in `program.blocks`
+    next_block: usize,
+    /// span of the innermost statement being checked
+    span: Span,
+}
+
+impl<'a> Checker<'a> {
+    pub(crate) fn new(program: &'a Program) -> Self {
+        Checker {
+            program,
+            subst: Vec::new(),
+            trail: Vec::new(),
+            scopes: vec![HashMap::new()],
+            returns: Vec::new(),
+            deferred: Vec::new(),
+            errors: Vec::new(),
+            next_block: 0,
+            span: Span::default(),
+        }
+    }
+
+    pub(crate) fn check(mut self) -> Vec<TypeError> {
+        for (i, stmt) in self.program.stmts.iter().enumerate() {
+            self.span = self.program.spans.get(i).copied().unwrap_or_default();
+            self.stmt(stmt);
+        }
+        for (deferred, ty, span) in std::mem::take(&mut self.deferred) {
+            let ty = self.resolve(&ty);
+            match (deferred, &ty) {
+                (_, Type::Var(_) | Type::Any) => {}
+                (Deferred::Operand(_), Type::Int | Type::String) => {}
+                (Deferred::Len, Type::String | Type::Array(_)) => {}
+                (Deferred::Operand(op), _) => {
+                    self.error_at(span, TypeErrorKind::Operand { op, ty })
+                }
+                (Deferred::Len, _) => self.error_at(
+                    span,
+                    TypeErrorKind::Builtin {
+                        name: "len",
+                        expected: "a string or an array",
+                        found: ty,
+                    },
+                ),
+            }
+        }
+        self.errors
+    }
+
+    fn error(&mut self, kind: TypeErrorKind) {
+        self.error_at(self.span, kind);
+    }
+
+    fn error_at(&mut self, span: Span, kind: TypeErrorKind) {
+        self.errors.push(TypeError { span, kind });
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.subst.push(None);
+        Type::Var(self.subst.len() - 1)
+    }
+
+    /// `ty` with every bound variable replaced by its type
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(v) => match &self.subst[*v] {
+                Some(bound) => self.resolve(bound),
+                None => ty.clone(),
+            },
+            Type::Array(element) => Type::Array(Box::new(self.resolve(element))),
+            Type::Hash(key, value) => {
+                Type::Hash(Box::new(self.resolve(key)), Box::new(self.resolve(value)))
+            }
+            Type::Func(params, ret) => Type::Func(
+                params.iter().map(|p| self.resolve(p)).collect(),
+                Box::new(self.resolve(ret)),
+            ),
+            Type::Int | Type::Bool | Type::String | Type::Null | Type::Any => ty.clone(),
+        }
+    }
+
+    /// `ty` with its outermost bound variables replaced
+    fn shallow(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(v) => match &self.subst[*v] {
+                Some(bound) => self.shallow(bound),
+                None => ty.clone(),
+            },
+            _ => ty.clone(),
+        }
+    }
+
+    fn occurs(&self, v: TypeVar, ty: &Type) -> bool {
+        self.resolve(ty).vars().contains(&v)
+    }
+
+    fn unify(&mut self, a: &Type, b: &Type) -> Result<(), ()> {
+        match (self.shallow(a), self.shallow(b)) {
+            (Type::Any, _) | (_, Type::Any) => Ok(()),
+            (Type::Var(a), Type::Var(b)) if a == b => Ok(()),
+            (Type::Var(v), ty) | (ty, Type::Var(v)) => {
+                if self.occurs(v, &ty) {
+                    return Err(());
+                }
+                self.subst[v] = Some(ty);
+                self.trail.push(v);
+                Ok(())
+            }
+            (Type::Array(a), Type::Array(b)) => self.unify(&a, &b),
+            (Type::Hash(ak, av), Type::Hash(bk, bv)) => {
+                self.unify(&ak, &bk)?;
+                self.unify(&av, &bv)
+            }
+            (Type::Func(ap, ar), Type::Func(bp, br)) if ap.len() == bp.len() => {
+                for (a, b) in ap.iter().zip(bp.iter()) {
+                    self.unify(a, b)?;
+                }
+                self.unify(&ar, &br)
+            }
+            (a, b) if a == b => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    /// unify `a` and `b`, leaving every variable as it was on failure
+    fn try_unify(&mut self, a: &Type, b: &Type) -> bool {
+        let mark = self.trail.len();
+        if self.unify(a, b).is_ok() {
+            return true;
+        }
+        for v in self.trail.drain(mark..) {
+            self.subst[v] = None;
+        }
+        false
+    }
+
+    /// report a mismatch unless `found` can be used as `expected`
+    fn expect(&mut self, expected: &Type, found: &Type) {
+        if !self.try_unify(expected, found) {
+            let (expected, found) = (self.resolve(expected), self.resolve(found));
+            self.error(TypeErrorKind::Mismatch { expected, found });
+        }
+    }
+
+    /// the common type of `a` and `b`, or `any` when they have none
+    fn join(&mut self, a: &Type, b: &Type) -> Type {
+        if self.try_unify(a, b) {
+            a.clone()
+        } else {
+            Type::Any
+        }
+    }
+
+    /// bind the names of `pattern` matched against a value of type `ty`.
+    /// a pattern that doesn't fit the type is not an error, the arm is
+    /// just never taken.
+    fn pattern(&mut self, pattern: &Pattern, ty: &Type) {
+        match pattern {
+            Pattern::Wildcard | Pattern::Literal(_) => {}
+            Pattern::Ident(name) => self.bind_name(name, Scheme::mono(ty.clone())),
+            Pattern::Array { elements, rest } => {
+                let (element, array) = match self.shallow(ty) {
+                    Type::Array(element) => (*element, ty.clone()),
+                    _ => (Type::Any, Type::Any),
+                };
+                for pattern in elements.iter() {
+                    self.pattern(pattern, &element);
+                }
+                if let Some(rest) = rest {
+                    self.pattern(rest, &array);
+                }
+            }
+            Pattern::Hash { pairs } => {
+                let value = match self.shallow(ty) {
+                    Type::Hash(_, value) => *value,
+                    _ => Type::Any,
+                };
+                for (_, pattern) in pairs.iter() {
+                    self.pattern(pattern, &value);
+                }
+            }
+        }
+    }
+
+    fn bind_name(&mut self, name: &str, scheme: Scheme) {
+        let scope = self.scopes.last_mut().expect("there is always a scope");
+        scope.insert(name.to_string(), scheme);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Scheme> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        let vars: HashMap<TypeVar, Type> = scheme.vars.iter().map(|&v| (v, self.fresh())).collect();
+        // the deferred checks on the scheme's variables apply to each use,
+        // and fail at the use
+        let mut copies = Vec::new();
+        for (deferred, ty, _) in self.deferred.iter() {
+            if let Type::Var(v) = self.resolve(ty) {
+                if let Some(var) = vars.get(&v) {
+                    copies.push((*deferred, var.clone(), self.span));
+                }
+            }
+        }
+        self.deferred.extend(copies);
+        substitute(&scheme.ty, &vars)
+    }
+
+    /// a scheme over the variables of `ty` that no binding in scope refers to
+    fn generalize(&self, ty: &Type) -> Scheme {
+        let ty = self.resolve(ty);
+        let mut in_scope = Vec::new();
+        for scheme in self.scopes.iter().flat_map(|scope| scope.values()) {
+            let vars = self.resolve(&scheme.ty).vars();
+            in_scope.extend(vars.into_iter().filter(|v| !scheme.vars.contains(v)));
+        }
+        for ret in self.returns.iter() {
+            in_scope.extend(self.resolve(ret).vars());
+        }
+        let vars = ty
+            .vars()
+            .into_iter()
+            .filter(|v| !in_scope.contains(v))
+            .collect();
+        Scheme { vars, ty }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Type {
+        match stmt {
+            Stmt::LetStmt {
+                name, ty, value, ..
+            } => {
+                let declared = ty.as_ref().map(Type::from);
+                let Some(name) = name.as_ident() else {
+                    let found = self.expr(value);
+                    let ty = match declared {
+                        Some(declared) => {
+                            self.expect(&declared, &found);
+                            declared
+                        }
+                        None => found,
+                    };
+                    self.pattern(name, &ty);
+                    return Type::Null;
+                };
+                let found = if let Expr::Func { .. } = value {
+                    // bound before the value for recursion
+                    let own = declared.clone().unwrap_or_else(|| self.fresh());
+                    self.bind_name(name, Scheme::mono(own.clone()));
+                    let found = self.expr(value);
+                    self.expect(&own, &found);
+                    found
+                } else {
+                    self.expr(value)
+                };
+                let scheme = match declared {
+                    Some(declared) => {
+                        self.expect(&declared, &found);
+                        Scheme::mono(declared)
+                    }
+                    // only function literals are generalized, since they are
+                    // the only values whose type variables are not shared
+                    // with some other value
+                    None if matches!(value, Expr::Func { .. }) => {
+                        self.scopes.last_mut().unwrap().remove(name);
+                        self.generalize(&found)
+                    }
+                    None => Scheme::mono(found),
+                };
+                self.bind_name(name, scheme);
+                Type::Null
+            }
+            Stmt::ReturnStmt(value) => {
+                let found = self.expr(value);
+                if let Some(ret) = self.returns.last().cloned() {
+                    self.expect(&ret, &found);
+                }
+                // control does not reach the end of the statement
+                self.fresh()
+            }
+            Stmt::ExprStmt(expr) => self.expr(expr),
+            Stmt::BlockStmt { stmts } => self.block(stmts),
+            Stmt::Throw(value) => {
+                self.expr(value);
+                // control does not reach the end of the statement
+                self.fresh()
+            }
+            // modules are not checked, so nothing is known of their exports
+            Stmt::Import { alias, .. } => {
+                self.bind_name(alias, Scheme::mono(Type::Any));
+                Type::Null
+            }
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Type {
+        let index = self.next_block;
+        self.next_block += 1;
+        let outer = self.span;
+        let mut ty = Type::Null;
+        let mut diverged = false;
+        for (i, stmt) in stmts.iter().enumerate() {
+            self.span = self
+                .program
+                .blocks
+                .get(index)
+                .and_then(|block| block.stmts.get(i))
+                .copied()
+                .unwrap_or(outer);
+            // the statements after a `return` or `throw` are still checked,
+            // but never give the block its value
+            let found = self.stmt(stmt);
+            if !diverged {
+                ty = found;
+            }
+            diverged |= matches!(stmt, Stmt::ReturnStmt(_) | Stmt::Throw(_));
+        }
+        self.span = outer;
+        ty
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Type {
+        match expr {
+            Expr::Ident(name) => match self.lookup(name).cloned() {
+                Some(scheme) => self.instantiate(&scheme),
+                // builtins as values, and undefined names, which are left to
+                // the resolver
+                None => Type::Any,
+            },
+            Expr::IntLiteral(_) => Type::Int,
+            Expr::BoolLiteral(_) => Type::Bool,
+            Expr::StringLiteral(_) => Type::String,
+            Expr::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.expr(condition);
+                let consequence = self.stmt(consequence);
+                match alternative {
+                    Some(alternative) => {
+                        let alternative = self.stmt(alternative);
+                        self.join(&consequence, &alternative)
+                    }
+                    None => Type::Any,
+                }
+            }
+            Expr::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                let mut ty = self.stmt(body);
+                if let Some(catch) = catch {
+                    // anything can be thrown
+                    self.scopes.push(HashMap::new());
+                    self.bind_name(&catch.param, Scheme::mono(Type::Any));
+                    let handler = self.stmt(&catch.body);
+                    self.scopes.pop();
+                    ty = self.join(&ty, &handler);
+                }
+                if let Some(finally) = finally {
+                    self.stmt(finally);
+                }
+                ty
+            }
+            Expr::PrefixExpr { op, right } => {
+                let right = self.expr(right);
+                match op {
+                    Prefix::Minus => {
+                        self.expect(&Type::Int, &right);
+                        Type::Int
+                    }
+                    Prefix::Bang => Type::Bool,
+                }
+            }
+            Expr::Match { value, arms } => {
+                let value = self.expr(value);
+                let mut ty: Option<Type> = None;
+                for arm in arms.iter() {
+                    self.scopes.push(HashMap::new());
+                    self.pattern(&arm.pattern, &value);
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard);
+                    }
+                    let body = self.expr(&arm.body);
+                    self.scopes.pop();
+                    ty = Some(match ty {
+                        Some(ty) => self.join(&ty, &body),
+                        None => body,
+                    });
+                }
+                ty.unwrap_or(Type::Any)
+            }
+            Expr::Propagate { value } => {
+                // error values have no type of their own, so neither does
+                // what is left once they are returned
+                self.expr(value);
+                Type::Any
+            }
+            // a macro runs on code before the program does. its body is
+            // still checked, with its parameters standing for any code.
+            Expr::Macro { params, body } => {
+                let scope = params
+                    .iter()
+                    .map(|name| (name.clone(), Scheme::mono(Type::Any)))
+                    .collect();
+                self.scopes.push(scope);
+                self.returns.push(Type::Any);
+                self.stmt(body);
+                self.returns.pop();
+                self.scopes.pop();
+                Type::Any
+            }
+            Expr::InfixExpr { left, right, op } => {
+                let left = self.expr(left);
+                let right = self.expr(right);
+                self.infix(op, &left, &right)
+            }
+            Expr::Func {
+                params,
+                param_types,
+                ret,
+                body,
+            } => {
+                self.scopes.push(HashMap::new());
+                let mut types = Vec::new();
+                for (i, param) in params.iter().enumerate() {
+                    let ty = match param_types.get(i) {
+                        Some(Some(ty)) => ty.into(),
+                        _ => self.fresh(),
+                    };
+                    self.pattern(param, &ty);
+                    types.push(ty);
+                }
+                let ret = match ret {
+                    Some(ty) => ty.into(),
+                    None => self.fresh(),
+                };
+                self.returns.push(ret.clone());
+                let found = self.stmt(body);
+                self.expect(&ret, &found);
+                self.returns.pop();
+                self.scopes.pop();
+                Type::Func(types, Box::new(ret))
+            }
+            Expr::Call { callee, args } => match callee.as_ref() {
+                Expr::Ident(name)
+                    if self.lookup(name).is_none() && BUILTINS.contains(&name.as_str()) =>
+                {
+                    let args: Vec<Type> = args.iter().map(|arg| self.expr(arg)).collect();
+                    self.builtin(name, &args)
+                }
+                _ => {
+                    let callee = self.expr(callee);
+                    let args: Vec<Type> = args.iter().map(|arg| self.expr(arg)).collect();
+                    self.call(&callee, &args)
+                }
+            },
+            Expr::Array { elements } => {
+                let mut element = None;
+                for e in elements.iter() {
+                    let ty = self.expr(e);
+                    element = Some(match element {
+                        Some(element) => self.join(&element, &ty),
+                        None => ty,
+                    });
+                }
+                let element = element.unwrap_or_else(|| self.fresh());
+                Type::Array(Box::new(element))
+            }
+            Expr::IndexExpr { left, index } => {
+                let left = self.expr(left);
+                let index = self.expr(index);
+                match self.shallow(&left) {
+                    Type::Array(element) => {
+                        self.expect(&Type::Int, &index);
+                        *element
+                    }
+                    Type::Hash(key, value) => {
+                        self.expect(&key, &index);
+                        *value
+                    }
+                    Type::String => {
+                        self.expect(&Type::Int, &index);
+                        Type::String
+                    }
+                    Type::Var(_) | Type::Any => Type::Any,
+                    ty => {
+                        let ty = self.resolve(&ty);
+                        self.error(TypeErrorKind::NotIndexable(ty));
+                        Type::Any
+                    }
+                }
+            }
+            Expr::Slice {
+                left,
+                start,
+                end,
+                step,
+            } => {
+                let left = self.expr(left);
+                for bound in [start, end, step].into_iter().flatten() {
+                    let bound = self.expr(bound);
+                    self.expect(&Type::Int, &bound);
+                }
+                match self.shallow(&left) {
+                    ty @ (Type::Array(_) | Type::String) => ty,
+                    Type::Var(_) | Type::Any => Type::Any,
+                    ty => {
+                        let ty = self.resolve(&ty);
+                        self.error(TypeErrorKind::NotIndexable(ty));
+                        Type::Any
+                    }
+                }
+            }
+            Expr::HashLiteral { pairs } => {
+                self.next_block += 1;
+                let mut types: Option<(Type, Type)> = None;
+                for (key, value) in pairs.iter() {
+                    let key = self.expr(key);
+                    let value = self.expr(value);
+                    types = Some(match types {
+                        Some((k, v)) => (self.join(&k, &key), self.join(&v, &value)),
+                        None => (key, value),
+                    });
+                }
+                let (key, value) = types.unwrap_or_else(|| (self.fresh(), self.fresh()));
+                Type::Hash(Box::new(key), Box::new(value))
+            }
+        }
+    }
+
+    fn infix(&mut self, op: &Infix, left: &Type, right: &Type) -> Type {
+        match op {
+            Infix::Plus => {
+                self.expect(left, right);
+                self.int_or_string("+", left);
+                left.clone()
+            }
+            // repetition
+            Infix::Asterisk if self.resolve(left) == Type::String => {
+                self.expect(&Type::Int, right);
+                Type::String
+            }
+            Infix::Minus | Infix::Asterisk | Infix::Slash => {
+                self.expect(&Type::Int, left);
+                self.expect(&Type::Int, right);
+                Type::Int
+            }
+            Infix::Lt | Infix::Gt => {
+                self.expect(left, right);
+                self.int_or_string(if *op == Infix::Lt { "<" } else { ">" }, left);
+                Type::Bool
+            }
+            Infix::Eq | Infix::NotEq => {
+                self.expect(left, right);
+                Type::Bool
+            }
+        }
+    }
+
+    /// check the operand of an operator defined on integers and strings
+    fn int_or_string(&mut self, op: &'static str, ty: &Type) {
+        match self.resolve(ty) {
+            Type::Int | Type::String | Type::Any => {}
+            ty @ Type::Var(_) => self.deferred.push((Deferred::Operand(op), ty, self.span)),
+            ty => self.error(TypeErrorKind::Operand { op, ty }),
+        }
+    }
+
+    fn call(&mut self, callee: &Type, args: &[Type]) -> Type {
+        match self.shallow(callee) {
+            Type::Func(params, ret) => {
+                if params.len() != args.len() {
+                    self.error(TypeErrorKind::ArgCount {
+                        expected: params.len(),
+                        found: args.len(),
+                    });
+                }
+                for (param, arg) in params.iter().zip(args.iter()) {
+                    self.expect(param, arg);
+                }
+                *ret
+            }
+            callee @ Type::Var(_) => {
+                let ret = self.fresh();
+                let func = Type::Func(args.to_vec(), Box::new(ret.clone()));
+                self.expect(&callee, &func);
+                ret
+            }
+            Type::Any => Type::Any,
+            ty => {
+                let ty = self.resolve(&ty);
+                self.error(TypeErrorKind::NotCallable(ty));
+                Type::Any
+            }
+        }
+    }
+
+    fn builtin(&mut self, name: &str, args: &[Type]) -> Type {
+        let arity = match name {
+            "puts" => return Type::Null,
+            "push" => 2,
+            _ => 1,
+        };
+        if args.len() != arity {
+            self.error(TypeErrorKind::ArgCount {
+                expected: arity,
+                found: args.len(),
+            });
+            return Type::Any;
+        }
+
+        let arg = self.shallow(&args[0]);
+        if name == "len" {
+            match arg {
+                Type::String | Type::Array(_) | Type::Any => {}
+                ty @ Type::Var(_) => self.deferred.push((Deferred::Len, ty, self.span)),
+                ty => {
+                    let found = self.resolve(&ty);
+                    self.error(TypeErrorKind::Builtin {
+                        name: "len",
+                        expected: "a string or an array",
+                        found,
+                    });
+                }
+            }
+            return Type::Int;
+        }
+
+        let element = match arg {
+            Type::Array(element) => *element,
+            Type::Any => return Type::Any,
+            ty @ Type::Var(_) => {
+                let element = self.fresh();
+                self.expect(&Type::Array(Box::new(element.clone())), &ty);
+                element
+            }
+            ty => {
+                let found = self.resolve(&ty);
+                let name = BUILTINS.into_iter().find(|b| *b == name).unwrap();
+                self.error(TypeErrorKind::Builtin {
+                    name,
+                    expected: "an array",
+                    found,
+                });
+                return Type::Any;
+            }
+        };
+        match name {
+            "first" | "last" => element,
+            "rest" => Type::Array(Box::new(element)),
+            _ => {
+                let element = self.join(&element, &args[1]);
+                Type::Array(Box::new(element))
+            }
+        }
+    }
+}
+
+/// `ty` with the variables in `vars` replaced
+fn substitute(ty: &Type, vars: &HashMap<TypeVar, Type>) -> Type {
+    match ty {
+        Type::Var(v) => vars.get(v).cloned().unwrap_or_else(|| ty.clone()),
+        Type::Array(element) => Type::Array(Box::new(substitute(element, vars))),
+        Type::Hash(key, value) => Type::Hash(
+            Box::new(substitute(key, vars)),
+            Box::new(substitute(value, vars)),
+        ),
+        Type::Func(params, ret) => Type::Func(
+            params.iter().map(|p| substitute(p, vars)).collect(),
+            Box::new(substitute(ret, vars)),
+        ),
+        Type::Int | Type::Bool | Type::String | Type::Null | Type::Any => ty.clone(),
+    }
+}
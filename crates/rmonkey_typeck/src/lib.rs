@@ -0,0 +1,239 @@
+//! Static type checking.
+//!
+//! [`check`] infers the type of every expression Hindley–Milner style and
+//! reports the ones that would fail at run time with a type error. Type
+//! annotations are optional: a missing annotation is inferred, and `any`
+//! opts a value out of checking. Mixed arrays and hashes, `if`s without an
+//! `else` and branches of different types are typed `any` instead of being
+//! rejected, since the language allows them.
+
+use std::fmt;
+
+use rmonkey_ast::{span::Span, Program};
+
+mod infer;
+pub mod types;
+
+pub use types::Type;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeErrorKind {
+    Mismatch {
+        expected: Type,
+        found: Type,
+    },
+    ArgCount {
+        expected: usize,
+        found: usize,
+    },
+    NotCallable(Type),
+    NotIndexable(Type),
+    Operand {
+        op: &'static str,
+        ty: Type,
+    },
+    Builtin {
+        name: &'static str,
+        expected: &'static str,
+        found: Type,
+    },
+}
+
+impl fmt::Display for TypeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeErrorKind::Mismatch { expected, found } => {
+                write!(f, "type mismatch: expected {expected}, found {found}")
+            }
+            TypeErrorKind::ArgCount { expected, found } => {
+                write!(
+                    f,
+                    "wrong number of arguments: expected {expected}, found {found}"
+                )
+            }
+            TypeErrorKind::NotCallable(ty) => write!(f, "{ty} is not a function"),
+            TypeErrorKind::NotIndexable(ty) => write!(f, "{ty} cannot be indexed"),
+            TypeErrorKind::Operand { op, ty } => {
+                write!(f, "operator {op} is not defined for {ty}")
+            }
+            TypeErrorKind::Builtin {
+                name,
+                expected,
+                found,
+            } => write!(f, "{name} expects {expected}, found {found}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    /// span of the innermost statement containing the error. empty when the
+    /// program was not produced from source text.
+    pub span: Span,
+    pub kind: TypeErrorKind,
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+/// type check `program`, returning the errors in the order they were found
+pub fn check(program: &Program) -> Vec<TypeError> {
+    infer::Checker::new(program).check()
+}
+
+#[cfg(test)]
+mod tests {
+    use rmonkey_lexer::Lexer;
+    use rmonkey_parser::Parser;
+
+    use super::*;
+
+    fn errors(input: &str) -> Vec<String> {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        check(&program).iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn test_well_typed() {
+        let inputs = [
+            "let x: int = 1; x + 2;",
+            r#"let s = "a" + "b"; len(s);"#,
+            "let add = fn(a: int, b: int) -> int { a + b }; add(1, 2);",
+            "let id = fn(x) { x }; id(1); id(true); id(\"s\");",
+            "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(10);",
+            "let twice = fn(f, x) { f(f(x)) }; twice(fn(x) { x + 2 }, 2);",
+            "let xs = [1, \"a\", true]; xs[0];",
+            "let h = {\"a\": 1, \"b\": 2}; h[\"a\"] + 1;",
+            "let f = fn(x) { if (x) { 1 } }; f(true);",
+            "let f = fn(x) { if (x) { return 1; } 2 }; f(false) + 1;",
+            "let f = fn() { return 1; \"a\" }; f() + 1;",
+            "let f = fn(x) { throw x; \"a\" }; f(1) + 1;",
+            "let x: any = 1; len(x);",
+            "let map = fn(xs, f) { if (len(xs) == 0) { [] } else { push(map(rest(xs), f), f(first(xs))) } };",
+            "let a = push([1, 2], 3); first(a) + last(a);",
+            "puts(1, \"a\");",
+            "!5;",
+            "let r = try { throw \"a\"; } catch (e) { len(e[\"message\"]) }; r + 1;",
+            "let f = fn(x) { if (x < 0) { throw x; } x }; f(1) + 1;",
+            "let n = match ([1, 2]) { [a, ...r] => a + len(r), _ => 0 }; n + 1;",
+            "match (1) { [a] => a, \"s\" => 1, n => n + 1 };",
+            "let [a, ...r] = [1, 2]; a + len(r);",
+            "let unless = macro(c, a, b) { quote(if (!(unquote(c))) { unquote(a) } else { unquote(b) }) }; unless(true, 1, 2);",
+            "let f = fn([a, b], {\"k\": v}) { a + b + v }; f([1, 2], {\"k\": 3}) + 1;",
+            r#"let s = "ab" * 2; if (s < "b") { s[0] + "c" } else { "" };"#,
+            "let lt = fn(a, b) { a < b }; lt(1, 2);",
+            r#"let xs = [1, 2, 3][1:]; xs[-1] + len("abc"[::-1]);"#,
+        ];
+        for input in inputs {
+            assert_eq!(errors(input), Vec::<String>::new(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let tests = [
+            ("len(5);", "len expects a string or an array, found int"),
+            (
+                "let x: int = \"a\";",
+                "type mismatch: expected int, found string",
+            ),
+            (
+                "let f = fn(a: string, b: [int]) -> bool { a == \"\" }; f(\"a\", [true]);",
+                "type mismatch: expected [int], found [bool]",
+            ),
+            (
+                "let f = fn(x) -> int { x }; f(true);",
+                "type mismatch: expected int, found bool",
+            ),
+            (
+                "let add = fn(a, b) { a + b }; add(1);",
+                "wrong number of arguments: expected 2, found 1",
+            ),
+            ("1 + true;", "type mismatch: expected int, found bool"),
+            ("true + false;", "operator + is not defined for bool"),
+            ("-\"a\";", "type mismatch: expected int, found string"),
+            ("1 < \"a\";", "type mismatch: expected int, found string"),
+            ("true < false;", "operator < is not defined for bool"),
+            (
+                "\"a\" * \"b\";",
+                "type mismatch: expected int, found string",
+            ),
+            ("5(1);", "int is not a function"),
+            ("let a = 1; a[0];", "int cannot be indexed"),
+            ("let a = 1; a[1:];", "int cannot be indexed"),
+            ("[1][\"a\":];", "type mismatch: expected int, found string"),
+            (
+                "[1, 2][\"a\"];",
+                "type mismatch: expected int, found string",
+            ),
+            ("first(1);", "first expects an array, found int"),
+            (
+                "len(1, 2);",
+                "wrong number of arguments: expected 1, found 2",
+            ),
+            (
+                "let f = fn(x) { len(x) }; f(1);",
+                "len expects a string or an array, found int",
+            ),
+            (
+                "let f = fn(x) { x + x }; f(true);",
+                "operator + is not defined for bool",
+            ),
+            (
+                "let f = fn(x) { x(1) }; f(2);",
+                "type mismatch: expected fn(int) -> 'a, found int",
+            ),
+            (
+                "let f = fn(x) { return 1; }; f(1) == \"a\";",
+                "type mismatch: expected int, found string",
+            ),
+            (
+                "let f = fn() { return 1; len(1) }; f();",
+                "len expects a string or an array, found int",
+            ),
+            (
+                "let r = try { 1 } catch (e) { 2 }; r + \"a\";",
+                "type mismatch: expected int, found string",
+            ),
+            (
+                "try { 1 } finally { len(1) };",
+                "len expects a string or an array, found int",
+            ),
+            (
+                "match (1) { n => len(n) };",
+                "len expects a string or an array, found int",
+            ),
+            (
+                "let r = match (1) { 0 => 1, _ => 2 }; r + \"a\";",
+                "type mismatch: expected int, found string",
+            ),
+            (
+                "let [a, b] = [1, 2]; a + \"s\";",
+                "type mismatch: expected int, found string",
+            ),
+            (
+                "let [a]: [string] = [1];",
+                "type mismatch: expected [string], found [int]",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(errors(input), vec![expected.to_string()], "{input}");
+        }
+    }
+
+    #[test]
+    fn test_error_span() {
+        let input = "let f = fn(x) {\n  let y = 1;\n  len(y)\n};";
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        let errors = check(&program);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(&input[errors[0].span.start..errors[0].span.end], "len(y)");
+    }
+}
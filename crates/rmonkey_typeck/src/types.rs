@@ -0,0 +1,153 @@
+use std::fmt;
+
+use rmonkey_ast::ty::TypeExpr;
+
+pub type TypeVar = usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Bool,
+    String,
+    Null,
+    /// the type of values the checker knows nothing about. it is compatible
+    /// with every type, so code using it is only checked at runtime.
+    Any,
+    Array(Box<Type>),
+    Hash(Box<Type>, Box<Type>),
+    Func(Vec<Type>, Box<Type>),
+    /// a type yet to be inferred
+    Var(TypeVar),
+}
+
+impl Type {
+    /// the type variables in `self`, in order of first appearance
+    pub fn vars(&self) -> Vec<TypeVar> {
+        let mut vars = Vec::new();
+        self.collect_vars(&mut vars);
+        vars
+    }
+
+    fn collect_vars(&self, vars: &mut Vec<TypeVar>) {
+        match self {
+            Type::Var(v) => {
+                if !vars.contains(v) {
+                    vars.push(*v);
+                }
+            }
+            Type::Array(element) => element.collect_vars(vars),
+            Type::Hash(key, value) => {
+                key.collect_vars(vars);
+                value.collect_vars(vars);
+            }
+            Type::Func(params, ret) => {
+                for param in params.iter() {
+                    param.collect_vars(vars);
+                }
+                ret.collect_vars(vars);
+            }
+            Type::Int | Type::Bool | Type::String | Type::Null | Type::Any => {}
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, vars: &[TypeVar]) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+            Type::String => write!(f, "string"),
+            Type::Null => write!(f, "null"),
+            Type::Any => write!(f, "any"),
+            Type::Array(element) => {
+                write!(f, "[")?;
+                element.write(f, vars)?;
+                write!(f, "]")
+            }
+            Type::Hash(key, value) => {
+                write!(f, "{{")?;
+                key.write(f, vars)?;
+                write!(f, ": ")?;
+                value.write(f, vars)?;
+                write!(f, "}}")
+            }
+            Type::Func(params, ret) => {
+                write!(f, "fn(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    param.write(f, vars)?;
+                }
+                write!(f, ") -> ")?;
+                ret.write(f, vars)
+            }
+            Type::Var(v) => {
+                // named 'a, 'b, ... in order of appearance
+                let i = vars.iter().position(|var| var == v).unwrap_or(0);
+                let letter = (b'a' + (i % 26) as u8) as char;
+                match i / 26 {
+                    0 => write!(f, "'{letter}"),
+                    n => write!(f, "'{letter}{n}"),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, &self.vars())
+    }
+}
+
+impl From<&TypeExpr> for Type {
+    fn from(ty: &TypeExpr) -> Self {
+        match ty {
+            TypeExpr::Int => Type::Int,
+            TypeExpr::Bool => Type::Bool,
+            TypeExpr::String => Type::String,
+            TypeExpr::Null => Type::Null,
+            TypeExpr::Any => Type::Any,
+            TypeExpr::Array { element } => Type::Array(Box::new(element.as_ref().into())),
+            TypeExpr::Hash { key, value } => Type::Hash(
+                Box::new(key.as_ref().into()),
+                Box::new(value.as_ref().into()),
+            ),
+            TypeExpr::Func { params, ret } => Type::Func(
+                params.iter().map(Type::from).collect(),
+                Box::new(ret.as_ref().into()),
+            ),
+        }
+    }
+}
+
+/// a type that may be used at different types for its variables, like the
+/// `fn('a) -> 'a` of `fn(x) { x }`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheme {
+    pub vars: Vec<TypeVar>,
+    pub ty: Type,
+}
+
+impl Scheme {
+    /// a scheme without variables of its own
+    pub fn mono(ty: Type) -> Self {
+        Scheme {
+            vars: Vec::new(),
+            ty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let ty = Type::Func(
+            vec![Type::Var(7), Type::Array(Box::new(Type::Var(3)))],
+            Box::new(Type::Hash(Box::new(Type::String), Box::new(Type::Var(7)))),
+        );
+        assert_eq!(ty.to_string(), "fn('a, ['b]) -> {string: 'a}");
+    }
+}
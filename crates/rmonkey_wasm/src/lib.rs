@@ -1,8 +1,9 @@
 mod utils;
 
-use std::panic;
+use std::{collections::HashMap, panic};
 
-use rmonkey_evaluator::Evaluator;
+use rmonkey_ast::json;
+use rmonkey_evaluator::{Evaluator, MemoryHost};
 use rmonkey_fmt::Formatter;
 use rmonkey_lexer::Lexer;
 use rmonkey_parser::Parser;
@@ -28,7 +29,7 @@ pub fn code_to_ast(code: &str) -> String {
     let mut p = Parser::new(l);
     let program = p.parse_program().map_err(|e| format!("{e}"));
     match program {
-        Ok(ast) => match serde_json::to_string(&ast) {
+        Ok(ast) => match json::to_json(&ast) {
             Ok(ast_json) => ast_json,
             Err(err) => err.to_string(),
         },
@@ -49,6 +50,9 @@ pub fn fmt(code: &str) -> Result<String, JsValue> {
     }
 }
 
+/// evaluate `code`. wasm has no source of randomness, so the `random`
+/// builtins give the same numbers each time the module is loaded; use
+/// [`eval_seeded`] with a seed from JavaScript to vary them.
 #[wasm_bindgen]
 pub fn eval_rmonkey(code: &str) -> String {
     let mut e = Evaluator::new();
@@ -63,3 +67,66 @@ pub fn eval_rmonkey(code: &str) -> String {
         Err(err) => err.to_string(),
     }
 }
+
+/// evaluate `code` with the `random` builtins seeded by `seed`, so that the
+/// same code and seed always give the same result
+#[wasm_bindgen]
+pub fn eval_seeded(code: &str, seed: u64) -> String {
+    let mut e = Evaluator::new();
+    e.seed_random(seed);
+    let l = Lexer::new(code);
+    let mut p = Parser::new(l);
+    let program = p.parse_program();
+    match program {
+        Ok(program) => match e.eval(program) {
+            Ok(result) => format!("{result}"),
+            Err(err) => format!("{err}"),
+        },
+        Err(err) => err.to_string(),
+    }
+}
+
+/// evaluate `code` with the modules of `modules_json`, an object from module
+/// paths like `"lib/math.monkey"` to their sources
+#[wasm_bindgen]
+pub fn eval_with_modules(code: &str, modules_json: &str) -> String {
+    let modules: HashMap<String, String> = match serde_json::from_str(modules_json) {
+        Ok(modules) => modules,
+        Err(err) => return err.to_string(),
+    };
+    let mut e = Evaluator::with_host(MemoryHost::new(modules));
+    let l = Lexer::new(code);
+    let mut p = Parser::new(l);
+    let program = p.parse_program();
+    match program {
+        Ok(program) => match e.eval(program) {
+            Ok(result) => format!("{result}"),
+            Err(err) => format!("{err}"),
+        },
+        Err(err) => err.to_string(),
+    }
+}
+
+/// evaluate a program given in the JSON schema of `rmonkey_ast::json`
+#[wasm_bindgen]
+pub fn eval_ast(ast_json: &str) -> String {
+    let mut e = Evaluator::new();
+    match json::from_json(ast_json) {
+        Ok(program) => match e.eval(program) {
+            Ok(result) => format!("{result}"),
+            Err(err) => format!("{err}"),
+        },
+        Err(err) => err.to_string(),
+    }
+}
+
+/// format a program given in the JSON schema of `rmonkey_ast::json`
+#[wasm_bindgen]
+pub fn fmt_ast(ast_json: &str) -> Result<String, JsValue> {
+    set_panic_hook();
+    let mut formatter = Formatter::default();
+    match json::from_json(ast_json) {
+        Ok(program) => Ok(formatter.fmt(program)),
+        Err(err) => Err(JsValue::from(err.to_string())),
+    }
+}
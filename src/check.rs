@@ -0,0 +1,93 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use rmonkey_lexer::Lexer;
+use rmonkey_parser::Parser;
+
+use crate::{fmt::collect_files, lint::line_col};
+
+const USAGE: &str = "usage: rmonkey check [<path>...]
+
+Type check .monkey files. Directories are searched recursively.
+With no path or `-`, read from stdin.";
+
+pub fn run(args: &[String]) -> ExitCode {
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown option: {flag}\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+
+    let stdin_mode = paths.is_empty() || paths.iter().any(|p| p == Path::new("-"));
+    let result = if stdin_mode {
+        check_stdin()
+    } else {
+        check_paths(&paths)
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// print the type errors of `code` and return whether there were none
+fn check(name: &str, code: &str) -> Result<bool, String> {
+    let mut parser = Parser::new(Lexer::new(code));
+    let program = parser.parse_program().map_err(|err| err.to_string())?;
+    let errors = rmonkey_typeck::check(&program);
+    for err in errors.iter() {
+        let (line, col) = line_col(code, err.span.start);
+        println!("{name}:{line}:{col}: error: {err}");
+    }
+    Ok(errors.is_empty())
+}
+
+fn check_stdin() -> Result<bool, String> {
+    let mut code = String::new();
+    io::stdin()
+        .read_to_string(&mut code)
+        .map_err(|err| format!("failed to read stdin: {err}"))?;
+    check("<stdin>", &code).map_err(|err| format!("<stdin>: {err}"))
+}
+
+/// returns `Ok(false)` when a type error was found or any file failed to
+/// check
+fn check_paths(paths: &[PathBuf]) -> Result<bool, String> {
+    let mut files = Vec::new();
+    for path in paths.iter() {
+        collect_files(path, &mut files).map_err(|err| format!("{}: {err}", path.display()))?;
+    }
+
+    let mut ok = true;
+    for file in files.iter() {
+        let name = file.display().to_string();
+        let result = fs::read_to_string(file)
+            .map_err(|err| err.to_string())
+            .and_then(|code| check(&name, &code));
+        match result {
+            Ok(clean) => ok &= clean,
+            Err(err) => {
+                eprintln!("{name}: {err}");
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
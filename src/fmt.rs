@@ -0,0 +1,171 @@
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use rmonkey_fmt::{options::FormatOptions, Formatter};
+use similar::TextDiff;
+
+const USAGE: &str = "usage: rmonkey fmt [--check] [--diff] [<path>...]
+
+Format .monkey files in place. Directories are searched recursively.
+With no path or a single `-`, read from stdin and write to stdout.
+
+options:
+  --check  don't write anything, exit with 1 if a file is not formatted
+  --diff   don't write anything, print a unified diff of the changes";
+
+#[derive(Debug, Default)]
+struct Args {
+    check: bool,
+    diff: bool,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    for arg in args {
+        match arg.as_str() {
+            "--check" => parsed.check = true,
+            "--diff" => parsed.diff = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            flag if flag.starts_with("--") => {
+                return Err(format!("unknown option: {flag}\n\n{USAGE}"))
+            }
+            path => parsed.paths.push(PathBuf::from(path)),
+        }
+    }
+    let stdin = parsed.paths.iter().any(|p| p == Path::new("-"));
+    if stdin && parsed.paths.len() > 1 {
+        return Err(format!("`-` can't be mixed with other paths\n\n{USAGE}"));
+    }
+    Ok(parsed)
+}
+
+pub fn run(args: &[String]) -> ExitCode {
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{msg}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let stdin_mode = args.paths.is_empty() || args.paths.iter().any(|p| p == Path::new("-"));
+    let result = if stdin_mode {
+        fmt_stdin(&args)
+    } else {
+        fmt_paths(&args)
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// format `code`. files always end with a single newline.
+fn format(code: &str, dir: &Path) -> Result<String, String> {
+    let options = FormatOptions::load(dir).map_err(|err| err.to_string())?;
+    let mut formatter = Formatter::new(options);
+    let formatted = formatter.fmt_source(code).map_err(|err| err.to_string())?;
+    Ok(format!("{formatted}\n"))
+}
+
+fn fmt_stdin(args: &Args) -> Result<bool, String> {
+    let mut code = String::new();
+    io::stdin()
+        .read_to_string(&mut code)
+        .map_err(|err| format!("failed to read stdin: {err}"))?;
+    let cwd = env::current_dir().map_err(|err| err.to_string())?;
+    let formatted = format(&code, &cwd).map_err(|err| format!("<stdin>: {err}"))?;
+
+    if args.diff {
+        print_diff("<stdin>", &code, &formatted);
+    } else if !args.check {
+        io::stdout()
+            .write_all(formatted.as_bytes())
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(code == formatted || !(args.check || args.diff))
+}
+
+/// returns `Ok(false)` when `--check` or `--diff` found an unformatted file
+/// or any file failed to format.
+fn fmt_paths(args: &Args) -> Result<bool, String> {
+    let mut files = Vec::new();
+    for path in args.paths.iter() {
+        collect_files(path, &mut files).map_err(|err| format!("{}: {err}", path.display()))?;
+    }
+
+    let mut ok = true;
+    for file in files.iter() {
+        match fmt_file(file, args) {
+            Ok(formatted) => ok &= formatted || !(args.check || args.diff),
+            Err(err) => {
+                eprintln!("{}: {err}", file.display());
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
+
+/// returns whether the file was already formatted
+fn fmt_file(file: &Path, args: &Args) -> Result<bool, String> {
+    let code = fs::read_to_string(file).map_err(|err| err.to_string())?;
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let formatted = format(&code, dir)?;
+    if code == formatted {
+        return Ok(true);
+    }
+
+    if args.diff {
+        print_diff(&file.display().to_string(), &code, &formatted);
+    } else if args.check {
+        println!("{} is not formatted", file.display());
+    } else {
+        fs::write(file, formatted).map_err(|err| err.to_string())?;
+    }
+    Ok(false)
+}
+
+pub(crate) fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    entries.sort();
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if entry.is_dir() {
+            collect_files(&entry, files)?;
+        } else if entry.extension().is_some_and(|ext| ext == "monkey") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+fn print_diff(name: &str, old: &str, new: &str) {
+    let diff = TextDiff::from_lines(old, new);
+    print!(
+        "{}",
+        diff.unified_diff()
+            .header(&format!("a/{name}"), &format!("b/{name}"))
+    );
+}
@@ -0,0 +1,159 @@
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use rmonkey_lint::{Level, LintConfig};
+
+use crate::fmt::collect_files;
+
+const USAGE: &str = "usage: rmonkey lint [--fix] [<path>...]
+
+Check .monkey files for likely mistakes. Directories are searched recursively.
+With no path or `-`, read from stdin.
+
+Rules are configured in the [lint] table of rmonkey.toml, and suppressed with
+a `// rmonkey: allow(<rule>, ...)` comment on the line or the line above.
+
+options:
+  --fix  apply the available fixes. files are changed in place, stdin is
+         written to stdout";
+
+#[derive(Debug, Default)]
+struct Args {
+    fix: bool,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    for arg in args {
+        match arg.as_str() {
+            "--fix" => parsed.fix = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            flag if flag.starts_with("--") => {
+                return Err(format!("unknown option: {flag}\n\n{USAGE}"))
+            }
+            path => parsed.paths.push(PathBuf::from(path)),
+        }
+    }
+    Ok(parsed)
+}
+
+pub fn run(args: &[String]) -> ExitCode {
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{msg}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let stdin_mode = args.paths.is_empty() || args.paths.iter().any(|p| p == Path::new("-"));
+    let result = if stdin_mode {
+        lint_stdin(&args)
+    } else {
+        lint_paths(&args)
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// lint `code`, after applying the fixes with `--fix`. prints the remaining
+/// diagnostics to `out` and returns the code with the fixes applied, and
+/// whether no error was found.
+fn lint(
+    name: &str,
+    code: &str,
+    dir: &Path,
+    args: &Args,
+    out: &mut dyn Write,
+) -> Result<(String, bool), String> {
+    let config = LintConfig::load(dir).map_err(|err| err.to_string())?;
+    let code = if args.fix {
+        rmonkey_lint::fix(code, &config).map_err(|err| err.to_string())?
+    } else {
+        code.to_string()
+    };
+
+    let diagnostics = rmonkey_lint::lint(&code, &config).map_err(|err| err.to_string())?;
+    for d in diagnostics.iter() {
+        let (line, col) = line_col(&code, d.span.start);
+        let (level, rule, message) = (d.level, d.rule, &d.message);
+        writeln!(out, "{name}:{line}:{col}: {level}[{rule}]: {message}")
+            .map_err(|err| err.to_string())?;
+    }
+    let ok = diagnostics.iter().all(|d| d.level != Level::Error);
+    Ok((code, ok))
+}
+
+fn lint_stdin(args: &Args) -> Result<bool, String> {
+    let mut code = String::new();
+    io::stdin()
+        .read_to_string(&mut code)
+        .map_err(|err| format!("failed to read stdin: {err}"))?;
+    let cwd = env::current_dir().map_err(|err| err.to_string())?;
+    // with `--fix` the fixed code goes to stdout, so the diagnostics go to
+    // stderr
+    let (fixed, ok) = if args.fix {
+        lint("<stdin>", &code, &cwd, args, &mut io::stderr())
+    } else {
+        lint("<stdin>", &code, &cwd, args, &mut io::stdout())
+    }
+    .map_err(|err| format!("<stdin>: {err}"))?;
+
+    if args.fix {
+        io::stdout()
+            .write_all(fixed.as_bytes())
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(ok)
+}
+
+/// returns `Ok(false)` when an error was found or any file failed to lint
+fn lint_paths(args: &Args) -> Result<bool, String> {
+    let mut files = Vec::new();
+    for path in args.paths.iter() {
+        collect_files(path, &mut files).map_err(|err| format!("{}: {err}", path.display()))?;
+    }
+
+    let mut ok = true;
+    for file in files.iter() {
+        match lint_file(file, args) {
+            Ok(clean) => ok &= clean,
+            Err(err) => {
+                eprintln!("{}: {err}", file.display());
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
+
+fn lint_file(file: &Path, args: &Args) -> Result<bool, String> {
+    let code = fs::read_to_string(file).map_err(|err| err.to_string())?;
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let name = file.display().to_string();
+    let (fixed, ok) = lint(&name, &code, dir, args, &mut io::stdout())?;
+    if fixed != code {
+        fs::write(file, fixed).map_err(|err| err.to_string())?;
+    }
+    Ok(ok)
+}
+
+/// 1-based line and column of `offset`
+pub(crate) fn line_col(code: &str, offset: usize) -> (usize, usize) {
+    let before = &code[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    let col = before[line_start..].chars().count() + 1;
+    (line, col)
+}
@@ -1,13 +1,35 @@
-use std::io::{self, Write};
+use std::{
+    env,
+    io::{self, Write},
+    process::ExitCode,
+};
 
-use rmonkey_evaluator::Evaluator;
+use rmonkey_evaluator::{Evaluator, FsHost};
 use rmonkey_lexer::Lexer;
 use rmonkey_parser::Parser;
 
-fn main() {
+mod check;
+mod fmt;
+mod lint;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("check") => check::run(&args[1..]),
+        Some("fmt") => fmt::run(&args[1..]),
+        Some("lint") => lint::run(&args[1..]),
+        Some(cmd) => {
+            eprintln!("unknown command: {cmd}\n\nusage: rmonkey [check|fmt|lint]");
+            ExitCode::FAILURE
+        }
+        None => repl(),
+    }
+}
+
+fn repl() -> ExitCode {
     println!("Welcome to Monkey");
 
-    let mut e = Evaluator::new();
+    let mut e = Evaluator::with_host(FsHost::from_env());
     loop {
         let mut buffer = String::new();
         let stdin = io::stdin();
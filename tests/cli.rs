@@ -0,0 +1,195 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+const UNFORMATTED: &str = "let add=fn(x,y){x+y};\nadd(1,2)";
+const FORMATTED: &str = "let add = fn(x, y) {\n  x + y;\n};\nadd(1, 2);\n";
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rmonkey_cli_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    dir
+}
+
+fn rmonkey(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rmonkey"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn fmt_in_place() {
+    let dir = temp_dir("in_place");
+    let file = dir.join("nested/add.monkey");
+    let other = dir.join("nested/notes.txt");
+    fs::write(&file, UNFORMATTED).unwrap();
+    fs::write(&other, UNFORMATTED).unwrap();
+
+    let output = rmonkey(&["fmt", dir.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&file).unwrap(), FORMATTED);
+    assert_eq!(fs::read_to_string(&other).unwrap(), UNFORMATTED);
+}
+
+#[test]
+fn fmt_check() {
+    let dir = temp_dir("check");
+    let file = dir.join("add.monkey");
+    fs::write(&file, UNFORMATTED).unwrap();
+
+    let output = rmonkey(&["fmt", "--check", file.to_str().unwrap()], "");
+    assert!(!output.status.success());
+    assert_eq!(fs::read_to_string(&file).unwrap(), UNFORMATTED);
+
+    fs::write(&file, FORMATTED).unwrap();
+    let output = rmonkey(&["fmt", "--check", file.to_str().unwrap()], "");
+    assert!(output.status.success());
+}
+
+#[test]
+fn fmt_diff() {
+    let output = rmonkey(&["fmt", "--diff"], UNFORMATTED);
+    assert!(!output.status.success());
+    let diff = String::from_utf8(output.stdout).unwrap();
+    assert!(diff.starts_with("--- a/<stdin>\n+++ b/<stdin>\n"), "{diff}");
+    assert!(diff.contains("-let add=fn(x,y){x+y};\n"), "{diff}");
+    assert!(diff.contains("+let add = fn(x, y) {\n"), "{diff}");
+}
+
+#[test]
+fn fmt_stdin() {
+    let output = rmonkey(&["fmt"], UNFORMATTED);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), FORMATTED);
+}
+
+#[test]
+fn fmt_stdin_with_paths() {
+    let dir = temp_dir("stdin_with_paths");
+    let file = dir.join("add.monkey");
+    fs::write(&file, UNFORMATTED).unwrap();
+
+    let output = rmonkey(&["fmt", "-", file.to_str().unwrap()], "");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with("`-` can't be mixed with other paths"),
+        "{stderr}"
+    );
+    assert_eq!(fs::read_to_string(&file).unwrap(), UNFORMATTED);
+}
+
+#[test]
+fn fmt_reads_config() {
+    let dir = temp_dir("config");
+    let file = dir.join("nested/add.monkey");
+    fs::write(&file, UNFORMATTED).unwrap();
+    fs::write(dir.join("rmonkey.toml"), "[format]\nindent_size = 4\n").unwrap();
+
+    let output = rmonkey(&["fmt", file.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        FORMATTED.replace("  x + y", "    x + y")
+    );
+}
+
+#[test]
+fn fmt_parse_error() {
+    let output = rmonkey(&["fmt"], "let = 1");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with("<stdin>: unexpected token error"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn lint_reports_diagnostics() {
+    let code = "let len = fn(x) { return x; x };\nlen(1);\nputs(y);\n";
+    let output = rmonkey(&["lint"], code);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "<stdin>:1:5: warning[shadowed_builtin]: `let len` shadows the builtin len\n\
+         <stdin>:1:29: warning[unreachable_code]: unreachable code after `return`\n\
+         <stdin>:3:6: error[undefined_name]: identifier not found: y\n"
+    );
+
+    let output = rmonkey(&["lint"], "let a = 1;\nif (a == a) { a };\n");
+    assert!(output.status.success());
+}
+
+#[test]
+fn lint_fix_in_place() {
+    let dir = temp_dir("lint_fix");
+    let file = dir.join("nested/fix.monkey");
+    fs::write(&file, "let a = (1);\nif (a) { a } else {};\n").unwrap();
+
+    let output = rmonkey(&["lint", "--fix", dir.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "");
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "let a = 1;\nif (a) { a };\n"
+    );
+}
+
+#[test]
+fn lint_reads_config() {
+    let dir = temp_dir("lint_config");
+    let file = dir.join("nested/config.monkey");
+    fs::write(&file, "let first = 1;\nfirst;\n").unwrap();
+    fs::write(
+        dir.join("rmonkey.toml"),
+        "[lint]\nshadowed_builtin = \"error\"\n",
+    )
+    .unwrap();
+
+    let output = rmonkey(&["lint", file.to_str().unwrap()], "");
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(":1:5: error[shadowed_builtin]"), "{stdout}");
+
+    fs::write(
+        dir.join("rmonkey.toml"),
+        "[lint]\nshadowed_builtin = \"off\"\n",
+    )
+    .unwrap();
+    let output = rmonkey(&["lint", file.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "");
+}
+
+#[test]
+fn check_reports_type_errors() {
+    let code = "let f = fn(s: string) -> int { len(s) };\nf(1);\nlen(5);\n";
+    let output = rmonkey(&["check"], code);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "<stdin>:2:1: error: type mismatch: expected string, found int\n\
+         <stdin>:3:1: error: len expects a string or an array, found int\n"
+    );
+
+    let dir = temp_dir("check_types");
+    let file = dir.join("nested/ok.monkey");
+    fs::write(&file, "let id = fn(x) { x };\nid(1) + len(id(\"a\"));\n").unwrap();
+    let output = rmonkey(&["check", dir.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "");
+}